@@ -1,6 +1,7 @@
 // https://github.com/ollama/ollama/blob/main/docs/api.md
 
 use std::collections::VecDeque;
+use std::time::Instant;
 
 use crate::env::LOCALAI_URL;
 use crate::{AI_CONTEXT, Error, HTTP_CLIENT, KV_DATABASE};
@@ -170,13 +171,17 @@ pub async fn get_gpt_response(
     map.messages.push_back(system_message);
 
     log::debug!("GPT Sent {:#?}", map.messages);
+    let started = Instant::now();
     let resp = HTTP_CLIENT
         .get()
         .unwrap()
         .post(format!("{}/api/chat", &*LOCALAI_URL))
         .json(&map)
         .send()
-        .await?;
+        .await
+        .inspect_err(|_| {
+            crate::metrics::record_ai_request("chat", "error", started.elapsed(), None)
+        })?;
 
     let json_string = resp.text().await?;
     // Deserialize the JSON string into a Value
@@ -186,9 +191,16 @@ pub async fn get_gpt_response(
     let model_response = match results {
         Ok(response) => {
             log::info!("GPT ModelResponse: {:#?}", response);
+            crate::metrics::record_ai_request(
+                "chat",
+                "success",
+                started.elapsed(),
+                Some((response.prompt_eval_count, response.eval_count)),
+            );
             response
         }
         Err(why) => {
+            crate::metrics::record_ai_request("chat", "error", started.elapsed(), None);
             log::warn!(
                 "GPT ModelResponse - Failed to parse {:?}: {:#?}",
                 why,
@@ -2,6 +2,7 @@ use crate::env::{FOOTER_URL, LOCALAI_URL, SERVE_STATIC_URL};
 use crate::{Context, Error, HTTP_CLIENT};
 
 use std::cmp;
+use std::time::Instant;
 
 use poise::serenity_prelude as serenity;
 use serde::{Deserialize, Serialize};
@@ -56,6 +57,7 @@ pub async fn stablediffusion(
         ),
     };
 
+    let started = Instant::now();
     let resp = HTTP_CLIENT
         .get()
         .unwrap()
@@ -63,12 +65,17 @@ pub async fn stablediffusion(
         .header("Content-Type", "application/json")
         .json(&map)
         .send()
-        .await?;
+        .await
+        .inspect_err(|_| {
+            crate::metrics::record_ai_request("image", "error", started.elapsed(), None)
+        })?;
 
     let json_string = resp.text().await?;
     log::info!("{}", json_string);
     let results: Result<SDPromptResponse, serde_json::Error> =
         serde_json::from_str(json_string.as_str());
+    let outcome = if results.is_ok() { "success" } else { "error" };
+    crate::metrics::record_ai_request("image", outcome, started.elapsed(), None);
     let response = results.unwrap();
 
     let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
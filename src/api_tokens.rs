@@ -0,0 +1,345 @@
+use crate::audit::{self, AuditEntry};
+use crate::db::Table;
+use crate::env::FOOTER_URL;
+use crate::{API_TOKENS, Context, Error, colors};
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const TOKEN_PREFIX: &str = "rbt";
+
+/// Keyed by token id
+const TOKENS: Table<ApiToken> = Table::new(API_TOKENS);
+
+// Only written when older than this, so busy scripts don't write on every request
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+/// Everything a token can be scoped to, `write` also allows reading
+pub const SCOPES: [&str; 15] = [
+    "info:read",
+    "tags:read",
+    "tags:write",
+    "tickets:read",
+    "tickets:write",
+    "streams:read",
+    "streams:write",
+    "music:read",
+    "music:write",
+    "permissions:read",
+    "permissions:write",
+    "history:read",
+    "logs:read",
+    "audit:read",
+    "metrics:read",
+];
+
+/// An API token, only the hash of the secret is kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    hash: String,
+    pub scopes: Vec<String>,
+    /// Requests act on behalf of this user, so they are also bound by their permissions
+    pub created_by: u64,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    #[serde(default)]
+    pub last_used_at: Option<i64>,
+}
+
+impl ApiToken {
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Tokens are managed by whoever created them, the bot owner can manage everyone's
+    pub fn managed_by(&self, user_id: u64) -> bool {
+        self.created_by == user_id || user_id == *crate::env::AUTHOR_ID
+    }
+
+    pub fn allows(&self, scope: &str) -> bool {
+        let write = scope
+            .strip_suffix(":read")
+            .map(|resource| format!("{}:write", resource));
+        self.scopes
+            .iter()
+            .any(|granted| granted == scope || Some(granted) == write.as_ref())
+    }
+
+    /// Everything but the hash, for listings and the audit log
+    pub fn info_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "name": self.name,
+            "scopes": self.scopes,
+            "created_by": self.created_by.to_string(),
+            "created_at": self.created_at,
+            "expires_at": self.expires_at,
+            "last_used_at": self.last_used_at,
+        })
+    }
+}
+
+fn hash_secret(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Split and validate a comma or space separated scope list
+pub fn parse_scopes(input: &[String]) -> Result<Vec<String>, String> {
+    let mut scopes = Vec::new();
+    for scope in input
+        .iter()
+        .flat_map(|scopes| scopes.split([',', ' ']))
+        .map(|scope| scope.trim().to_lowercase())
+        .filter(|scope| !scope.is_empty())
+    {
+        if !SCOPES.contains(&scope.as_str()) {
+            return Err(format!("Unknown scope: {}", scope));
+        }
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    if scopes.is_empty() {
+        return Err("At least one scope is required".to_string());
+    }
+    Ok(scopes)
+}
+
+/// Issue a token, the returned secret is shown once and can't be recovered
+pub fn create_token(
+    name: &str,
+    scopes: Vec<String>,
+    created_by: u64,
+    expires_at: Option<i64>,
+) -> Result<(ApiToken, String), Error> {
+    let id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+    let secret = format!("{}_{}_{}", TOKEN_PREFIX, id, uuid::Uuid::new_v4().simple());
+    let token = ApiToken {
+        id,
+        name: name.trim().to_string(),
+        hash: hash_secret(&secret),
+        scopes,
+        created_by,
+        created_at: chrono::Utc::now().timestamp(),
+        expires_at,
+        last_used_at: None,
+    };
+    save_token(&token)?;
+    Ok((token, secret))
+}
+
+pub fn load_token(id: &str) -> Result<Option<ApiToken>, Error> {
+    Ok(TOKENS.get(id)?)
+}
+
+fn save_token(token: &ApiToken) -> Result<(), Error> {
+    TOKENS.insert(&token.id, token)?;
+    Ok(())
+}
+
+pub fn delete_token(id: &str) -> Result<(), Error> {
+    TOKENS.remove(id)?;
+    Ok(())
+}
+
+pub fn list_tokens() -> Result<Vec<ApiToken>, Error> {
+    let mut tokens = TOKENS.values()?;
+    tokens.sort_by_key(|token| token.created_at);
+    Ok(tokens)
+}
+
+/// Resolve a bearer token, expired or unknown tokens resolve to `None`
+pub fn verify_token(secret: &str) -> Option<ApiToken> {
+    // Tokens look like `rbt_<id>_<secret>`, the id avoids hashing against every stored token
+    let mut parts = secret.trim().splitn(3, '_');
+    let (Some(TOKEN_PREFIX), Some(id), Some(_)) = (parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+    let mut token = load_token(id).ok()??;
+    let now = chrono::Utc::now().timestamp();
+    if !crate::web::auth::constant_time_eq(&token.hash, &hash_secret(secret.trim()))
+        || token.is_expired(now)
+    {
+        return None;
+    }
+
+    if token
+        .last_used_at
+        .is_none_or(|last_used_at| now - last_used_at >= LAST_USED_RESOLUTION_SECS)
+    {
+        token.last_used_at = Some(now);
+        if let Err(e) = save_token(&token) {
+            log::error!("Failed to update API token {} last use: {}", token.id, e);
+        }
+    }
+    Some(token)
+}
+
+// Slash only, prefix commands can't reply privately and would leak the token
+#[poise::command(
+    slash_command,
+    subcommands("create", "list", "revoke"),
+    subcommand_required,
+    category = "Permissions"
+)]
+pub async fn apitoken(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Issue an API token for scripts using the web API
+#[poise::command(
+    slash_command,
+    check = "crate::permissions::check_admin",
+    guild_only,
+    category = "Permissions"
+)]
+async fn create(
+    ctx: Context<'_>,
+    #[description = "What the token is for"] name: String,
+    #[description = "Comma separated, e.g. tags:write,history:read"] scopes: String,
+    #[description = "Expire after this long, e.g. 30d (never by default)"] duration: Option<String>,
+) -> Result<(), Error> {
+    let scopes = match parse_scopes(&[scopes]) {
+        Ok(scopes) => scopes,
+        Err(e) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(format!("❌ {}. Available: {}", e, SCOPES.join(", ")))
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let expires_at = match duration.as_deref().map(crate::permissions::parse_duration) {
+        None => None,
+        Some(Some(duration)) => Some(chrono::Utc::now().timestamp() + duration.as_secs() as i64),
+        Some(None) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content("❌ Invalid duration. Use something like 12h, 30d or 52w.")
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let (token, secret) = create_token(&name, scopes, ctx.author().id.get(), expires_at)?;
+    audit::record(AuditEntry::discord(ctx, "api_token.create", &token.id).after(token.info_json()));
+
+    let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
+    let expiry = match token.expires_at {
+        Some(expires_at) => format!("<t:{}:f>", expires_at),
+        None => "Never".to_string(),
+    };
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("API Token: {}", token.name))
+        .description(format!(
+            "```\n{}\n```\nSend it as `Authorization: Bearer <token>`. It won't be shown again.",
+            secret
+        ))
+        .field("Scopes", token.scopes.join(", "), false)
+        .field("Expires", expiry, true)
+        .field("ID", format!("`{}`", token.id), true)
+        .footer(footer)
+        .color(colors::SUCCESS)
+        .timestamp(serenity::model::Timestamp::now());
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// List your API tokens
+#[poise::command(
+    slash_command,
+    check = "crate::permissions::check_admin",
+    guild_only,
+    category = "Permissions"
+)]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let user_id = ctx.author().id.get();
+    let tokens: Vec<ApiToken> = list_tokens()?
+        .into_iter()
+        .filter(|token| token.managed_by(user_id))
+        .collect();
+    let now = chrono::Utc::now().timestamp();
+
+    let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
+    let mut embed = serenity::CreateEmbed::new()
+        .title(format!("API Tokens ({})", tokens.len()))
+        .footer(footer)
+        .color(colors::PRIMARY)
+        .timestamp(serenity::model::Timestamp::now());
+    if tokens.is_empty() {
+        embed = embed.description("No API tokens have been issued.");
+    }
+    // Embeds are limited to 25 fields
+    for token in tokens.iter().take(25) {
+        let expiry = match token.expires_at {
+            Some(_) if token.is_expired(now) => "expired".to_string(),
+            Some(expires_at) => format!("expires <t:{}:R>", expires_at),
+            None => "never expires".to_string(),
+        };
+        let last_used = match token.last_used_at {
+            Some(last_used_at) => format!("used <t:{}:R>", last_used_at),
+            None => "never used".to_string(),
+        };
+        embed = embed.field(
+            format!("{} (`{}`)", token.name, token.id),
+            format!(
+                "{}\nBy <@{}>, {}, {}",
+                token.scopes.join(", "),
+                token.created_by,
+                expiry,
+                last_used
+            ),
+            false,
+        );
+    }
+
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Revoke an API token
+#[poise::command(
+    slash_command,
+    check = "crate::permissions::check_admin",
+    guild_only,
+    category = "Permissions"
+)]
+async fn revoke(
+    ctx: Context<'_>,
+    #[description = "Token ID, see /apitoken list"] id: String,
+) -> Result<(), Error> {
+    let id = id.trim().trim_matches('`');
+    // Other users' tokens are reported as missing, so their ids can't be probed
+    let Some(token) = load_token(id)?.filter(|token| token.managed_by(ctx.author().id.get()))
+    else {
+        ctx.send(
+            poise::CreateReply::default()
+                .content(format!("❌ You have no API token with ID `{}`.", id))
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    delete_token(&token.id)?;
+    audit::record(
+        AuditEntry::discord(ctx, "api_token.revoke", &token.id).before(token.info_json()),
+    );
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format!("✅ Revoked API token **{}**", token.name))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,280 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::db::Table;
+use crate::env::FOOTER_URL;
+use crate::web::auth::WebUser;
+use crate::{AUDIT_LOG, Context, Error, KV_DATABASE, colors};
+
+use poise::serenity_prelude as serenity;
+use redb::ReadableDatabase;
+use serde::{Deserialize, Serialize};
+
+/// Keyed `<timestamp>_<sequence>`, appended to and read back in key order
+const ENTRIES: Table<AuditEntry> = Table::new(AUDIT_LOG);
+
+// Keeps keys unique when several changes land in the same microsecond
+static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSource {
+    Discord,
+    Web,
+    System,
+}
+
+impl std::str::FromStr for AuditSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "discord" => Ok(AuditSource::Discord),
+            "web" => Ok(AuditSource::Web),
+            "system" => Ok(AuditSource::System),
+            _ => Err(format!("Invalid source: {}", s)),
+        }
+    }
+}
+
+impl AuditSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditSource::Discord => "discord",
+            AuditSource::Web => "web",
+            AuditSource::System => "system",
+        }
+    }
+}
+
+/// A single change, `before` is unset for creations and `after` for deletions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    pub source: AuditSource,
+    pub actor_id: Option<u64>,
+    pub actor: String,
+    /// Dotted action name, e.g. `permission.grant` or `tag.update`
+    pub action: String,
+    pub target: String,
+    pub guild_id: Option<u64>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+impl AuditEntry {
+    fn new(source: AuditSource, actor: &str, action: &str, target: &str) -> Self {
+        AuditEntry {
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+            source,
+            actor_id: None,
+            actor: actor.to_string(),
+            action: action.to_string(),
+            target: target.to_string(),
+            guild_id: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    /// A change made through a command, attributed to its author
+    pub fn discord(ctx: Context<'_>, action: &str, target: &str) -> Self {
+        let mut entry = Self::new(AuditSource::Discord, &ctx.author().name, action, target);
+        entry.actor_id = Some(ctx.author().id.get());
+        entry.guild_id = ctx.guild_id().map(|guild_id| guild_id.get());
+        entry
+    }
+
+    /// A change made through the dashboard or API, attributed to the logged in user
+    pub fn web(user: &WebUser, action: &str, target: &str) -> Self {
+        let mut entry = Self::new(AuditSource::Web, &user.username, action, target);
+        entry.actor_id = Some(user.user_id);
+        entry
+    }
+
+    /// A change the bot made on its own, like expiring a grant
+    pub fn system(action: &str, target: &str) -> Self {
+        Self::new(AuditSource::System, "system", action, target)
+    }
+
+    pub fn guild(mut self, guild_id: Option<u64>) -> Self {
+        self.guild_id = guild_id;
+        self
+    }
+
+    /// `None` values are left unset, so an `Option` can be passed as is
+    pub fn before(mut self, value: impl Serialize) -> Self {
+        self.before = serde_json::to_value(value).ok().filter(|v| !v.is_null());
+        self
+    }
+
+    pub fn after(mut self, value: impl Serialize) -> Self {
+        self.after = serde_json::to_value(value).ok().filter(|v| !v.is_null());
+        self
+    }
+}
+
+/// Append an entry, failures are logged rather than failing the change itself
+pub fn record(entry: AuditEntry) {
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed) % 10000;
+    let key = format!("{}_{:04}", entry.timestamp, sequence);
+    let result = ENTRIES.insert(&key, &entry);
+
+    match result {
+        Ok(_) => log::info!(
+            "Audit: {} {} {} via {}",
+            entry.actor,
+            entry.action,
+            entry.target,
+            entry.source.as_str()
+        ),
+        Err(e) => log::error!("Failed to write audit entry {}: {}", entry.action, e),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AuditFilter {
+    /// Matches the action or any action under it, `permission` covers `permission.grant`
+    pub action: Option<String>,
+    pub actor_id: Option<u64>,
+    pub target: Option<String>,
+    pub guild_id: Option<u64>,
+    pub source: Option<AuditSource>,
+}
+
+impl AuditFilter {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.action.as_ref().is_none_or(|action| {
+            entry.action == *action || entry.action.starts_with(&format!("{}.", action))
+        }) && self
+            .actor_id
+            .is_none_or(|actor_id| entry.actor_id == Some(actor_id))
+            && self
+                .target
+                .as_ref()
+                .is_none_or(|target| entry.target.contains(target.as_str()))
+            && self
+                .guild_id
+                .is_none_or(|guild_id| entry.guild_id == Some(guild_id))
+            && self.source.is_none_or(|source| entry.source == source)
+    }
+}
+
+/// Newest entries first, starting below `cursor` (the key of the last entry of the previous page)
+pub fn query(
+    filter: &AuditFilter,
+    cursor: Option<&str>,
+    limit: usize,
+) -> Result<(Vec<(String, AuditEntry)>, Option<String>), Error> {
+    let db = KV_DATABASE.get().unwrap();
+    let tx = db.begin_read()?;
+    let table = tx.open_table(AUDIT_LOG)?;
+
+    let range = match cursor {
+        Some(cursor) => table.range::<&str>(..cursor)?,
+        None => table.range::<&str>(..)?,
+    };
+
+    let mut entries = Vec::new();
+    let mut has_more = false;
+    for item in range.rev() {
+        let (key, value) = item?;
+        let Ok(entry) = serde_json::from_str::<AuditEntry>(value.value()) else {
+            continue;
+        };
+        if !filter.matches(&entry) {
+            continue;
+        }
+        if entries.len() == limit {
+            has_more = true;
+            break;
+        }
+        entries.push((key.value().to_string(), entry));
+    }
+
+    let next_cursor = if has_more {
+        entries.last().map(|(key, _)| key.clone())
+    } else {
+        None
+    };
+    Ok((entries, next_cursor))
+}
+
+fn summarize(value: &Option<serde_json::Value>) -> String {
+    let text = match value {
+        Some(value) => value.to_string(),
+        None => "—".to_string(),
+    };
+    if text.chars().count() > 150 {
+        format!("{}…", text.chars().take(150).collect::<String>())
+    } else {
+        text
+    }
+}
+
+/// Show recent permission and configuration changes in this server
+#[poise::command(
+    prefix_command,
+    slash_command,
+    check = "crate::permissions::check_admin",
+    guild_only,
+    category = "Permissions"
+)]
+pub async fn audit(
+    ctx: Context<'_>,
+    #[description = "Action or action group, e.g. permission or tag.update"] action: Option<String>,
+    #[description = "Only changes made by this user"] actor: Option<serenity::User>,
+    #[description = "Only changes made from discord, web or system"] source: Option<String>,
+    #[description = "Text the target contains"] target: Option<String>,
+    #[description = "Number of entries (1-20, default 10)"] limit: Option<usize>,
+) -> Result<(), Error> {
+    let source = match source.as_deref().map(str::parse::<AuditSource>) {
+        None => None,
+        Some(Ok(source)) => Some(source),
+        Some(Err(_)) => {
+            ctx.say("❌ Invalid source. Use: discord, web or system")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let filter = AuditFilter {
+        action: action.map(|action| action.trim().to_lowercase()),
+        actor_id: actor.map(|actor| actor.id.get()),
+        target,
+        guild_id: ctx.guild_id().map(|guild_id| guild_id.get()),
+        source,
+    };
+    let (entries, _) = query(&filter, None, limit.unwrap_or(10).clamp(1, 20))?;
+
+    let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
+    let mut embed = serenity::CreateEmbed::new()
+        .title(format!("Audit Log ({})", entries.len()))
+        .footer(footer)
+        .color(colors::PRIMARY)
+        .timestamp(serenity::model::Timestamp::now());
+    if entries.is_empty() {
+        embed = embed.description("No matching changes found.");
+    }
+
+    for (_, entry) in &entries {
+        let when = chrono::DateTime::parse_from_rfc3339(&entry.timestamp)
+            .map(|time| format!("<t:{}:R>", time.timestamp()))
+            .unwrap_or_else(|_| entry.timestamp.clone());
+        let actor = match entry.actor_id {
+            Some(actor_id) => format!("<@{}>", actor_id),
+            None => entry.actor.clone(),
+        };
+        let mut value = format!("{} by {} via {}", when, actor, entry.source.as_str());
+        if entry.before.is_some() || entry.after.is_some() {
+            value.push_str(&format!(
+                "\n`{}` → `{}`",
+                summarize(&entry.before),
+                summarize(&entry.after)
+            ));
+        }
+        embed = embed.field(format!("{} · {}", entry.action, entry.target), value, false);
+    }
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
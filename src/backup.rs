@@ -0,0 +1,427 @@
+use crate::audit::{self, AuditEntry};
+use crate::env::FOOTER_URL;
+use crate::{ALL_TABLES, Context, Error, colors};
+use poise::serenity_prelude as serenity;
+use redb::{Database, ReadableDatabase, TableHandle};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::time::{Duration, interval};
+
+const EXPORT_FORMAT: &str = "rust-discord-bot-export";
+const EXPORT_VERSION: u32 = 1;
+
+const DEFAULT_BACKUP_DIR: &str = "backups";
+const DEFAULT_INTERVAL_HOURS: u64 = 24;
+const DEFAULT_KEEP: usize = 7;
+const SNAPSHOT_PREFIX: &str = "storage-";
+
+// Discord rejects larger attachments from bots
+const MAX_ATTACHMENT_BYTES: usize = 10 * 1024 * 1024;
+
+/// Sessions are short lived login credentials, so they are never exported or restored
+const EXCLUDED_TABLES: [&str; 1] = ["sessions"];
+
+/// Every table as raw key/value strings, values are the JSON stored in the table
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Export {
+    pub format: String,
+    pub version: u32,
+    pub schema_version: u32,
+    pub exported_at: String,
+    pub tables: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportSummary {
+    pub tables: usize,
+    pub rows: usize,
+    /// Tables in the export this build doesn't know about
+    pub skipped: Vec<String>,
+}
+
+fn exported_tables()
+-> impl Iterator<Item = redb::TableDefinition<'static, &'static str, &'static str>> {
+    ALL_TABLES
+        .into_iter()
+        .filter(|table| !EXCLUDED_TABLES.contains(&table.name()))
+}
+
+/// Dump every table from a single read transaction, so the export is consistent
+pub fn export(db: &Database) -> Result<Export, Error> {
+    let tx = db.begin_read()?;
+    let mut tables = BTreeMap::new();
+    for definition in exported_tables() {
+        let table = tx.open_table(definition)?;
+        let mut rows = BTreeMap::new();
+        for item in table.range::<&str>(..)? {
+            let (key, value) = item?;
+            rows.insert(key.value().to_string(), value.value().to_string());
+        }
+        tables.insert(definition.name().to_string(), rows);
+    }
+
+    Ok(Export {
+        format: EXPORT_FORMAT.to_string(),
+        version: EXPORT_VERSION,
+        schema_version: crate::db::schema_version(db)?,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        tables,
+    })
+}
+
+/// Replace the contents of every exported table with the export, then migrate it to this build's schema
+pub fn import(db: &Database, export: &Export) -> Result<ImportSummary, Error> {
+    if export.format != EXPORT_FORMAT || export.version != EXPORT_VERSION {
+        return Err(format!(
+            "Not a supported export (format {} version {})",
+            export.format, export.version
+        )
+        .into());
+    }
+    let latest = crate::db::latest_schema_version();
+    if export.schema_version > latest {
+        return Err(format!(
+            "Export is from schema version {}, this build only knows up to {}",
+            export.schema_version, latest
+        )
+        .into());
+    }
+
+    let mut summary = ImportSummary {
+        tables: 0,
+        rows: 0,
+        skipped: export
+            .tables
+            .keys()
+            .filter(|name| !exported_tables().any(|table| table.name() == name.as_str()))
+            .cloned()
+            .collect(),
+    };
+
+    let tx = db.begin_write()?;
+    for definition in exported_tables() {
+        let mut table = tx.open_table(definition)?;
+        // Tables missing from the export didn't exist yet when it was made
+        table.retain(|_, _| false)?;
+        if let Some(rows) = export.tables.get(definition.name()) {
+            for (key, value) in rows {
+                table.insert(key.as_str(), value.as_str())?;
+            }
+            summary.tables += 1;
+            summary.rows += rows.len();
+        }
+    }
+    tx.commit()?;
+
+    crate::db::run_migrations(db)?;
+    Ok(summary)
+}
+
+/// Restore from an export file, for the `--import` flag
+pub fn import_file(db: &Database, path: &Path) -> Result<ImportSummary, Error> {
+    let export: Export = serde_json::from_slice(&std::fs::read(path)?)?;
+    let summary = import(db, &export)?;
+    audit::record(AuditEntry::system("backup.import", &path.display().to_string()).after(&summary));
+    Ok(summary)
+}
+
+fn backup_config() -> (PathBuf, u64, usize) {
+    let config = crate::REACTION_CONFIG
+        .get()
+        .and_then(|config| config.backup.as_ref());
+    (
+        PathBuf::from(
+            config
+                .and_then(|backup| backup.dir.as_deref())
+                .unwrap_or(DEFAULT_BACKUP_DIR),
+        ),
+        config
+            .and_then(|backup| backup.interval_hours)
+            .unwrap_or(DEFAULT_INTERVAL_HOURS)
+            .max(1),
+        config
+            .and_then(|backup| backup.keep)
+            .unwrap_or(DEFAULT_KEEP)
+            .max(1),
+    )
+}
+
+/// Copy every table into a new database file in `dir`, while the bot keeps running
+pub fn snapshot(db: &Database, dir: &Path) -> Result<PathBuf, Error> {
+    std::fs::create_dir_all(dir)?;
+    let name = format!(
+        "{}{}.db",
+        SNAPSHOT_PREFIX,
+        chrono::Utc::now().format("%Y%m%d-%H%M%S")
+    );
+    let path = dir.join(&name);
+    // Written under another name first so a crash never leaves a half written snapshot in the set
+    let partial = dir.join(format!("{}.partial", name));
+
+    {
+        let source = db.begin_read()?;
+        let target = Database::create(&partial)?;
+        let tx = target.begin_write()?;
+        for definition in ALL_TABLES {
+            let from = source.open_table(definition)?;
+            let mut to = tx.open_table(definition)?;
+            for item in from.range::<&str>(..)? {
+                let (key, value) = item?;
+                to.insert(key.value(), value.value())?;
+            }
+        }
+        tx.commit()?;
+    }
+    std::fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+/// Remove the oldest snapshots so at most `keep` are left, returns how many were removed
+pub fn rotate(dir: &Path, keep: usize) -> Result<usize, Error> {
+    let mut snapshots: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(SNAPSHOT_PREFIX) && name.ends_with(".db"))
+        })
+        .collect();
+    // Names embed the time, so they sort oldest first
+    snapshots.sort();
+
+    let excess = snapshots.len().saturating_sub(keep);
+    for path in &snapshots[..excess] {
+        std::fs::remove_file(path)?;
+    }
+    Ok(excess)
+}
+
+/// Take a snapshot and rotate the set, off the async runtime since it copies the whole database
+async fn backup_now() -> Result<(PathBuf, usize), Error> {
+    let (dir, _, keep) = backup_config();
+    tokio::task::spawn_blocking(move || {
+        let db = crate::db::get_db()?;
+        let path = snapshot(db, &dir)?;
+        let removed = rotate(&dir, keep)?;
+        Ok::<_, Error>((path, removed))
+    })
+    .await?
+}
+
+pub async fn start_backup_task() {
+    let configured = crate::REACTION_CONFIG
+        .get()
+        .is_some_and(|config| config.backup.is_some());
+    if !configured {
+        log::info!("Scheduled backups are not configured");
+        return;
+    }
+
+    let (dir, interval_hours, keep) = backup_config();
+    let mut interval = interval(Duration::from_secs(interval_hours * 60 * 60));
+    log::info!(
+        "Backup task started (every {}h into {}, keeping {})",
+        interval_hours,
+        dir.display(),
+        keep
+    );
+
+    loop {
+        interval.tick().await;
+
+        match backup_now().await {
+            Ok((path, removed)) => log::info!(
+                "Backed up database to {} ({} old snapshots removed)",
+                path.display(),
+                removed
+            ),
+            Err(e) => log::error!("Error backing up database: {:?}", e),
+        }
+    }
+}
+
+// Slash only, exports include API token hashes and shouldn't be posted publicly
+#[poise::command(
+    slash_command,
+    subcommands("backup", "export_command"),
+    subcommand_required,
+    owners_only,
+    category = "Utility"
+)]
+pub async fn admin(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Take a database snapshot now
+#[poise::command(slash_command, owners_only, category = "Utility")]
+async fn backup(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let (path, removed) = backup_now().await?;
+    let size = std::fs::metadata(&path)?.len();
+    audit::record(AuditEntry::discord(
+        ctx,
+        "backup.snapshot",
+        &path.display().to_string(),
+    ));
+
+    let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
+    let embed = serenity::CreateEmbed::new()
+        .title("✅ Backup Complete")
+        .field("File", format!("`{}`", path.display()), false)
+        .field("Size", format!("{:.1} KiB", size as f64 / 1024.0), true)
+        .field("Old snapshots removed", removed.to_string(), true)
+        .footer(footer)
+        .color(colors::SUCCESS)
+        .timestamp(serenity::model::Timestamp::now());
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Export every table as JSON
+#[poise::command(slash_command, rename = "export", owners_only, category = "Utility")]
+async fn export_command(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let export = export(crate::db::get_db()?)?;
+    let rows: usize = export.tables.values().map(|rows| rows.len()).sum();
+    let bytes = serde_json::to_vec_pretty(&export)?;
+
+    if bytes.len() > MAX_ATTACHMENT_BYTES {
+        ctx.send(
+            poise::CreateReply::default()
+                .content("❌ The export is too large to attach, download it from the dashboard's Backup page instead.")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    audit::record(AuditEntry::discord(ctx, "backup.export", "database"));
+    let name = format!("export-{}.json", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format!(
+                "✅ Exported {} rows from {} tables (schema version {})",
+                rows,
+                export.tables.len(),
+                export.schema_version
+            ))
+            .attachment(serenity::CreateAttachment::bytes(bytes, name))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::{LEGACY_PAPERS, TempDatabase};
+
+    fn migrated_database() -> TempDatabase {
+        let temp = TempDatabase::new();
+        crate::db::run_migrations(&temp.db).unwrap();
+        temp
+    }
+
+    #[test]
+    fn round_trips_every_table_but_sessions() {
+        let source = migrated_database();
+        source.insert(crate::TABLE, "greeting", "hello");
+        source.insert(crate::PAPERS, "1_2", LEGACY_PAPERS);
+        source.insert(crate::AUDIT_LOG, "1700000000_0000", "{}");
+        source.insert(crate::SESSIONS, "session", "{}");
+
+        let exported = export(&source.db).unwrap();
+        assert!(!exported.tables.contains_key("sessions"));
+
+        let target = TempDatabase::new();
+        let summary = import(&target.db, &exported).unwrap();
+
+        assert!(summary.skipped.is_empty());
+        assert_eq!(summary.tables, exported.tables.len());
+        assert_eq!(export(&target.db).unwrap().tables, exported.tables);
+        assert_eq!(target.get(crate::SESSIONS, "session"), None);
+    }
+
+    #[test]
+    fn rejects_newer_schemas_and_other_formats() {
+        let temp = migrated_database();
+        temp.insert(crate::TABLE, "greeting", "hello");
+        let before = temp.contents();
+
+        let mut newer = export(&temp.db).unwrap();
+        newer.schema_version = crate::db::latest_schema_version() + 1;
+        assert!(import(&temp.db, &newer).is_err());
+
+        let mut foreign = export(&temp.db).unwrap();
+        foreign.format = "something-else".to_string();
+        assert!(import(&temp.db, &foreign).is_err());
+
+        assert_eq!(temp.contents(), before);
+    }
+
+    #[test]
+    fn migrates_exports_from_older_schemas() {
+        let export = Export {
+            format: EXPORT_FORMAT.to_string(),
+            version: EXPORT_VERSION,
+            schema_version: 0,
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            tables: BTreeMap::from([(
+                "papers".to_string(),
+                BTreeMap::from([("1_2".to_string(), LEGACY_PAPERS.to_string())]),
+            )]),
+        };
+
+        let temp = migrated_database();
+        import(&temp.db, &export).unwrap();
+
+        assert_eq!(
+            crate::db::schema_version(&temp.db).unwrap(),
+            crate::db::latest_schema_version()
+        );
+        let state: crate::utility::papers::PapersState =
+            serde_json::from_str(&temp.get(crate::PAPERS, "1_2").unwrap()).unwrap();
+        assert_eq!(state.buttons[0].label, "Red");
+    }
+
+    #[test]
+    fn rotate_keeps_the_newest_snapshots() {
+        let dir = std::env::temp_dir().join(format!("bot-backups-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let snapshots = [
+            "storage-20260101-000000.db",
+            "storage-20260102-000000.db",
+            "storage-20260103-000000.db",
+            "storage-20260104-000000.db",
+        ];
+        for name in snapshots
+            .iter()
+            .chain(&["notes.txt", "storage-20260105-000000.db.partial"])
+        {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let removed = rotate(&dir, 2).unwrap();
+
+        let mut left: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(removed, 2);
+        assert_eq!(
+            left,
+            [
+                "notes.txt",
+                "storage-20260103-000000.db",
+                "storage-20260104-000000.db",
+                "storage-20260105-000000.db.partial",
+            ]
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use crate::db::Table;
+use crate::{Error, HISTORY, KV_DATABASE};
+use chrono::{DateTime, Utc};
+use redb::{ReadableDatabase, ReadableTable, ReadableTableMetadata};
+use serde::Deserialize;
+use tokio::time::{Duration, interval};
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Written through the table, but read and pruned with range scans that stop early
+const ENTRIES: Table<serde_json::Value> = Table::new(HISTORY);
+
+/// The fields of a history entry that can be filtered on, entries are keyed `<rfc3339 timestamp>_<user id>`
+#[derive(Debug, Deserialize)]
+struct HistoryFields {
+    timestamp: String,
+    #[serde(default)]
+    user: String,
+    #[serde(default)]
+    user_id: String,
+    #[serde(default)]
+    command: String,
+    #[serde(default)]
+    guild: String,
+    #[serde(default)]
+    success: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct HistoryFilter {
+    /// A user ID, or part of a username
+    pub user: Option<String>,
+    /// Matches the command or any of its subcommands, `tag` covers `tag create`
+    pub command: Option<String>,
+    /// A guild ID, or `DM`
+    pub guild: Option<String>,
+    /// Only entries from these guilds, for users who may only see some of them
+    pub guilds: Option<Vec<u64>>,
+    pub success: Option<bool>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, entry: &HistoryFields, timestamp: DateTime<Utc>) -> bool {
+        self.user.as_ref().is_none_or(|user| {
+            entry.user_id == *user || entry.user.to_lowercase().contains(&user.to_lowercase())
+        }) && self.command.as_ref().is_none_or(|command| {
+            entry.command == *command || entry.command.starts_with(&format!("{} ", command))
+        }) && self
+            .guild
+            .as_ref()
+            .is_none_or(|guild| entry.guild.eq_ignore_ascii_case(guild))
+            && self.guilds.as_ref().is_none_or(|guilds| {
+                entry
+                    .guild
+                    .parse()
+                    .is_ok_and(|guild_id| guilds.contains(&guild_id))
+            })
+            && self.success.is_none_or(|success| entry.success == success)
+            && self.until.is_none_or(|until| timestamp <= until)
+    }
+}
+
+/// Newest entries first, starting below `cursor` (the key of the last entry of the previous page)
+pub fn query(
+    filter: &HistoryFilter,
+    cursor: Option<&str>,
+    limit: usize,
+) -> Result<(Vec<serde_json::Value>, Option<String>), Error> {
+    let db = KV_DATABASE.get().unwrap();
+    let tx = db.begin_read()?;
+    let table = tx.open_table(HISTORY)?;
+
+    let range = match cursor {
+        Some(cursor) => table.range::<&str>(..cursor)?,
+        None => table.range::<&str>(..)?,
+    };
+
+    let mut entries = Vec::new();
+    let mut last_key = None;
+    let mut has_more = false;
+    for item in range.rev() {
+        let (key, value) = item?;
+        let Ok(fields) = serde_json::from_str::<HistoryFields>(value.value()) else {
+            continue;
+        };
+        let Ok(timestamp) = DateTime::parse_from_rfc3339(&fields.timestamp) else {
+            continue;
+        };
+        let timestamp = timestamp.with_timezone(&Utc);
+        // Keys sort by time, so nothing further down can be newer
+        if filter.since.is_some_and(|since| timestamp < since) {
+            break;
+        }
+        if !filter.matches(&fields, timestamp) {
+            continue;
+        }
+        if entries.len() == limit {
+            has_more = true;
+            break;
+        }
+        if let Ok(entry) = serde_json::from_str(value.value()) {
+            entries.push(entry);
+            last_key = Some(key.value().to_string());
+        }
+    }
+
+    Ok((entries, if has_more { last_key } else { None }))
+}
+
+/// Commands run since `since`, without reading older entries
+pub fn count_since(since: DateTime<Utc>) -> Result<usize, Error> {
+    let db = KV_DATABASE.get().unwrap();
+    let tx = db.begin_read()?;
+    let table = tx.open_table(HISTORY)?;
+    let start = since.to_rfc3339();
+    Ok(table.range::<&str>(start.as_str()..)?.count())
+}
+
+fn retention() -> (Option<u64>, Option<u64>) {
+    crate::REACTION_CONFIG
+        .get()
+        .and_then(|config| config.history.as_ref())
+        .map(|history| (history.retention_days, history.max_rows))
+        .unwrap_or_default()
+}
+
+/// Store a command invocation, failures are only logged so they never fail the command
+pub fn record(key: &str, entry: &serde_json::Value) {
+    if let Err(e) = ENTRIES.insert(key, entry) {
+        log::error!("Failed to write command history entry {}: {}", key, e);
+    }
+}
+
+/// Remove entries past the configured age or row limit, returns how many were removed
+pub fn prune(retention_days: Option<u64>, max_rows: Option<u64>) -> Result<u64, Error> {
+    let db = KV_DATABASE.get().unwrap();
+    let tx = db.begin_write()?;
+    let removed;
+    {
+        let mut table = tx.open_table(HISTORY)?;
+        let before = table.len()?;
+
+        if let Some(days) = retention_days {
+            let cutoff = (Utc::now() - chrono::Duration::days(days as i64)).to_rfc3339();
+            table.retain_in::<&str, _>(..cutoff.as_str(), |_, _| false)?;
+        }
+
+        if let Some(max_rows) = max_rows {
+            let excess = table.len()?.saturating_sub(max_rows);
+            if excess > 0 {
+                // Oldest first, the key of the newest entry that has to go
+                let last = table
+                    .iter()?
+                    .nth(excess as usize - 1)
+                    .transpose()?
+                    .map(|(key, _)| key.value().to_string());
+                if let Some(last) = last {
+                    table.retain_in::<&str, _>(..=last.as_str(), |_, _| false)?;
+                }
+            }
+        }
+
+        removed = before - table.len()?;
+    }
+    tx.commit()?;
+    Ok(removed)
+}
+
+pub async fn start_retention_task() {
+    let (retention_days, max_rows) = retention();
+    if retention_days.is_none() && max_rows.is_none() {
+        log::info!("Command history retention is not configured, keeping everything");
+        return;
+    }
+
+    let mut interval = interval(PRUNE_INTERVAL);
+    log::info!(
+        "Command history retention started (days: {:?}, max rows: {:?})",
+        retention_days,
+        max_rows
+    );
+
+    loop {
+        interval.tick().await;
+
+        match prune(retention_days, max_rows) {
+            Ok(0) => {}
+            Ok(removed) => log::info!("Pruned {} command history entries", removed),
+            Err(e) => log::error!("Error pruning command history: {:?}", e),
+        }
+    }
+}
@@ -34,10 +34,55 @@ pub struct AiConfig {
     pub system_prompt: String,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct MusicConfig {
+    /// Directory of local audio files indexed for `/music local`
+    pub library_path: Option<String>,
+    pub lyrics: Option<LyricsConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LyricsConfig {
+    /// Base URL of an LRCLIB-compatible API, defaults to https://lrclib.net
+    pub api_url: Option<String>,
+    /// Directory of `.lrc` files used when the API has no match
+    pub lrc_path: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StreamsConfig {
+    /// Seconds between stream checks, defaults to 60
+    pub interval_secs: Option<u64>,
+    /// Status checks allowed in flight at once, defaults to 4
+    pub concurrency: Option<usize>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HistoryConfig {
+    /// Drop command history older than this many days, kept forever by default
+    pub retention_days: Option<u64>,
+    /// Keep at most this many of the newest commands, unlimited by default
+    pub max_rows: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BackupConfig {
+    /// Directory snapshots are written to, defaults to `backups`
+    pub dir: Option<String>,
+    /// Hours between scheduled snapshots, defaults to 24
+    pub interval_hours: Option<u64>,
+    /// Snapshots kept before the oldest is removed, defaults to 7
+    pub keep: Option<usize>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Config {
     pub response: HashMap<String, Response>,
     pub ai: Option<AiConfig>,
+    pub music: Option<MusicConfig>,
+    pub streams: Option<StreamsConfig>,
+    pub history: Option<HistoryConfig>,
+    pub backup: Option<BackupConfig>,
 }
 
 pub fn load_config() -> Result<Config, Error> {
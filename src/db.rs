@@ -1,6 +1,11 @@
-use redb::{Database, ReadableDatabase, TableDefinition};
+use poise::serenity_prelude as serenity;
+use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition, WriteTransaction};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
 use std::error::Error as StdError;
 use std::fmt;
+use std::marker::PhantomData;
 
 #[derive(Debug)]
 pub enum DbError {
@@ -11,7 +16,6 @@ pub enum DbError {
     Query(String),
     Insert(String),
     Delete(String),
-    #[allow(dead_code)]
     Serialization(String),
 }
 
@@ -172,3 +176,571 @@ pub fn update_entry(
     tx.commit().map_err(|e| DbError::Insert(e.to_string()))?;
     Ok(())
 }
+
+/// Join the parts of a composite key with `_`, the separator every keyed table uses
+pub fn key(parts: &[&dyn fmt::Display]) -> String {
+    parts
+        .iter()
+        .map(|part| part.to_string())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// A table holding JSON serialized values of one type under string keys.
+/// Tags and the AI context store plain strings, and the command history and audit log are paged
+/// backwards through key ranges, so those keep using transactions directly.
+pub struct Table<T> {
+    definition: TableDefinition<'static, &'static str, &'static str>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Table<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Table<T> {}
+
+impl<T: Serialize + DeserializeOwned> Table<T> {
+    pub const fn new(definition: TableDefinition<'static, &'static str, &'static str>) -> Self {
+        Table {
+            definition,
+            _marker: PhantomData,
+        }
+    }
+
+    fn decode(value: &str) -> Result<T, DbError> {
+        serde_json::from_str(value).map_err(|e| DbError::Serialization(e.to_string()))
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<T>, DbError> {
+        read_entry(self.definition, key)?
+            .map(|value| Self::decode(&value))
+            .transpose()
+    }
+
+    /// Insert or replace a value, returns the one it replaced
+    pub fn insert(&self, key: &str, value: &T) -> Result<Option<T>, DbError> {
+        let value =
+            serde_json::to_string(value).map_err(|e| DbError::Serialization(e.to_string()))?;
+        let db = get_db()?;
+        let tx = db
+            .begin_write()
+            .map_err(|e| DbError::WriteTransaction(e.to_string()))?;
+        let previous = {
+            let mut table = tx
+                .open_table(self.definition)
+                .map_err(|e| DbError::TableOpen(e.to_string()))?;
+            table
+                .insert(key, value.as_str())
+                .map_err(|e| DbError::Insert(e.to_string()))?
+                .and_then(|previous| Self::decode(previous.value()).ok())
+        };
+        tx.commit().map_err(|e| DbError::Insert(e.to_string()))?;
+        Ok(previous)
+    }
+
+    /// Remove a value, returns it if there was one
+    pub fn remove(&self, key: &str) -> Result<Option<T>, DbError> {
+        let db = get_db()?;
+        let tx = db
+            .begin_write()
+            .map_err(|e| DbError::WriteTransaction(e.to_string()))?;
+        let removed = {
+            let mut table = tx
+                .open_table(self.definition)
+                .map_err(|e| DbError::TableOpen(e.to_string()))?;
+            table
+                .remove(key)
+                .map_err(|e| DbError::Delete(e.to_string()))?
+                .and_then(|removed| Self::decode(removed.value()).ok())
+        };
+        tx.commit().map_err(|e| DbError::Delete(e.to_string()))?;
+        Ok(removed)
+    }
+
+    /// Entries whose key starts with `prefix` in key order, read with a range scan rather than the whole table
+    pub fn prefix(&self, prefix: &str) -> Result<Vec<(String, T)>, DbError> {
+        let db = get_db()?;
+        let tx = db
+            .begin_read()
+            .map_err(|e| DbError::ReadTransaction(e.to_string()))?;
+        let table = tx
+            .open_table(self.definition)
+            .map_err(|e| DbError::TableOpen(e.to_string()))?;
+
+        let mut entries = Vec::new();
+        for item in table
+            .range::<&str>(prefix..)
+            .map_err(|e| DbError::Query(e.to_string()))?
+        {
+            let (key, value) = item.map_err(|e| DbError::Query(e.to_string()))?;
+            if !key.value().starts_with(prefix) {
+                break;
+            }
+            if let Ok(value) = Self::decode(value.value()) {
+                entries.push((key.value().to_string(), value));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Replace the whole contents of the table in a single transaction
+    pub fn replace_all<'a>(
+        &self,
+        entries: impl IntoIterator<Item = (&'a str, &'a T)>,
+    ) -> Result<(), DbError>
+    where
+        T: 'a,
+    {
+        let db = get_db()?;
+        let tx = db
+            .begin_write()
+            .map_err(|e| DbError::WriteTransaction(e.to_string()))?;
+        {
+            let mut table = tx
+                .open_table(self.definition)
+                .map_err(|e| DbError::TableOpen(e.to_string()))?;
+            table
+                .retain(|_, _| false)
+                .map_err(|e| DbError::Delete(e.to_string()))?;
+            for (key, value) in entries {
+                let value = serde_json::to_string(value)
+                    .map_err(|e| DbError::Serialization(e.to_string()))?;
+                table
+                    .insert(key, value.as_str())
+                    .map_err(|e| DbError::Insert(e.to_string()))?;
+            }
+        }
+        tx.commit().map_err(|e| DbError::Insert(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Every value in key order, values that no longer decode are skipped
+    pub fn values(&self) -> Result<Vec<T>, DbError> {
+        read_table(self.definition, |_, value| Self::decode(value).ok())
+    }
+
+    /// Every entry with its key in key order, values that no longer decode are skipped
+    pub fn entries(&self) -> Result<Vec<(String, T)>, DbError> {
+        read_table(self.definition, |key, value| {
+            Self::decode(value)
+                .ok()
+                .map(|value| (key.to_string(), value))
+        })
+    }
+}
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Ids a migration looked up from Discord, e.g. the guilds of a channel or user
+pub type Lookups = HashMap<u64, Vec<u64>>;
+
+/// A one-off change to stored data, run once in its own write transaction
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    /// Fetches what `run` needs from Discord, before the write transaction opens
+    pub lookup: Option<fn(&Database) -> Result<Lookups, crate::Error>>,
+    pub run: fn(&WriteTransaction, &Lookups) -> Result<(), crate::Error>,
+}
+
+/// Applied in order at startup, append new migrations with the next version.
+/// When a lookup fails, that migration and the ones after it wait for the next start,
+/// so code must keep handling the data they would have changed.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "convert papers buttons saved before button types",
+        lookup: None,
+        run: crate::utility::papers::migrate_role_buttons,
+    },
+    Migration {
+        version: 2,
+        description: "key permissions, command policies and stream follows with `_`",
+        lookup: None,
+        run: migrate_key_separators,
+    },
+    Migration {
+        version: 3,
+        description: "scope stream follows to the guild of their channel",
+        lookup: Some(crate::streams::lookup_follow_guilds),
+        run: crate::streams::migrate_legacy_follows,
+    },
+    Migration {
+        version: 4,
+        description: "scope permissions to the guilds of their users",
+        lookup: Some(crate::permissions::lookup_member_guilds),
+        run: crate::permissions::migrate_legacy_permissions,
+    },
+];
+
+/// Move every entry to the key `rename` gives it, returns the old keys of moved entries mapped to the new ones
+fn rekey(
+    tx: &WriteTransaction,
+    definition: TableDefinition<&str, &str>,
+    rename: impl Fn(&str, &str) -> Option<String>,
+) -> Result<HashMap<String, String>, crate::Error> {
+    let mut table = tx.open_table(definition)?;
+    let mut moves = Vec::new();
+    for item in table.range::<&str>(..)? {
+        let (key, value) = item?;
+        if let Some(new_key) = rename(key.value(), value.value())
+            && new_key != key.value()
+        {
+            moves.push((key.value().to_string(), new_key, value.value().to_string()));
+        }
+    }
+
+    let mut renamed = HashMap::new();
+    for (old_key, new_key, value) in moves {
+        table.remove(old_key.as_str())?;
+        table.insert(new_key.as_str(), value.as_str())?;
+        renamed.insert(old_key, new_key);
+    }
+    Ok(renamed)
+}
+
+/// Schema migration 2: permissions, command policies and stream follows were keyed with `:`
+fn migrate_key_separators(tx: &WriteTransaction, _: &Lookups) -> Result<(), crate::Error> {
+    // Guild, target kind and id, none of which contain either separator
+    rekey(tx, crate::PERMISSIONS, |old_key, _| {
+        Some(old_key.replace(':', "_"))
+    })?;
+    rekey(tx, crate::COMMAND_POLICIES, |old_key, _| {
+        let (guild_id, command) = old_key.split_once(':')?;
+        Some(key(&[&guild_id, &command]))
+    })?;
+    // Channel names may contain `_`, so follows are rekeyed from their values and live states follow along
+    let follows = rekey(tx, crate::STREAMS, |_, value| {
+        serde_json::from_str::<crate::streams::StreamFollow>(value)
+            .ok()
+            .map(|follow| follow.key())
+    })?;
+    let states = rekey(tx, crate::LIVE_STREAMS_STATE, |old_key, _| {
+        follows.get(old_key).cloned()
+    })?;
+    log::info!(
+        "Rekeyed {} stream follows and {} live states",
+        follows.len(),
+        states.len()
+    );
+    Ok(())
+}
+
+/// The schema version this build migrates databases up to
+pub fn latest_schema_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// The schema version of the database, 0 if it predates versioning
+pub fn schema_version(db: &Database) -> Result<u32, crate::Error> {
+    let tx = db.begin_read()?;
+    let table = tx.open_table(crate::META)?;
+    let version = match table.get(SCHEMA_VERSION_KEY)? {
+        Some(version) => version.value().parse()?,
+        None => 0,
+    };
+    Ok(version)
+}
+
+/// Wait for a future inside a migration lookup, which runs synchronously on a runtime thread
+pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+/// Whether a Discord request failed because what it asked for doesn't exist
+pub fn is_not_found(error: &serenity::Error) -> bool {
+    matches!(
+        error,
+        serenity::Error::Http(serenity::HttpError::UnsuccessfulRequest(response))
+            if response.status_code.as_u16() == 404
+    )
+}
+
+/// Bring the database up to the latest schema version.
+/// The version is stored in the same transaction as each migration, so a failed one is retried on the next start.
+pub fn run_migrations(db: &Database) -> Result<(), crate::Error> {
+    let current = schema_version(db)?;
+    let latest = latest_schema_version();
+    if current > latest {
+        return Err(format!(
+            "Database schema version {} is newer than this build ({}), it was written by a newer version",
+            current, latest
+        )
+        .into());
+    }
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > current)
+    {
+        let lookups = match migration.lookup.map(|lookup| lookup(db)) {
+            None => Lookups::new(),
+            Some(Ok(lookups)) => lookups,
+            // Recording the version now would leave the data unmigrated for good
+            Some(Err(e)) => {
+                log::warn!(
+                    "Postponing database migration {} to the next start, lookup failed: {}",
+                    migration.version,
+                    e
+                );
+                return Ok(());
+            }
+        };
+
+        log::info!(
+            "Migrating database to version {}: {}",
+            migration.version,
+            migration.description
+        );
+        let tx = db.begin_write()?;
+        (migration.run)(&tx, &lookups)
+            .map_err(|e| format!("Migration {} failed: {}", migration.version, e))?;
+        {
+            let mut table = tx.open_table(crate::META)?;
+            table.insert(SCHEMA_VERSION_KEY, migration.version.to_string().as_str())?;
+        }
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::utility::papers::{ButtonType, PapersState};
+    use std::path::PathBuf;
+
+    /// A database in the temp dir with every table created, removed again on drop
+    pub(crate) struct TempDatabase {
+        pub db: Database,
+        path: PathBuf,
+    }
+
+    impl TempDatabase {
+        pub fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("bot-test-{}.db", uuid::Uuid::new_v4()));
+            let db = Database::create(&path).unwrap();
+            let tx = db.begin_write().unwrap();
+            for table in crate::ALL_TABLES {
+                tx.open_table(table).unwrap();
+            }
+            tx.commit().unwrap();
+            TempDatabase { db, path }
+        }
+
+        pub fn insert(&self, table: TableDefinition<&str, &str>, key: &str, value: &str) {
+            let tx = self.db.begin_write().unwrap();
+            tx.open_table(table).unwrap().insert(key, value).unwrap();
+            tx.commit().unwrap();
+        }
+
+        pub fn get(&self, table: TableDefinition<&str, &str>, key: &str) -> Option<String> {
+            let tx = self.db.begin_read().unwrap();
+            let table = tx.open_table(table).unwrap();
+            table
+                .get(key)
+                .unwrap()
+                .map(|value| value.value().to_string())
+        }
+
+        pub fn rows(&self, table: TableDefinition<&str, &str>) -> Vec<(String, String)> {
+            let tx = self.db.begin_read().unwrap();
+            let table = tx.open_table(table).unwrap();
+            table
+                .iter()
+                .unwrap()
+                .map(|item| {
+                    let (key, value) = item.unwrap();
+                    (key.value().to_string(), value.value().to_string())
+                })
+                .collect()
+        }
+
+        /// Every row of every table, for comparing whole databases
+        pub fn contents(&self) -> Vec<Vec<(String, String)>> {
+            crate::ALL_TABLES
+                .iter()
+                .map(|definition| self.rows(*definition))
+                .collect()
+        }
+
+        /// Run one migration directly, with lookups standing in for Discord
+        pub fn migrate(&self, migration: &Migration, lookups: &Lookups) {
+            let tx = self.db.begin_write().unwrap();
+            (migration.run)(&tx, lookups).unwrap();
+            tx.commit().unwrap();
+        }
+    }
+
+    impl Drop for TempDatabase {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    // Papers buttons as saved before button types, the oldest shape still migrated
+    pub(crate) const LEGACY_PAPERS: &str = r#"{
+        "channel_id": 1,
+        "message_id": 2,
+        "guild_id": 3,
+        "embed_config": {"title": "Roles"},
+        "buttons": [{"name": "Red", "role_id": 10, "style": "Danger", "emoji": null}]
+    }"#;
+
+    #[test]
+    fn migrates_a_legacy_database_to_the_latest_version() {
+        let temp = TempDatabase::new();
+        temp.insert(crate::PAPERS, "1_2", LEGACY_PAPERS);
+        assert_eq!(schema_version(&temp.db).unwrap(), 0);
+
+        run_migrations(&temp.db).unwrap();
+
+        let state: PapersState =
+            serde_json::from_str(&temp.get(crate::PAPERS, "1_2").unwrap()).unwrap();
+        assert_eq!(state.guild_id, Some(3));
+        assert_eq!(state.embed_config.title.as_deref(), Some("Roles"));
+        assert_eq!(state.buttons.len(), 1);
+        assert_eq!(state.buttons[0].label, "Red");
+        assert!(matches!(
+            state.buttons[0].button_type,
+            ButtonType::Role { role_id: 10 }
+        ));
+        assert_eq!(schema_version(&temp.db).unwrap(), latest_schema_version());
+    }
+
+    #[test]
+    fn migrating_twice_changes_nothing() {
+        let temp = TempDatabase::new();
+        temp.insert(crate::PAPERS, "1_2", LEGACY_PAPERS);
+        run_migrations(&temp.db).unwrap();
+        let migrated = temp.contents();
+
+        run_migrations(&temp.db).unwrap();
+
+        assert_eq!(temp.contents(), migrated);
+    }
+
+    #[test]
+    fn rejects_a_database_from_a_newer_build() {
+        let temp = TempDatabase::new();
+        let newer = (latest_schema_version() + 1).to_string();
+        temp.insert(crate::META, SCHEMA_VERSION_KEY, &newer);
+
+        assert!(run_migrations(&temp.db).is_err());
+        assert_eq!(temp.get(crate::META, SCHEMA_VERSION_KEY), Some(newer));
+    }
+
+    fn migration(version: u32) -> &'static Migration {
+        MIGRATIONS
+            .iter()
+            .find(|migration| migration.version == version)
+            .unwrap()
+    }
+
+    #[test]
+    fn rekeys_tables_keyed_with_colons() {
+        let temp = TempDatabase::new();
+        temp.insert(crate::PERMISSIONS, "1:role:2", "{}");
+        temp.insert(crate::PERMISSIONS, "5", "{}");
+        temp.insert(crate::COMMAND_POLICIES, "1:music queue", "{}");
+        let follow = r#"{"guild_id": 1, "user_id": 2, "url": "https://www.twitch.tv/a_b",
+            "channel_id": 3, "platform": "twitch", "channel_name": "a_b"}"#;
+        temp.insert(crate::STREAMS, "1:twitch:a_b:3", follow);
+        temp.insert(crate::LIVE_STREAMS_STATE, "1:twitch:a_b:3", "{}");
+
+        temp.migrate(migration(2), &Lookups::new());
+
+        let keys = |table| {
+            temp.rows(table)
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys(crate::PERMISSIONS), ["1_role_2", "5"]);
+        assert_eq!(keys(crate::COMMAND_POLICIES), ["1_music queue"]);
+        assert_eq!(keys(crate::STREAMS), ["1_twitch_a_b_3"]);
+        assert_eq!(keys(crate::LIVE_STREAMS_STATE), ["1_twitch_a_b_3"]);
+    }
+
+    #[test]
+    fn scopes_legacy_follows_it_could_resolve() {
+        let temp = TempDatabase::new();
+        let follow = |name: &str, channel_id: u64| {
+            serde_json::json!({
+                "user_id": 1,
+                "url": format!("https://www.twitch.tv/{}", name),
+                "channel_id": channel_id,
+                "platform": "twitch",
+                "channel_name": name,
+            })
+            .to_string()
+        };
+        temp.insert(crate::STREAMS, "twitch_resolved", &follow("resolved", 10));
+        temp.insert(crate::STREAMS, "twitch_gone", &follow("gone", 20));
+        temp.insert(crate::LIVE_STREAMS_STATE, "twitch_resolved", "{}");
+
+        temp.migrate(migration(3), &Lookups::from([(10, vec![3])]));
+
+        let follows: Vec<crate::streams::StreamFollow> = temp
+            .rows(crate::STREAMS)
+            .iter()
+            .map(|(_, value)| serde_json::from_str(value).unwrap())
+            .collect();
+        let resolved = follows
+            .iter()
+            .find(|follow| follow.channel_name == "resolved")
+            .unwrap();
+        let gone = follows
+            .iter()
+            .find(|follow| follow.channel_name == "gone")
+            .unwrap();
+        assert_eq!(follows.len(), 2);
+        assert_eq!(resolved.guild_id, Some(3));
+        assert_eq!(gone.guild_id, None);
+        assert_eq!(temp.get(crate::LIVE_STREAMS_STATE, "twitch_resolved"), None);
+        assert!(
+            temp.get(crate::LIVE_STREAMS_STATE, &resolved.key())
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn keeps_legacy_grants_of_users_in_no_guild() {
+        let temp = TempDatabase::new();
+        temp.insert(
+            crate::PERMISSIONS,
+            "5",
+            r#"{"user_id": 5, "permissions": ["admin"]}"#,
+        );
+        temp.insert(
+            crate::PERMISSIONS,
+            "6",
+            r#"{"user_id": 6, "permissions": ["mod"]}"#,
+        );
+
+        temp.migrate(
+            migration(4),
+            &Lookups::from([(5, vec![1, 2]), (6, Vec::new())]),
+        );
+
+        let grants: Vec<serde_json::Value> = temp
+            .rows(crate::PERMISSIONS)
+            .iter()
+            .map(|(_, value)| serde_json::from_str(value).unwrap())
+            .collect();
+        let guilds_of = |id: u64| {
+            grants
+                .iter()
+                // Legacy grants still use the old field name
+                .filter(|grant| grant["id"] == id || grant["user_id"] == id)
+                .map(|grant| grant["guild_id"].as_u64())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(guilds_of(5), [Some(1), Some(2)]);
+        assert_eq!(guilds_of(6), [None]);
+        assert_eq!(temp.get(crate::PERMISSIONS, "5"), None);
+    }
+}
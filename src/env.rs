@@ -33,5 +33,37 @@ pub static KICK_CLIENT_ID: LazyLock<String> =
 pub static KICK_CLIENT_SECRET: LazyLock<String> =
     LazyLock::new(|| std::env::var("KICK_CLIENT_SECRET").unwrap());
 
+/// Optional, Twitch follows fail to check until both are set
+pub static TWITCH_CLIENT_ID: LazyLock<Option<String>> =
+    LazyLock::new(|| std::env::var("TWITCH_CLIENT_ID").ok());
+
+pub static TWITCH_CLIENT_SECRET: LazyLock<Option<String>> =
+    LazyLock::new(|| std::env::var("TWITCH_CLIENT_SECRET").ok());
+
+/// Optional, YouTube live checks fall back to probing the feed's watch pages without it
+pub static YOUTUBE_API_KEY: LazyLock<Option<String>> =
+    LazyLock::new(|| std::env::var("YOUTUBE_API_KEY").ok());
+
 pub static TMDB_API_KEY: LazyLock<String> =
     LazyLock::new(|| std::env::var("TMDB_API_KEY").unwrap());
+
+/// Optional, Discord OAuth2 login for the dashboard needs the client id, secret and base url
+pub static DISCORD_CLIENT_ID: LazyLock<Option<String>> =
+    LazyLock::new(|| std::env::var("DISCORD_CLIENT_ID").ok());
+
+pub static DISCORD_CLIENT_SECRET: LazyLock<Option<String>> =
+    LazyLock::new(|| std::env::var("DISCORD_CLIENT_SECRET").ok());
+
+/// Public url of the dashboard, e.g. `https://bot.example`
+pub static WEB_BASE_URL: LazyLock<Option<String>> = LazyLock::new(|| {
+    std::env::var("WEB_BASE_URL")
+        .ok()
+        .map(|url| url.trim_end_matches('/').to_string())
+});
+
+/// Optional, lets the dashboard be logged into as the bot author without OAuth (local development)
+pub static WEB_ADMIN_TOKEN: LazyLock<Option<String>> = LazyLock::new(|| {
+    std::env::var("WEB_ADMIN_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+});
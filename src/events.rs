@@ -0,0 +1,114 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+use simplelog::{Config, SharedLogger};
+use std::collections::VecDeque;
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::broadcast;
+
+const CHANNEL_CAPACITY: usize = 1024;
+
+// Sent to new subscribers so the logging page doesn't start out empty
+const BACKLOG_SIZE: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogRecord {
+    pub timestamp: String,
+    pub level: String,
+    pub module: String,
+    pub message: String,
+}
+
+/// Something that happened while the bot was running, streamed live to the dashboard
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum LiveEvent {
+    Log(LogRecord),
+    /// The same entry that's stored in the command history
+    Command(serde_json::Value),
+}
+
+static EVENTS: LazyLock<broadcast::Sender<LiveEvent>> =
+    LazyLock::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+static BACKLOG: LazyLock<Mutex<VecDeque<LogRecord>>> =
+    LazyLock::new(|| Mutex::new(VecDeque::with_capacity(BACKLOG_SIZE)));
+
+pub fn subscribe() -> broadcast::Receiver<LiveEvent> {
+    EVENTS.subscribe()
+}
+
+/// The most recent log records, oldest first
+pub fn recent_logs() -> Vec<LogRecord> {
+    BACKLOG
+        .lock()
+        .map(|backlog| backlog.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+pub fn publish_command(entry: serde_json::Value) {
+    // Sending only fails when nobody is listening
+    let _ = EVENTS.send(LiveEvent::Command(entry));
+}
+
+/// Log sink that fans records out to live subscribers, combined with the terminal and file loggers
+pub struct BroadcastLogger {
+    level: LevelFilter,
+    ignored: Vec<&'static str>,
+}
+
+impl BroadcastLogger {
+    /// Targets starting with any of `ignored` are skipped, like `add_filter_ignore_str`
+    pub fn new(level: LevelFilter, ignored: &[&'static str]) -> Box<Self> {
+        Box::new(BroadcastLogger {
+            level,
+            ignored: ignored.to_vec(),
+        })
+    }
+}
+
+impl Log for BroadcastLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level
+            && !self
+                .ignored
+                .iter()
+                .any(|ignored| metadata.target().starts_with(ignored))
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let record = LogRecord {
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            level: record.level().to_string(),
+            module: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        // Never log from here, it would recurse into this logger
+        if let Ok(mut backlog) = BACKLOG.lock() {
+            if backlog.len() == BACKLOG_SIZE {
+                backlog.pop_front();
+            }
+            backlog.push_back(record.clone());
+        }
+        let _ = EVENTS.send(LiveEvent::Log(record));
+    }
+
+    fn flush(&self) {}
+}
+
+impl SharedLogger for BroadcastLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        None
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
@@ -1,11 +1,17 @@
 mod ai;
+mod api_tokens;
 mod anime;
+mod audit;
+mod backup;
 mod colors;
+mod command_history;
 mod config;
 mod db;
 mod env;
+mod events;
 mod fixembed;
 mod language;
+mod metrics;
 mod music;
 mod permissions;
 mod random;
@@ -38,7 +44,11 @@ static REACTION_CONFIG: OnceLock<config::Config> = OnceLock::new();
 static START_TIME: OnceLock<Instant> = OnceLock::new();
 static COMMANDS_LIST: OnceLock<Vec<serde_json::Value>> = OnceLock::new();
 static EMOJIS_LIST: OnceLock<Vec<serde_json::Value>> = OnceLock::new();
+static COMMAND_PATHS: OnceLock<Vec<String>> = OnceLock::new();
 static DISCORD_HTTP: OnceLock<std::sync::Arc<serenity::Http>> = OnceLock::new();
+static DISCORD_CACHE: OnceLock<std::sync::Arc<serenity::Cache>> = OnceLock::new();
+static SHARD_MANAGER: OnceLock<std::sync::Arc<serenity::ShardManager>> = OnceLock::new();
+static SONGBIRD: OnceLock<std::sync::Arc<songbird::Songbird>> = OnceLock::new();
 
 const TABLE: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("tags");
 const AI_CONTEXT: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("context");
@@ -46,12 +56,46 @@ const STREAMS: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("s
 const LIVE_STREAMS_STATE: redb::TableDefinition<&str, &str> =
     redb::TableDefinition::new("live_streams_state");
 const PERMISSIONS: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("permissions");
+const COMMAND_POLICIES: redb::TableDefinition<&str, &str> =
+    redb::TableDefinition::new("command_policies");
 const HISTORY: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("history");
+const AUDIT_LOG: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("audit_log");
+const SESSIONS: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("sessions");
+const API_TOKENS: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("api_tokens");
 const AYDY: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("aydy");
 const PAPERS: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("papers");
 const TICKETS: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("tickets");
 const ACTIVE_TICKETS: redb::TableDefinition<&str, &str> =
     redb::TableDefinition::new("active_tickets");
+const MUSIC_HISTORY: redb::TableDefinition<&str, &str> =
+    redb::TableDefinition::new("music_history");
+const SOUNDS: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("sounds");
+const LOCAL_TRACKS: redb::TableDefinition<&str, &str> =
+    redb::TableDefinition::new("local_tracks");
+// Bookkeeping like the schema version
+const META: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("meta");
+
+// Every table above, so they can be created at startup and reported on together
+const ALL_TABLES: [redb::TableDefinition<&str, &str>; 18] = [
+    TABLE,
+    AI_CONTEXT,
+    STREAMS,
+    LIVE_STREAMS_STATE,
+    PERMISSIONS,
+    COMMAND_POLICIES,
+    HISTORY,
+    AUDIT_LOG,
+    SESSIONS,
+    API_TOKENS,
+    AYDY,
+    PAPERS,
+    TICKETS,
+    ACTIVE_TICKETS,
+    MUSIC_HISTORY,
+    SOUNDS,
+    LOCAL_TRACKS,
+    META,
+];
 
 fn split_string_chunks(long_string: &str, chunk_size: usize) -> Vec<String> {
     long_string
@@ -221,30 +265,30 @@ async fn event_handler(
     Ok(())
 }
 
-async fn discordbot(subsys: &mut tokio_graceful_shutdown::SubsystemHandle) -> Result<()> {
-    log::info!("Initializing clients and databases...");
-
+/// Open the database, creating missing tables and migrating it to the current schema
+fn init_database() -> &'static redb::Database {
     KV_DATABASE.get_or_init(|| {
         let mut db = redb::Database::create("storage.db").unwrap();
 
         // Make sure tables exist
         {
             let tx = db.begin_write().unwrap();
-            tx.open_table(TABLE).unwrap();
-            tx.open_table(AI_CONTEXT).unwrap();
-            tx.open_table(STREAMS).unwrap();
-            tx.open_table(PERMISSIONS).unwrap();
-            tx.open_table(HISTORY).unwrap();
-            tx.open_table(AYDY).unwrap();
-            tx.open_table(LIVE_STREAMS_STATE).unwrap();
-            tx.open_table(PAPERS).unwrap();
-            tx.open_table(TICKETS).unwrap();
-            tx.open_table(ACTIVE_TICKETS).unwrap();
+            for table in ALL_TABLES {
+                tx.open_table(table).unwrap();
+            }
             tx.commit().unwrap();
         }
+        // Refuse to start on data this build doesn't understand
+        db::run_migrations(&db).expect("Failed to migrate database");
         db.compact().unwrap();
         db
-    });
+    })
+}
+
+async fn discordbot(subsys: &mut tokio_graceful_shutdown::SubsystemHandle) -> Result<()> {
+    log::info!("Initializing clients and databases...");
+
+    init_database();
 
     HTTP_CLIENT.get_or_init(reqwest::Client::new);
     REACTION_CONFIG.get_or_init(|| config::load_config().unwrap());
@@ -270,6 +314,7 @@ async fn discordbot(subsys: &mut tokio_graceful_shutdown::SubsystemHandle) -> Re
                 language::kanji::kanji(),
                 music::play::music(),
                 music::musicclip::yt_edit(),
+                music::soundboard::sound(),
                 streams::follow(),
                 streams::unfollow(),
                 streams::following(),
@@ -277,6 +322,10 @@ async fn discordbot(subsys: &mut tokio_graceful_shutdown::SubsystemHandle) -> Re
                 permissions::addperm(),
                 permissions::removeperm(),
                 permissions::listperms(),
+                permissions::policy::perms(),
+                audit::audit(),
+                api_tokens::apitoken(),
+                backup::admin(),
                 utility::tags::tag(),
                 utility::papers::papers(),
                 utility::profile::setstatus(),
@@ -300,9 +349,10 @@ async fn discordbot(subsys: &mut tokio_graceful_shutdown::SubsystemHandle) -> Re
                             let error_msg =
                                 format!("❌ Error: {}\nDetails:\n```\n{:?}\n```", error, error);
                             let _ = ctx.say(error_msg).await;
+                            metrics::record_command(ctx, "error");
 
                             // Log failed command to history
-                            if let Some(db) = KV_DATABASE.get() {
+                            if KV_DATABASE.get().is_some() {
                                 let timestamp = chrono::Utc::now().to_rfc3339();
                                 let user = ctx.author();
                                 let command_name = ctx.command().qualified_name.clone();
@@ -355,19 +405,9 @@ async fn discordbot(subsys: &mut tokio_graceful_shutdown::SubsystemHandle) -> Re
                                     "error": format!("{:?}", error)
                                 });
 
+                                events::publish_command(history_entry.clone());
                                 let key = format!("{}_{}", timestamp, user.id);
-
-                                if let Ok(tx) = db.begin_write() {
-                                    {
-                                        if let Ok(mut table) = tx.open_table(HISTORY) {
-                                            let _ = table.insert(
-                                                key.as_str(),
-                                                history_entry.to_string().as_str(),
-                                            );
-                                        }
-                                    }
-                                    let _ = tx.commit();
-                                }
+                                command_history::record(&key, &history_entry);
                             }
                         }
                         poise::FrameworkError::ArgumentParse { error, .. } => {
@@ -384,16 +424,18 @@ async fn discordbot(subsys: &mut tokio_graceful_shutdown::SubsystemHandle) -> Re
                     }
                 })
             },
+            command_check: Some(|ctx| Box::pin(permissions::policy::command_check(ctx))),
             event_handler: |ctx, event, framework, data| {
                 Box::pin(event_handler(ctx, event, framework, data))
             },
             post_command: |ctx| {
                 Box::pin(async move {
+                    metrics::record_command(ctx, "success");
+
                     // Log command execution to history
-                    let db = match KV_DATABASE.get() {
-                        Some(db) => db,
-                        None => return,
-                    };
+                    if KV_DATABASE.get().is_none() {
+                        return;
+                    }
 
                     let timestamp = chrono::Utc::now().to_rfc3339();
                     let user = ctx.author();
@@ -444,17 +486,9 @@ async fn discordbot(subsys: &mut tokio_graceful_shutdown::SubsystemHandle) -> Re
                         "success": true
                     });
 
+                    events::publish_command(history_entry.clone());
                     let key = format!("{}_{}", timestamp, user.id);
-
-                    if let Ok(tx) = db.begin_write() {
-                        {
-                            if let Ok(mut table) = tx.open_table(HISTORY) {
-                                let _ =
-                                    table.insert(key.as_str(), history_entry.to_string().as_str());
-                            }
-                        }
-                        let _ = tx.commit();
-                    }
+                    command_history::record(&key, &history_entry);
                 })
             },
             ..Default::default()
@@ -568,6 +602,13 @@ async fn discordbot(subsys: &mut tokio_graceful_shutdown::SubsystemHandle) -> Re
 
                 let _ = COMMANDS_LIST.set(commands_metadata);
 
+                let mut command_paths = Vec::new();
+                permissions::policy::collect_command_paths(
+                    &framework.options().commands,
+                    &mut command_paths,
+                );
+                let _ = COMMAND_PATHS.set(command_paths);
+
                 Ok(Data {})
             })
         })
@@ -584,15 +625,20 @@ async fn discordbot(subsys: &mut tokio_graceful_shutdown::SubsystemHandle) -> Re
         url: None,
     };
 
+    // Keep a handle to the voice manager so the web API can control playback
+    let songbird_manager = SONGBIRD.get_or_init(songbird::Songbird::serenity).clone();
+
     let mut client = serenity::ClientBuilder::new(token, intents)
         .framework(framework)
         .activity(activity)
-        .register_songbird()
+        .register_songbird_with(songbird_manager)
         .await
         .expect("Error Creating Client");
 
     // Store HTTP client for web API
     DISCORD_HTTP.get_or_init(|| client.http.clone());
+    DISCORD_CACHE.get_or_init(|| client.cache.clone());
+    SHARD_MANAGER.get_or_init(|| client.shard_manager.clone());
 
     // Start the stream checker background task
     let http_clone = client.http.clone();
@@ -600,6 +646,30 @@ async fn discordbot(subsys: &mut tokio_graceful_shutdown::SubsystemHandle) -> Re
         streams::start_stream_checker(http_clone).await;
     });
 
+    // Revoke time-limited permission grants once they expire
+    tokio::spawn(async move {
+        permissions::start_expiry_checker().await;
+    });
+
+    // Prune old command history, if retention is configured
+    tokio::spawn(async move {
+        command_history::start_retention_task().await;
+    });
+
+    // Snapshot the database on a schedule, if backups are configured
+    tokio::spawn(async move {
+        backup::start_backup_task().await;
+    });
+
+    // Index the local music library, if one is configured
+    if music::library::library_path().is_some() {
+        tokio::spawn(async move {
+            if let Err(e) = music::library::scan_library().await {
+                log::error!("Failed to index local music library: {}", e);
+            }
+        });
+    }
+
     // Start the AYDY checker background task
     let http_clone2 = client.http.clone();
     tokio::spawn(async move {
@@ -624,10 +694,13 @@ async fn main() -> Result<()> {
         .install_default()
         .expect("Failed to install rustls crypto provider");
 
-    let config = ConfigBuilder::new()
-        .add_filter_ignore_str("tracing")
-        .add_filter_ignore_str("serenity")
-        .build();
+    // Noisy dependencies are kept out of every log sink
+    let ignored = ["tracing", "serenity"];
+    let mut config = ConfigBuilder::new();
+    for target in ignored {
+        config.add_filter_ignore_str(target);
+    }
+    let config = config.build();
 
     CombinedLogger::init(vec![
         TermLogger::new(
@@ -645,8 +718,30 @@ async fn main() -> Result<()> {
                 .open("bot.log")
                 .unwrap(),
         ),
+        events::BroadcastLogger::new(LevelFilter::Info, &ignored),
     ])
     .unwrap();
+
+    // `--import <file>` restores a JSON export and exits without starting the bot
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--import") {
+        let path = args
+            .get(index + 1)
+            .ok_or_else(|| miette::miette!("--import needs the path of an export file"))?;
+        let summary = backup::import_file(init_database(), std::path::Path::new(path))
+            .map_err(|e| miette::miette!("Failed to import {}: {}", path, e))?;
+        log::info!(
+            "Imported {} rows into {} tables from {}",
+            summary.rows,
+            summary.tables,
+            path
+        );
+        if !summary.skipped.is_empty() {
+            log::warn!("Skipped unknown tables: {}", summary.skipped.join(", "));
+        }
+        return Ok(());
+    }
+
     log::info!("Starting tokio startup...");
 
     // Setup and execute subsystem tree
@@ -0,0 +1,360 @@
+use redb::{ReadableDatabase, ReadableTableMetadata, TableHandle};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+// Upper bounds in seconds, commands mostly answer within a second but AI requests take much longer
+const COMMAND_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+const AI_BUCKETS: &[f64] = &[0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0];
+
+struct Histogram {
+    buckets: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Histogram {
+            buckets,
+            counts: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.buckets.iter().zip(self.counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Write the bucket, sum and count series, `labels` is already formatted like `a="b",`
+    fn write(&self, out: &mut String, name: &str, labels: &str) {
+        for (bound, count) in self.buckets.iter().zip(&self.counts) {
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}le=\"{}\"}} {}",
+                name, labels, bound, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}le=\"+Inf\"}} {}",
+            name, labels, self.count
+        );
+        let labels = labels.trim_end_matches(',');
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+/// Keyed by (command, outcome)
+static COMMANDS: LazyLock<Mutex<BTreeMap<(String, &'static str), Histogram>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Keyed by (kind, outcome)
+static AI_REQUESTS: LazyLock<Mutex<BTreeMap<(&'static str, &'static str), Histogram>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Keyed by (kind, direction)
+static AI_TOKENS: LazyLock<Mutex<BTreeMap<(&'static str, &'static str), u64>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Keyed by (platform, result)
+static STREAM_POLLS: LazyLock<Mutex<BTreeMap<(String, &'static str), u64>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Record a finished command, `outcome` is `success` or `error`.
+/// Timed from the invoking message or interaction, so it includes time spent in checks.
+pub fn record_command(ctx: crate::Context<'_>, outcome: &'static str) {
+    let duration = (chrono::Utc::now() - *ctx.created_at())
+        .to_std()
+        .unwrap_or_default();
+    COMMANDS
+        .lock()
+        .unwrap()
+        .entry((ctx.command().qualified_name.clone(), outcome))
+        .or_insert_with(|| Histogram::new(COMMAND_BUCKETS))
+        .observe(duration.as_secs_f64());
+}
+
+/// Record a request to the AI backend, `tokens` is (prompt, completion) when the backend reports it
+pub fn record_ai_request(
+    kind: &'static str,
+    outcome: &'static str,
+    duration: Duration,
+    tokens: Option<(u64, u64)>,
+) {
+    AI_REQUESTS
+        .lock()
+        .unwrap()
+        .entry((kind, outcome))
+        .or_insert_with(|| Histogram::new(AI_BUCKETS))
+        .observe(duration.as_secs_f64());
+
+    if let Some((prompt, completion)) = tokens {
+        let mut counts = AI_TOKENS.lock().unwrap();
+        *counts.entry((kind, "prompt")).or_default() += prompt;
+        *counts.entry((kind, "completion")).or_default() += completion;
+    }
+}
+
+/// Record a single channel check by the stream checker, `result` is `live`, `offline` or `error`
+pub fn record_stream_poll(platform: &str, result: &'static str) {
+    *STREAM_POLLS
+        .lock()
+        .unwrap()
+        .entry((platform.to_string(), result))
+        .or_default() += 1;
+}
+
+/// Escape a label value for the text exposition format
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Everything we track, rendered in the Prometheus text format
+pub async fn render() -> String {
+    let mut out = String::new();
+
+    write_header(
+        &mut out,
+        "bot_uptime_seconds",
+        "gauge",
+        "Seconds since the bot connected to Discord.",
+    );
+    let uptime = crate::START_TIME
+        .get()
+        .map(|start| start.elapsed().as_secs_f64())
+        .unwrap_or(0.0);
+    let _ = writeln!(out, "bot_uptime_seconds {}", uptime);
+
+    {
+        let commands = COMMANDS.lock().unwrap();
+        write_header(
+            &mut out,
+            "bot_commands_total",
+            "counter",
+            "Commands run, by command and outcome.",
+        );
+        for ((command, outcome), histogram) in commands.iter() {
+            let _ = writeln!(
+                out,
+                "bot_commands_total{{command=\"{}\",outcome=\"{}\"}} {}",
+                escape(command),
+                outcome,
+                histogram.count
+            );
+        }
+        write_header(
+            &mut out,
+            "bot_command_duration_seconds",
+            "histogram",
+            "Time from invocation until the command finished.",
+        );
+        for ((command, outcome), histogram) in commands.iter() {
+            let labels = format!("command=\"{}\",outcome=\"{}\",", escape(command), outcome);
+            histogram.write(&mut out, "bot_command_duration_seconds", &labels);
+        }
+    }
+
+    if let Some(shard_manager) = crate::SHARD_MANAGER.get() {
+        write_header(
+            &mut out,
+            "bot_gateway_latency_seconds",
+            "gauge",
+            "Heartbeat round trip of each gateway shard.",
+        );
+        for (shard_id, runner) in shard_manager.runners.lock().await.iter() {
+            if let Some(latency) = runner.latency {
+                let _ = writeln!(
+                    out,
+                    "bot_gateway_latency_seconds{{shard=\"{}\"}} {}",
+                    shard_id,
+                    latency.as_secs_f64()
+                );
+            }
+        }
+    }
+
+    if let Some(cache) = crate::DISCORD_CACHE.get() {
+        let guilds = cache.guilds();
+        let members: u64 = guilds
+            .iter()
+            .filter_map(|guild_id| cache.guild(*guild_id).map(|guild| guild.member_count))
+            .sum();
+        write_header(&mut out, "bot_guilds", "gauge", "Guilds the bot is in.");
+        let _ = writeln!(out, "bot_guilds {}", guilds.len());
+        write_header(
+            &mut out,
+            "bot_members",
+            "gauge",
+            "Members across all guilds the bot is in.",
+        );
+        let _ = writeln!(out, "bot_members {}", members);
+    }
+
+    if let Some(songbird) = crate::SONGBIRD.get() {
+        // Don't hold the manager's map across the awaits below
+        let calls: Vec<_> = songbird.iter().map(|(_, call)| call).collect();
+        let mut connected = 0;
+        for call in calls {
+            if call.lock().await.current_channel().is_some() {
+                connected += 1;
+            }
+        }
+        write_header(
+            &mut out,
+            "bot_voice_connections",
+            "gauge",
+            "Voice channels the bot is connected to.",
+        );
+        let _ = writeln!(out, "bot_voice_connections {}", connected);
+    }
+
+    {
+        let requests = AI_REQUESTS.lock().unwrap();
+        write_header(
+            &mut out,
+            "bot_ai_request_duration_seconds",
+            "histogram",
+            "Requests to the AI backend, by kind and outcome.",
+        );
+        for ((kind, outcome), histogram) in requests.iter() {
+            let labels = format!("kind=\"{}\",outcome=\"{}\",", kind, outcome);
+            histogram.write(&mut out, "bot_ai_request_duration_seconds", &labels);
+        }
+    }
+    write_header(
+        &mut out,
+        "bot_ai_tokens_total",
+        "counter",
+        "Tokens processed by the AI backend, by kind and direction.",
+    );
+    for ((kind, direction), count) in AI_TOKENS.lock().unwrap().iter() {
+        let _ = writeln!(
+            out,
+            "bot_ai_tokens_total{{kind=\"{}\",direction=\"{}\"}} {}",
+            kind, direction, count
+        );
+    }
+
+    write_header(
+        &mut out,
+        "bot_stream_polls_total",
+        "counter",
+        "Channel checks by the stream checker, by platform and result.",
+    );
+    for ((platform, result), count) in STREAM_POLLS.lock().unwrap().iter() {
+        let _ = writeln!(
+            out,
+            "bot_stream_polls_total{{platform=\"{}\",result=\"{}\"}} {}",
+            escape(platform),
+            result,
+            count
+        );
+    }
+    let health = crate::streams::health::snapshot();
+    write_header(
+        &mut out,
+        "bot_stream_checker_run_seconds",
+        "gauge",
+        "How long the last stream checker run took.",
+    );
+    let _ = writeln!(
+        out,
+        "bot_stream_checker_run_seconds {}",
+        health.last_run_ms as f64 / 1000.0
+    );
+    write_header(
+        &mut out,
+        "bot_stream_followed_channels",
+        "gauge",
+        "Distinct channels the stream checker follows.",
+    );
+    let _ = writeln!(
+        out,
+        "bot_stream_followed_channels {}",
+        health.followed_channels
+    );
+    write_header(
+        &mut out,
+        "bot_stream_backing_off",
+        "gauge",
+        "Channels skipped after repeated failures.",
+    );
+    let _ = writeln!(out, "bot_stream_backing_off {}", health.backing_off.len());
+    write_header(
+        &mut out,
+        "bot_stream_rate_limited",
+        "gauge",
+        "Whether checks for a platform are paused by its rate limit.",
+    );
+    for platform in &health.rate_limited {
+        let _ = writeln!(
+            out,
+            "bot_stream_rate_limited{{platform=\"{}\"}} 1",
+            escape(platform)
+        );
+    }
+
+    write_table_sizes(&mut out);
+    out
+}
+
+fn write_table_sizes(out: &mut String) {
+    let Ok(tx) = crate::db::get_db().and_then(|db| {
+        db.begin_read()
+            .map_err(|e| crate::db::DbError::ReadTransaction(e.to_string()))
+    }) else {
+        return;
+    };
+
+    write_header(
+        out,
+        "bot_db_table_rows",
+        "gauge",
+        "Rows in each database table.",
+    );
+    let mut stored_bytes = Vec::new();
+    for definition in crate::ALL_TABLES {
+        let Ok(table) = tx.open_table(definition) else {
+            continue;
+        };
+        if let Ok(rows) = table.len() {
+            let _ = writeln!(
+                out,
+                "bot_db_table_rows{{table=\"{}\"}} {}",
+                definition.name(),
+                rows
+            );
+        }
+        if let Ok(stats) = table.stats() {
+            stored_bytes.push((definition.name().to_string(), stats.stored_bytes()));
+        }
+    }
+    write_header(
+        out,
+        "bot_db_table_bytes",
+        "gauge",
+        "Bytes stored in each database table, excluding metadata.",
+    );
+    for (name, bytes) in stored_bytes {
+        let _ = writeln!(out, "bot_db_table_bytes{{table=\"{}\"}} {}", name, bytes);
+    }
+}
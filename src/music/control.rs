@@ -0,0 +1,236 @@
+use std::sync::{Arc, LazyLock};
+
+use crate::music::history::HistoryRecorder;
+use crate::{Error, HTTP_CLIENT, SONGBIRD};
+
+use poise::serenity_prelude as serenity;
+use serde::Serialize;
+use songbird::events::{Event, EventData, TrackEvent};
+use songbird::input::{AuxMetadata, Compose, Input, YoutubeDl};
+use songbird::tracks::{PlayMode, Track, TrackHandle, TrackQueue};
+use tokio::sync::broadcast;
+
+/// Metadata attached to every queued track so the queue can be shown outside Discord
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TrackMetadata {
+    pub title: String,
+    pub artist: Option<String>,
+    pub source_url: Option<String>,
+    pub thumbnail: Option<String>,
+    pub duration_secs: Option<u64>,
+    pub requested_by: String,
+    pub requester_id: Option<u64>,
+}
+
+impl TrackMetadata {
+    pub fn from_aux(metadata: &AuxMetadata, requested_by: &str, requester_id: Option<u64>) -> Self {
+        Self {
+            title: metadata
+                .title
+                .clone()
+                .unwrap_or_else(|| "Untitled".to_string()),
+            artist: metadata.artist.clone(),
+            source_url: metadata.source_url.clone(),
+            thumbnail: metadata.thumbnail.clone(),
+            duration_secs: metadata.duration.map(|d| d.as_secs()),
+            requested_by: requested_by.to_string(),
+            requester_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TrackStatus {
+    pub uuid: String,
+    #[serde(flatten)]
+    pub metadata: TrackMetadata,
+    pub state: String,
+    pub volume: f32,
+    pub position_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PlaybackState {
+    pub guild_id: String,
+    pub connected: bool,
+    pub now_playing: Option<TrackStatus>,
+    pub queue: Vec<TrackStatus>,
+}
+
+// Guild ids whose playback state changed, so listeners can refresh immediately
+static MUSIC_EVENTS: LazyLock<broadcast::Sender<u64>> = LazyLock::new(|| broadcast::channel(64).0);
+
+pub fn subscribe() -> broadcast::Receiver<u64> {
+    MUSIC_EVENTS.subscribe()
+}
+
+pub fn notify(guild_id: serenity::GuildId) {
+    // Nobody listening is not an error
+    let _ = MUSIC_EVENTS.send(guild_id.get());
+}
+
+fn play_mode_str(mode: &PlayMode) -> &'static str {
+    match mode {
+        PlayMode::Play => "playing",
+        PlayMode::Pause => "paused",
+        PlayMode::Stop => "stopped",
+        PlayMode::End => "ended",
+        _ => "errored",
+    }
+}
+
+/// Resolve a URL or search query into a YouTube input and its metadata
+pub async fn resolve(query: String) -> Result<(YoutubeDl<'static>, AuxMetadata), Error> {
+    let http_client = HTTP_CLIENT.get().unwrap();
+    let mut src = if query.starts_with("http") {
+        YoutubeDl::new(http_client.clone(), query)
+    } else {
+        YoutubeDl::new_search(http_client.clone(), query)
+    };
+    let metadata = src.aux_metadata().await?;
+    Ok((src, metadata))
+}
+
+/// Queue an input on a call with its metadata attached.
+/// The play is recorded in the listening history once the track actually starts.
+pub async fn enqueue_on_call(
+    call: &mut songbird::Call,
+    guild_id: serenity::GuildId,
+    input: Input,
+    metadata: TrackMetadata,
+) -> TrackHandle {
+    let mut track = Track::new_with_data(input, Arc::new(metadata.clone()));
+    track.events.add_event(
+        EventData::new(
+            Event::Track(TrackEvent::Play),
+            HistoryRecorder { guild_id, metadata },
+        ),
+        std::time::Duration::ZERO,
+    );
+    call.enqueue(track).await
+}
+
+async fn guild_queue(guild_id: serenity::GuildId) -> Option<TrackQueue> {
+    let manager = SONGBIRD.get()?;
+    let handler_lock = manager.get(guild_id)?;
+    let handler = handler_lock.lock().await;
+    Some(handler.queue().clone())
+}
+
+async fn track_status(handle: &TrackHandle) -> Option<TrackStatus> {
+    let info = handle.get_info().await.ok()?;
+    Some(TrackStatus {
+        uuid: handle.uuid().to_string(),
+        metadata: (*handle.data::<TrackMetadata>()).clone(),
+        state: play_mode_str(&info.playing).to_string(),
+        volume: info.volume,
+        position_secs: info.position.as_secs(),
+    })
+}
+
+/// Snapshot of what is playing and queued in a guild
+pub async fn playback_state(guild_id: serenity::GuildId) -> PlaybackState {
+    let mut state = PlaybackState {
+        guild_id: guild_id.to_string(),
+        connected: false,
+        now_playing: None,
+        queue: Vec::new(),
+    };
+
+    let Some(queue) = guild_queue(guild_id).await else {
+        return state;
+    };
+    state.connected = true;
+
+    for (index, handle) in queue.current_queue().iter().enumerate() {
+        if let Some(status) = track_status(handle).await {
+            if index == 0 {
+                state.now_playing = Some(status);
+            } else {
+                state.queue.push(status);
+            }
+        }
+    }
+
+    state
+}
+
+/// Queue a track from a URL or search query
+pub async fn enqueue(
+    guild_id: serenity::GuildId,
+    query: String,
+    requested_by: &str,
+) -> Result<TrackMetadata, Error> {
+    let manager = SONGBIRD.get().ok_or("Voice client not ready")?;
+    let handler_lock = manager.get(guild_id).ok_or("Not in a voice channel")?;
+
+    let (src, aux) = resolve(query).await?;
+    let metadata = TrackMetadata::from_aux(&aux, requested_by, None);
+    {
+        let mut handler = handler_lock.lock().await;
+        enqueue_on_call(&mut handler, guild_id, src.into(), metadata.clone()).await;
+    }
+
+    notify(guild_id);
+    Ok(metadata)
+}
+
+pub async fn skip(guild_id: serenity::GuildId) -> Result<(), Error> {
+    let queue = guild_queue(guild_id)
+        .await
+        .ok_or("Not in a voice channel")?;
+    queue.skip()?;
+    notify(guild_id);
+    Ok(())
+}
+
+pub async fn pause(guild_id: serenity::GuildId) -> Result<(), Error> {
+    let queue = guild_queue(guild_id)
+        .await
+        .ok_or("Not in a voice channel")?;
+    queue.pause()?;
+    notify(guild_id);
+    Ok(())
+}
+
+pub async fn resume(guild_id: serenity::GuildId) -> Result<(), Error> {
+    let queue = guild_queue(guild_id)
+        .await
+        .ok_or("Not in a voice channel")?;
+    queue.resume()?;
+    notify(guild_id);
+    Ok(())
+}
+
+/// Set the volume of the current track, clamped to the same range as the player buttons
+pub async fn set_volume(guild_id: serenity::GuildId, volume: f32) -> Result<f32, Error> {
+    let queue = guild_queue(guild_id)
+        .await
+        .ok_or("Not in a voice channel")?;
+    let current = queue.current().ok_or("Nothing is playing")?;
+    let volume = volume.clamp(0.0, 2.0);
+    current.set_volume(volume)?;
+    notify(guild_id);
+    Ok(volume)
+}
+
+/// Move an upcoming track within the queue.
+/// Positions are 1-based and exclude the currently playing track.
+pub async fn reorder(guild_id: serenity::GuildId, from: usize, to: usize) -> Result<(), Error> {
+    let queue = guild_queue(guild_id)
+        .await
+        .ok_or("Not in a voice channel")?;
+
+    queue.modify_queue(|tracks| {
+        if from == 0 || to == 0 || from >= tracks.len() || to >= tracks.len() {
+            return Err::<(), Error>("Queue position out of range".into());
+        }
+        if let Some(track) = tracks.remove(from) {
+            tracks.insert(to, track);
+        }
+        Ok(())
+    })?;
+
+    notify(guild_id);
+    Ok(())
+}
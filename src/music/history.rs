@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+
+use crate::db::Table;
+use crate::env::FOOTER_URL;
+use crate::music::control::TrackMetadata;
+use crate::{Context, Error, MUSIC_HISTORY, colors};
+
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use songbird::events::{Event, EventContext, EventHandler as VoiceEventHandler};
+
+/// Keyed `<guild id>_<rfc3339 time>_<uuid>`
+const PLAYS: Table<MusicPlay> = Table::new(MUSIC_HISTORY);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MusicPlay {
+    pub guild_id: u64,
+    pub requested_by: String,
+    pub requester_id: Option<u64>,
+    pub title: String,
+    pub source_url: Option<String>,
+    pub duration_secs: Option<u64>,
+    pub timestamp: i64, // Unix timestamp
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrackCount {
+    pub title: String,
+    pub source_url: Option<String>,
+    pub plays: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RequesterCount {
+    pub requested_by: String,
+    pub requester_id: Option<u64>,
+    pub plays: usize,
+}
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum StatsPeriod {
+    #[name = "Past week"]
+    Week,
+    #[name = "Past month"]
+    Month,
+    #[name = "All time"]
+    AllTime,
+}
+
+impl StatsPeriod {
+    /// Oldest timestamp included in the period
+    pub fn cutoff(&self) -> Option<i64> {
+        let now = chrono::Utc::now();
+        match self {
+            StatsPeriod::Week => Some((now - chrono::Duration::days(7)).timestamp()),
+            StatsPeriod::Month => Some((now - chrono::Duration::days(30)).timestamp()),
+            StatsPeriod::AllTime => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StatsPeriod::Week => "Past week",
+            StatsPeriod::Month => "Past month",
+            StatsPeriod::AllTime => "All time",
+        }
+    }
+}
+
+impl std::str::FromStr for StatsPeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "week" => Ok(StatsPeriod::Week),
+            "month" => Ok(StatsPeriod::Month),
+            "all" | "alltime" => Ok(StatsPeriod::AllTime),
+            _ => Err(format!("Invalid period: {}", s)),
+        }
+    }
+}
+
+/// Records a track in the listening history the first time it starts playing
+pub struct HistoryRecorder {
+    pub guild_id: serenity::GuildId,
+    pub metadata: TrackMetadata,
+}
+
+#[serenity::async_trait]
+impl VoiceEventHandler for HistoryRecorder {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        if let Err(e) = record_play(self.guild_id, &self.metadata) {
+            log::error!("Failed to record music history: {}", e);
+        }
+        crate::music::control::notify(self.guild_id);
+
+        // Resuming after a pause fires the same event, only count the first start
+        Some(Event::Cancel)
+    }
+}
+
+pub fn record_play(guild_id: serenity::GuildId, metadata: &TrackMetadata) -> Result<(), Error> {
+    let now = chrono::Utc::now();
+    let play = MusicPlay {
+        guild_id: guild_id.get(),
+        requested_by: metadata.requested_by.clone(),
+        requester_id: metadata.requester_id,
+        title: metadata.title.clone(),
+        source_url: metadata.source_url.clone(),
+        duration_secs: metadata.duration_secs,
+        timestamp: now.timestamp(),
+    };
+
+    // Guild first so a guild's plays are contiguous and ordered by time
+    let key = crate::db::key(&[&guild_id, &now.to_rfc3339(), &uuid::Uuid::new_v4()]);
+    PLAYS.insert(&key, &play)?;
+    Ok(())
+}
+
+/// All plays for a guild since the cutoff, oldest first
+pub fn load_plays(guild_id: u64, since: Option<i64>) -> Result<Vec<MusicPlay>, Error> {
+    let plays = PLAYS
+        .prefix(&crate::db::key(&[&guild_id, &""]))?
+        .into_iter()
+        .map(|(_, play)| play)
+        .filter(|play| since.is_none_or(|cutoff| play.timestamp >= cutoff))
+        .collect();
+    Ok(plays)
+}
+
+/// Most recent plays for a guild, newest first
+pub fn recent_plays(guild_id: u64, limit: usize) -> Result<Vec<MusicPlay>, Error> {
+    let mut plays = load_plays(guild_id, None)?;
+    plays.reverse();
+    plays.truncate(limit);
+    Ok(plays)
+}
+
+pub fn top_tracks(plays: &[MusicPlay], limit: usize) -> Vec<TrackCount> {
+    // Group by URL when known so retitled uploads count together
+    let mut counts: HashMap<String, TrackCount> = HashMap::new();
+    for play in plays {
+        let key = play
+            .source_url
+            .clone()
+            .unwrap_or_else(|| play.title.clone());
+        counts
+            .entry(key)
+            .or_insert_with(|| TrackCount {
+                title: play.title.clone(),
+                source_url: play.source_url.clone(),
+                plays: 0,
+            })
+            .plays += 1;
+    }
+
+    let mut counts: Vec<TrackCount> = counts.into_values().collect();
+    counts.sort_by(|a, b| b.plays.cmp(&a.plays).then_with(|| a.title.cmp(&b.title)));
+    counts.truncate(limit);
+    counts
+}
+
+pub fn top_requesters(plays: &[MusicPlay], limit: usize) -> Vec<RequesterCount> {
+    let mut counts: HashMap<String, RequesterCount> = HashMap::new();
+    for play in plays {
+        let key = play
+            .requester_id
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| play.requested_by.clone());
+        counts
+            .entry(key)
+            .or_insert_with(|| RequesterCount {
+                requested_by: play.requested_by.clone(),
+                requester_id: play.requester_id,
+                plays: 0,
+            })
+            .plays += 1;
+    }
+
+    let mut counts: Vec<RequesterCount> = counts.into_values().collect();
+    counts.sort_by(|a, b| {
+        b.plays
+            .cmp(&a.plays)
+            .then_with(|| a.requested_by.cmp(&b.requested_by))
+    });
+    counts.truncate(limit);
+    counts
+}
+
+fn format_track(title: &str, source_url: &Option<String>) -> String {
+    match source_url {
+        Some(url) => format!("[{}]({})", title, url),
+        None => title.to_string(),
+    }
+}
+
+// Embed fields and descriptions have hard length limits, drop whole lines that don't fit
+fn join_lines(lines: Vec<String>, max_len: usize) -> String {
+    let mut joined = String::new();
+    for line in lines {
+        if joined.chars().count() + line.chars().count() + 1 > max_len {
+            break;
+        }
+        if !joined.is_empty() {
+            joined.push('\n');
+        }
+        joined.push_str(&line);
+    }
+    joined
+}
+
+fn format_requester(requested_by: &str, requester_id: Option<u64>) -> String {
+    match requester_id {
+        Some(id) => format!("<@{}>", id),
+        None => requested_by.to_string(),
+    }
+}
+
+/// Show recently played tracks in this server
+#[poise::command(prefix_command, slash_command, guild_only, category = "Music")]
+pub async fn history(
+    ctx: Context<'_>,
+    #[description = "Number of tracks to show (default 10, max 25)"] count: Option<usize>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let count = count.unwrap_or(10).clamp(1, 25);
+    let plays = recent_plays(guild_id.get(), count)?;
+
+    let description = if plays.is_empty() {
+        "Nothing has been played yet.".to_string()
+    } else {
+        let lines = plays
+            .iter()
+            .map(|play| {
+                format!(
+                    "<t:{}:R> {} — {}",
+                    play.timestamp,
+                    format_track(&play.title, &play.source_url),
+                    format_requester(&play.requested_by, play.requester_id)
+                )
+            })
+            .collect();
+        join_lines(lines, 4096)
+    };
+
+    let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
+    let embed = serenity::CreateEmbed::new()
+        .title("Recently Played")
+        .description(description)
+        .footer(footer)
+        .color(colors::PEACH)
+        .timestamp(serenity::model::Timestamp::now());
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Show the most played tracks and top requesters in this server
+#[poise::command(prefix_command, slash_command, guild_only, category = "Music")]
+pub async fn top(
+    ctx: Context<'_>,
+    #[description = "Time period (default: past week)"] period: Option<StatsPeriod>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let period = period.unwrap_or(StatsPeriod::Week);
+    let plays = load_plays(guild_id.get(), period.cutoff())?;
+
+    let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
+    let mut embed = serenity::CreateEmbed::new()
+        .title(format!("Music Stats — {}", period.label()))
+        .footer(footer)
+        .color(colors::PEACH)
+        .timestamp(serenity::model::Timestamp::now());
+
+    if plays.is_empty() {
+        embed = embed.description("Nothing has been played in this period.");
+    } else {
+        let tracks: Vec<String> = top_tracks(&plays, 10)
+            .iter()
+            .enumerate()
+            .map(|(i, track)| {
+                format!(
+                    "{}. {} ({} plays)",
+                    i + 1,
+                    format_track(&track.title, &track.source_url),
+                    track.plays
+                )
+            })
+            .collect();
+        let tracks = join_lines(tracks, 1024);
+        let requesters: Vec<String> = top_requesters(&plays, 5)
+            .iter()
+            .enumerate()
+            .map(|(i, requester)| {
+                format!(
+                    "{}. {} ({} plays)",
+                    i + 1,
+                    format_requester(&requester.requested_by, requester.requester_id),
+                    requester.plays
+                )
+            })
+            .collect();
+        let requesters = join_lines(requesters, 1024);
+
+        embed = embed
+            .description(format!("**{}** tracks played", plays.len()))
+            .field("Top Tracks", tracks, false)
+            .field("Top Requesters", requesters, false);
+    }
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
@@ -0,0 +1,320 @@
+use std::path::{Path, PathBuf};
+
+use crate::db::Table;
+use crate::env::FOOTER_URL;
+use crate::music::control::{self, TrackMetadata};
+use crate::{Context, Error, LOCAL_TRACKS, REACTION_CONFIG, colors};
+
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::Hint;
+
+const AUDIO_EXTENSIONS: [&str; 9] = [
+    "mp3", "flac", "m4a", "aac", "ogg", "opus", "wav", "mka", "alac",
+];
+
+/// Keyed by track id, rewritten as a whole on every index
+const TRACKS: Table<LocalTrack> = Table::new(LOCAL_TRACKS);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalTrack {
+    pub id: String,
+    /// Path relative to the library root
+    pub path: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration_secs: Option<u64>,
+}
+
+impl LocalTrack {
+    pub fn display_name(&self) -> String {
+        match self.artist {
+            Some(ref artist) => format!("{} - {}", artist, self.title),
+            None => self.title.clone(),
+        }
+    }
+
+    fn search_text(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.title,
+            self.artist.as_deref().unwrap_or_default(),
+            self.album.as_deref().unwrap_or_default(),
+            self.path
+        )
+        .to_lowercase()
+    }
+}
+
+pub fn library_path() -> Option<PathBuf> {
+    REACTION_CONFIG
+        .get()?
+        .music
+        .as_ref()?
+        .library_path
+        .as_ref()
+        .map(PathBuf::from)
+}
+
+// Short id so tracks fit in Discord's 100 character autocomplete values.
+// SHA-256 rather than `DefaultHasher`, whose output may change between Rust releases.
+fn track_id(relative_path: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(relative_path.as_bytes()));
+    digest[..16].to_string()
+}
+
+fn collect_audio_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("Failed to read music directory {}: {}", dir.display(), e);
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // `file_type` doesn't follow symlinks, so linked directories (and any cycles through them) are skipped
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            collect_audio_files(&path, files);
+        } else if path.is_file()
+            && path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        {
+            files.push(path);
+        }
+    }
+}
+
+fn apply_tags(revision: &MetadataRevision, track: &mut LocalTrack) {
+    for tag in revision.tags() {
+        match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => track.title = tag.value.to_string(),
+            Some(StandardTagKey::Artist) => track.artist = Some(tag.value.to_string()),
+            Some(StandardTagKey::AlbumArtist) if track.artist.is_none() => {
+                track.artist = Some(tag.value.to_string())
+            }
+            Some(StandardTagKey::Album) => track.album = Some(tag.value.to_string()),
+            _ => {}
+        }
+    }
+}
+
+/// Read tags and duration from a file, falling back to the file name for the title
+fn read_track(root: &Path, path: &Path) -> Option<LocalTrack> {
+    let relative = path.strip_prefix(root).ok()?.to_string_lossy().into_owned();
+    let mut track = LocalTrack {
+        id: track_id(&relative),
+        path: relative,
+        title: path.file_stem()?.to_string_lossy().into_owned(),
+        artist: None,
+        album: None,
+        duration_secs: None,
+    };
+
+    let file = std::fs::File::open(path).ok()?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let mut probed = match symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    ) {
+        Ok(probed) => probed,
+        Err(e) => {
+            log::debug!("Could not probe {}: {}", path.display(), e);
+            return Some(track);
+        }
+    };
+
+    // Tags can live before the container (e.g. ID3) or inside it
+    if let Some(mut metadata) = probed.metadata.get()
+        && let Some(revision) = metadata.skip_to_latest()
+    {
+        apply_tags(revision, &mut track);
+    }
+    if let Some(revision) = probed.format.metadata().skip_to_latest() {
+        apply_tags(revision, &mut track);
+    }
+
+    if let Some(default_track) = probed.format.default_track() {
+        let params = &default_track.codec_params;
+        track.duration_secs = match (params.n_frames, params.time_base, params.sample_rate) {
+            (Some(frames), Some(time_base), _) => Some(time_base.calc_time(frames).seconds),
+            (Some(frames), None, Some(rate)) if rate > 0 => Some(frames / rate as u64),
+            _ => None,
+        };
+    }
+
+    Some(track)
+}
+
+/// Re-index the configured library directory, replacing the previous index
+pub async fn scan_library() -> Result<usize, Error> {
+    let root = library_path().ok_or("No music library_path configured")?;
+    if !root.is_dir() {
+        return Err(format!("Music library {} is not a directory", root.display()).into());
+    }
+
+    log::info!("Indexing local music library at {}", root.display());
+    let tracks = tokio::task::spawn_blocking(move || {
+        let mut files = Vec::new();
+        collect_audio_files(&root, &mut files);
+        files
+            .iter()
+            .filter_map(|path| read_track(&root, path))
+            .collect::<Vec<_>>()
+    })
+    .await?;
+
+    TRACKS.replace_all(tracks.iter().map(|track| (track.id.as_str(), track)))?;
+
+    log::info!("Indexed {} local tracks", tracks.len());
+    Ok(tracks.len())
+}
+
+/// Tracks whose tags or path contain every word of the query, best matches first
+pub fn search(query: &str, limit: usize) -> Result<Vec<LocalTrack>, Error> {
+    let query = query.trim().to_lowercase();
+    let words: Vec<&str> = query.split_whitespace().collect();
+
+    let mut matches: Vec<(f64, LocalTrack)> = TRACKS
+        .values()?
+        .into_iter()
+        .filter(|track| {
+            let text = track.search_text();
+            words.iter().all(|word| text.contains(word))
+        })
+        .map(|track| {
+            let score = strsim::jaro_winkler(&query, &track.display_name().to_lowercase());
+            (score, track)
+        })
+        .collect();
+
+    matches.sort_by(|a, b| b.0.total_cmp(&a.0));
+    Ok(matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, track)| track)
+        .collect())
+}
+
+async fn autocomplete_local(_ctx: Context<'_>, partial: &str) -> Vec<serenity::AutocompleteChoice> {
+    search(partial, 25)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|track| {
+            let name: String = track.display_name().chars().take(100).collect();
+            serenity::AutocompleteChoice::new(name, track.id)
+        })
+        .collect()
+}
+
+/// Queue a track from the local music library
+#[poise::command(prefix_command, slash_command, guild_only, category = "Music")]
+pub async fn local(
+    ctx: Context<'_>,
+    #[description = "Title, artist or album to search for"]
+    #[autocomplete = "autocomplete_local"]
+    query: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
+
+    let Some(root) = library_path() else {
+        ctx.say("❌ No local music library is configured.").await?;
+        return Ok(());
+    };
+
+    // Autocomplete submits the track id, typed queries fall back to the best search match
+    let track = match TRACKS.get(&query)? {
+        Some(track) => Some(track),
+        None => search(&query, 1)?.into_iter().next(),
+    };
+    let Some(track) = track else {
+        ctx.say(format!("❌ No local tracks match **{}**.", query))
+            .await?;
+        return Ok(());
+    };
+
+    let manager = songbird::get(ctx.serenity_context())
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .clone();
+    let Some(handler_lock) = manager.get(guild_id) else {
+        let embed = serenity::CreateEmbed::new()
+            .description("I'm not in a channel.")
+            .footer(footer)
+            .color(colors::CRUST)
+            .timestamp(serenity::model::Timestamp::now());
+        ctx.send(poise::CreateReply::default().embed(embed)).await?;
+        return Ok(());
+    };
+
+    let metadata = TrackMetadata {
+        title: track.title.clone(),
+        artist: track.artist.clone(),
+        source_url: None,
+        thumbnail: None,
+        duration_secs: track.duration_secs,
+        requested_by: ctx.author().name.clone(),
+        requester_id: Some(ctx.author().id.get()),
+    };
+    let input = songbird::input::File::new(root.join(&track.path)).into();
+    {
+        let mut handler = handler_lock.lock().await;
+        control::enqueue_on_call(&mut handler, guild_id, input, metadata).await;
+    }
+    control::notify(guild_id);
+
+    let duration = track
+        .duration_secs
+        .map(|secs| format!("{}:{:02}", secs / 60, secs % 60))
+        .unwrap_or_else(|| "Unknown".to_string());
+    let mut embed = serenity::CreateEmbed::new()
+        .title(&track.title)
+        .description("Queued")
+        .field("Duration", duration, true)
+        .footer(footer)
+        .color(colors::PEACH)
+        .timestamp(serenity::model::Timestamp::now());
+    if let Some(ref artist) = track.artist {
+        embed = embed.field("Artist", artist, true);
+    }
+    if let Some(ref album) = track.album {
+        embed = embed.field("Album", album, true);
+    }
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Re-index the local music library
+#[poise::command(
+    prefix_command,
+    slash_command,
+    check = "crate::permissions::check_admin",
+    category = "Music"
+)]
+pub async fn rescan(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer().await?;
+    let count = scan_library().await?;
+    ctx.say(format!("✅ Indexed **{}** local tracks", count))
+        .await?;
+    Ok(())
+}
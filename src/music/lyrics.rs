@@ -0,0 +1,369 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::anime::sonarr::paginate;
+use crate::music::control::TrackMetadata;
+use crate::{Context, Error, HTTP_CLIENT, REACTION_CONFIG};
+
+use poise::serenity_prelude as serenity;
+use serde::Deserialize;
+use songbird::tracks::{PlayMode, TrackHandle};
+
+const DEFAULT_LYRICS_API: &str = "https://lrclib.net";
+const PAGE_LENGTH: usize = 2000;
+
+#[derive(Debug, Clone)]
+pub struct LyricLine {
+    pub time: Duration,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Lyrics {
+    pub title: String,
+    pub artist: Option<String>,
+    pub source: String,
+    pub plain: String,
+    pub synced: Vec<LyricLine>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct LrclibTrack {
+    track_name: String,
+    artist_name: Option<String>,
+    #[serde(default)]
+    instrumental: bool,
+    plain_lyrics: Option<String>,
+    synced_lyrics: Option<String>,
+}
+
+fn lyrics_config() -> Option<&'static crate::config::LyricsConfig> {
+    REACTION_CONFIG.get()?.music.as_ref()?.lyrics.as_ref()
+}
+
+fn api_url() -> String {
+    lyrics_config()
+        .and_then(|config| config.api_url.clone())
+        .unwrap_or_else(|| DEFAULT_LYRICS_API.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn parse_timestamp(tag: &str) -> Option<Duration> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes: u64 = minutes.trim().parse().ok()?;
+    let seconds: f64 = seconds.trim().parse().ok()?;
+    // Remote text, so negative, NaN or huge values skip the line instead of panicking
+    let seconds = Duration::try_from_secs_f64(seconds).ok()?;
+    Duration::from_secs(minutes.checked_mul(60)?).checked_add(seconds)
+}
+
+/// Parse LRC formatted lyrics, a line may carry several `[mm:ss.xx]` timestamps
+pub fn parse_lrc(lrc: &str) -> Vec<LyricLine> {
+    let mut lines = Vec::new();
+    for raw in lrc.lines() {
+        let mut rest = raw.trim();
+        let mut times = Vec::new();
+        while let Some(stripped) = rest.strip_prefix('[') {
+            let Some((tag, after)) = stripped.split_once(']') else {
+                break;
+            };
+            // Metadata tags like [ar:Artist] don't parse as timestamps and are skipped
+            if let Some(time) = parse_timestamp(tag) {
+                times.push(time);
+            }
+            rest = after;
+        }
+        for time in times {
+            lines.push(LyricLine {
+                time,
+                text: rest.trim().to_string(),
+            });
+        }
+    }
+    lines.sort_by_key(|line| line.time);
+    lines
+}
+
+fn plain_from_synced(lines: &[LyricLine]) -> String {
+    lines
+        .iter()
+        .map(|line| line.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+async fn fetch_lrclib(title: &str, artist: Option<&str>) -> Result<Option<LrclibTrack>, Error> {
+    let client = HTTP_CLIENT.get().unwrap();
+    let base = api_url();
+    let user_agent = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+    // Exact lookup first, artist names from YouTube are often the channel so fall back to search
+    if let Some(artist) = artist {
+        let response = client
+            .get(format!("{}/api/get", base))
+            .header(reqwest::header::USER_AGENT, user_agent)
+            .query(&[("track_name", title), ("artist_name", artist)])
+            .send()
+            .await?;
+        if response.status().is_success() {
+            return Ok(Some(response.json().await?));
+        }
+    }
+
+    let query = match artist {
+        Some(artist) => format!("{} {}", artist, title),
+        None => title.to_string(),
+    };
+    for q in [query.as_str(), title] {
+        let results: Vec<LrclibTrack> = client
+            .get(format!("{}/api/search", base))
+            .header(reqwest::header::USER_AGENT, user_agent)
+            .query(&[("q", q)])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(track) = results
+            .into_iter()
+            .find(|track| track.instrumental || track.plain_lyrics.is_some())
+        {
+            return Ok(Some(track));
+        }
+    }
+
+    Ok(None)
+}
+
+fn normalize_file_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Look for `Artist - Title.lrc` or `Title.lrc` in the configured LRC directory
+async fn find_local_lrc(title: &str, artist: Option<&str>) -> Result<Option<Lyrics>, Error> {
+    let Some(dir) = lyrics_config().and_then(|config| config.lrc_path.as_ref()) else {
+        return Ok(None);
+    };
+
+    let mut candidates = vec![normalize_file_name(title)];
+    if let Some(artist) = artist {
+        candidates.insert(0, normalize_file_name(&format!("{} - {}", artist, title)));
+    }
+
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    let mut found: Option<(usize, PathBuf)> = None;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if !path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("lrc"))
+        {
+            continue;
+        }
+        let Some(stem) = path.file_stem().map(|stem| stem.to_string_lossy()) else {
+            continue;
+        };
+        let stem = normalize_file_name(&stem);
+        if let Some(rank) = candidates.iter().position(|candidate| *candidate == stem)
+            && found.as_ref().is_none_or(|(best, _)| rank < *best)
+        {
+            found = Some((rank, path));
+        }
+    }
+
+    let Some((_, path)) = found else {
+        return Ok(None);
+    };
+    let contents = tokio::fs::read_to_string(&path).await?;
+    let synced = parse_lrc(&contents);
+    // Files without timestamps are treated as plain lyrics
+    let plain = if synced.is_empty() {
+        contents
+    } else {
+        plain_from_synced(&synced)
+    };
+
+    Ok(Some(Lyrics {
+        title: title.to_string(),
+        artist: artist.map(str::to_string),
+        source: "Local LRC".to_string(),
+        plain,
+        synced,
+    }))
+}
+
+/// Find lyrics for a track, trying the API first and the local LRC directory after
+pub async fn find_lyrics(title: &str, artist: Option<&str>) -> Result<Option<Lyrics>, Error> {
+    match fetch_lrclib(title, artist).await {
+        Ok(Some(track)) => {
+            let synced = track
+                .synced_lyrics
+                .as_deref()
+                .map(parse_lrc)
+                .unwrap_or_default();
+            let plain = match track.plain_lyrics {
+                Some(plain) => plain,
+                None if track.instrumental => "🎵 Instrumental".to_string(),
+                None => plain_from_synced(&synced),
+            };
+            return Ok(Some(Lyrics {
+                title: track.track_name,
+                artist: track.artist_name,
+                source: "LRCLIB".to_string(),
+                plain,
+                synced,
+            }));
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Lyrics lookup failed for {}: {}", title, e),
+    }
+
+    find_local_lrc(title, artist).await
+}
+
+/// Split lyrics into pages on line boundaries
+fn lyric_pages(lyrics: &str) -> Vec<String> {
+    let mut pages = vec![String::new()];
+    for line in lyrics.lines() {
+        let page = pages.last_mut().unwrap();
+        if !page.is_empty() && page.len() + line.len() + 1 > PAGE_LENGTH {
+            pages.push(String::new());
+        }
+        let page = pages.last_mut().unwrap();
+        if !page.is_empty() {
+            page.push('\n');
+        }
+        page.push_str(line);
+    }
+    pages
+        .into_iter()
+        .flat_map(|page| crate::split_string_chunks(&page, PAGE_LENGTH))
+        .collect()
+}
+
+/// Post each synced line to the thread when the track reaches it
+async fn stream_synced_lines(
+    http: Arc<serenity::Http>,
+    thread_id: serenity::ChannelId,
+    handle: TrackHandle,
+    lines: Vec<LyricLine>,
+) {
+    for line in lines {
+        loop {
+            let Ok(info) = handle.get_info().await else {
+                return;
+            };
+            if !matches!(info.playing, PlayMode::Play | PlayMode::Pause) {
+                return;
+            }
+            if info.position >= line.time {
+                break;
+            }
+            // Poll so pauses and seeks are followed
+            let wait = (line.time - info.position).min(Duration::from_secs(1));
+            tokio::time::sleep(wait).await;
+        }
+
+        if line.text.is_empty() {
+            continue;
+        }
+        if let Err(e) = thread_id.say(&http, &line.text).await {
+            log::warn!("Failed to post synced lyric: {}", e);
+            return;
+        }
+    }
+}
+
+/// Show lyrics for the currently playing track
+#[poise::command(prefix_command, slash_command, guild_only, category = "Music")]
+pub async fn lyrics(
+    ctx: Context<'_>,
+    #[description = "Post time-synced lines in a thread as the song plays"] synced: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let manager = songbird::get(ctx.serenity_context())
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .clone();
+
+    let current = match manager.get(guild_id) {
+        Some(handler_lock) => handler_lock.lock().await.queue().current(),
+        None => None,
+    };
+    let Some(handle) = current else {
+        ctx.say("❌ Nothing is playing.").await?;
+        return Ok(());
+    };
+    let metadata = handle.data::<TrackMetadata>();
+
+    ctx.defer().await?;
+    let Some(lyrics) = find_lyrics(&metadata.title, metadata.artist.as_deref()).await? else {
+        ctx.say(format!("❌ No lyrics found for **{}**.", metadata.title))
+            .await?;
+        return Ok(());
+    };
+
+    let title = match lyrics.artist {
+        Some(ref artist) => format!("{} - {}", artist, lyrics.title),
+        None => lyrics.title.clone(),
+    };
+
+    if synced.unwrap_or(false) {
+        if lyrics.synced.is_empty() {
+            ctx.say(format!("ℹ️ {} has no time-synced lyrics.", lyrics.source))
+                .await?;
+        } else {
+            let position = handle
+                .get_info()
+                .await
+                .map(|info| info.position)
+                .unwrap_or_default();
+            // Lines that already played are skipped rather than posted all at once
+            let upcoming: Vec<LyricLine> = lyrics
+                .synced
+                .iter()
+                .filter(|line| line.time >= position)
+                .cloned()
+                .collect();
+
+            let message = ctx
+                .channel_id()
+                .say(ctx, format!("🎤 Synced lyrics for **{}**", title))
+                .await?;
+            let thread_name: String = format!("Lyrics: {}", title).chars().take(100).collect();
+            let thread = ctx
+                .channel_id()
+                .create_thread_from_message(
+                    ctx,
+                    message.id,
+                    serenity::CreateThread::new(thread_name)
+                        .auto_archive_duration(serenity::AutoArchiveDuration::OneHour),
+                )
+                .await?;
+
+            let http = ctx.serenity_context().http.clone();
+            tokio::spawn(stream_synced_lines(
+                http,
+                thread.id,
+                handle.clone(),
+                upcoming,
+            ));
+        }
+    }
+
+    let pages = if lyrics.plain.trim().is_empty() {
+        vec!["No lyrics text available.".to_string()]
+    } else {
+        lyric_pages(&lyrics.plain)
+    };
+    let pages: Vec<&str> = pages.iter().map(String::as_str).collect();
+    paginate(ctx, &pages, &format!("{} ({})", title, lyrics.source)).await?;
+
+    Ok(())
+}
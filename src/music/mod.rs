@@ -1,2 +1,7 @@
+pub mod control;
+pub mod history;
+pub mod library;
+pub mod lyrics;
 pub mod musicclip;
 pub mod play;
+pub mod soundboard;
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::{Context, Error, colors};
 
@@ -8,28 +8,238 @@ use uuid::Uuid;
 use yt_dlp::Youtube;
 use yt_dlp::client::deps::Libraries;
 
-async fn edit_audio_clip(
-    input_path: &std::path::Path,
-    output_path: &std::path::Path,
-    start_time: u32,
-    duration: u32,
-) -> Result<(), Error> {
-    let start_time_str = format!("{}", start_time);
-    let duration_str = format!("{}", duration);
+const MAX_AUDIO_CLIP_SECS: f64 = 300.0;
+const MAX_VIDEO_CLIP_SECS: f64 = 60.0;
+const DEFAULT_CLIP_SECS: f64 = 30.0;
+
+// Upload limit for servers without boosts
+const DEFAULT_UPLOAD_LIMIT: u64 = 10 * 1024 * 1024;
+
+// How many times to re-encode at a lower bitrate before giving up on the size limit
+const MAX_REENCODE_ATTEMPTS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter)]
+pub enum ClipFormat {
+    #[name = "mp3"]
+    Mp3,
+    #[name = "ogg"]
+    Ogg,
+    #[name = "opus"]
+    Opus,
+    #[name = "wav"]
+    Wav,
+    #[name = "mp4 (video)"]
+    Mp4,
+    #[name = "webm (video)"]
+    Webm,
+}
+
+impl ClipFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ClipFormat::Mp3 => "mp3",
+            ClipFormat::Ogg => "ogg",
+            ClipFormat::Opus => "opus",
+            ClipFormat::Wav => "wav",
+            ClipFormat::Mp4 => "mp4",
+            ClipFormat::Webm => "webm",
+        }
+    }
+
+    pub fn is_video(&self) -> bool {
+        matches!(self, ClipFormat::Mp4 | ClipFormat::Webm)
+    }
+
+    fn audio_codec(&self) -> &'static str {
+        match self {
+            ClipFormat::Mp3 => "libmp3lame",
+            ClipFormat::Ogg => "libvorbis",
+            ClipFormat::Opus | ClipFormat::Webm => "libopus",
+            ClipFormat::Wav => "pcm_s16le",
+            ClipFormat::Mp4 => "aac",
+        }
+    }
+
+    // Bitrate used for the first encode, in kbps
+    fn default_audio_bitrate(&self) -> u32 {
+        match self {
+            ClipFormat::Mp3 | ClipFormat::Ogg => 192,
+            ClipFormat::Opus => 128,
+            ClipFormat::Mp4 => 128,
+            ClipFormat::Webm => 96,
+            ClipFormat::Wav => 0,
+        }
+    }
+}
+
+/// Options for a single ffmpeg encode of a clip
+#[derive(Debug, Clone)]
+pub struct ClipOptions {
+    pub start: f64,
+    pub duration: f64,
+    pub format: ClipFormat,
+    pub fade_in: Option<f64>,
+    pub fade_out: Option<f64>,
+    pub normalize: bool,
+    /// Audio bitrate override in kbps
+    pub audio_bitrate: Option<u32>,
+    /// Video bitrate in kbps, `None` uses constant quality
+    pub video_bitrate: Option<u32>,
+}
+
+/// Parse `ss`, `mm:ss` or `hh:mm:ss` (seconds may have a fraction) into seconds
+pub fn parse_timestamp(input: &str) -> Result<f64, String> {
+    let parts: Vec<&str> = input.trim().split(':').collect();
+    if parts.is_empty() || parts.len() > 3 || parts.iter().any(|p| p.is_empty()) {
+        return Err(format!("Invalid timestamp: {}", input));
+    }
+
+    let mut seconds = 0.0;
+    for (i, part) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        let value: f64 = if is_last {
+            part.parse()
+                .map_err(|_| format!("Invalid timestamp: {}", input))?
+        } else {
+            part.parse::<u32>()
+                .map_err(|_| format!("Invalid timestamp: {}", input))? as f64
+        };
+        // `f64` parsing also accepts `NaN` and `inf`, which would slip past every range check below
+        if !value.is_finite() || value < 0.0 || (i > 0 && value >= 60.0) {
+            return Err(format!("Invalid timestamp: {}", input));
+        }
+        seconds = seconds * 60.0 + value;
+    }
+
+    Ok(seconds)
+}
+
+pub fn format_timestamp(seconds: f64) -> String {
+    let total = seconds as u64;
+    let (hours, minutes, secs) = (total / 3600, (total % 3600) / 60, total % 60);
+    let fraction = seconds - total as f64;
+    let secs = if fraction >= 0.05 {
+        format!("{:04.1}", secs as f64 + fraction)
+    } else {
+        format!("{:02}", secs)
+    };
+    if hours > 0 {
+        format!("{}:{:02}:{}", hours, minutes, secs)
+    } else {
+        format!("{}:{}", minutes, secs)
+    }
+}
+
+/// Build ffmpeg arguments for a clip.
+/// When `video_input` is set it is trimmed alongside `audio_input` and muxed together.
+pub fn build_ffmpeg_args(
+    audio_input: &Path,
+    video_input: Option<&Path>,
+    output: &Path,
+    options: &ClipOptions,
+) -> Vec<String> {
+    let start = format!("{:.3}", options.start);
+    let duration = format!("{:.3}", options.duration);
+    let mut args: Vec<String> = Vec::new();
+
+    // Seek before each input so only the needed part is decoded
+    if let Some(video_input) = video_input {
+        args.extend([
+            "-ss".into(),
+            start.clone(),
+            "-t".into(),
+            duration.clone(),
+            "-i".into(),
+            video_input.to_string_lossy().into_owned(),
+        ]);
+    }
+    args.extend([
+        "-ss".into(),
+        start,
+        "-t".into(),
+        duration,
+        "-i".into(),
+        audio_input.to_string_lossy().into_owned(),
+    ]);
+
+    let mut audio_filters = Vec::new();
+    let mut video_filters = Vec::new();
+    if let Some(fade_in) = options.fade_in.filter(|f| *f > 0.0) {
+        audio_filters.push(format!("afade=t=in:st=0:d={:.3}", fade_in));
+        video_filters.push(format!("fade=t=in:st=0:d={:.3}", fade_in));
+    }
+    if let Some(fade_out) = options.fade_out.filter(|f| *f > 0.0) {
+        let fade_start = (options.duration - fade_out).max(0.0);
+        audio_filters.push(format!(
+            "afade=t=out:st={:.3}:d={:.3}",
+            fade_start, fade_out
+        ));
+        video_filters.push(format!("fade=t=out:st={:.3}:d={:.3}", fade_start, fade_out));
+    }
+    if options.normalize {
+        audio_filters.push("loudnorm=I=-16:TP=-1.5:LRA=11".to_string());
+    }
+
+    if video_input.is_some() {
+        args.extend(["-map".into(), "0:v:0".into(), "-map".into(), "1:a:0".into()]);
+        // Keep clips small enough to upload
+        video_filters.push("scale=-2:'min(720,ih)'".to_string());
+        args.extend(["-vf".into(), video_filters.join(",")]);
+    } else {
+        args.push("-vn".into());
+    }
+
+    if !audio_filters.is_empty() {
+        args.extend(["-af".into(), audio_filters.join(",")]);
+    }
+
+    args.extend(["-c:a".into(), options.format.audio_codec().into()]);
+    let audio_bitrate = options
+        .audio_bitrate
+        .unwrap_or(options.format.default_audio_bitrate());
+    if audio_bitrate > 0 {
+        args.extend(["-b:a".into(), format!("{}k", audio_bitrate)]);
+    }
+
+    match options.format {
+        ClipFormat::Mp4 => {
+            args.extend([
+                "-c:v".into(),
+                "libx264".into(),
+                "-preset".into(),
+                "veryfast".into(),
+            ]);
+            args.extend(["-movflags".into(), "+faststart".into()]);
+        }
+        ClipFormat::Webm => {
+            args.extend([
+                "-c:v".into(),
+                "libvpx-vp9".into(),
+                "-deadline".into(),
+                "realtime".into(),
+                "-cpu-used".into(),
+                "8".into(),
+            ]);
+        }
+        _ => {}
+    }
+    if options.format.is_video() {
+        match options.video_bitrate {
+            Some(bitrate) => args.extend(["-b:v".into(), format!("{}k", bitrate)]),
+            None if options.format == ClipFormat::Mp4 => {
+                args.extend(["-crf".into(), "23".into()]);
+            }
+            None => args.extend(["-crf".into(), "32".into(), "-b:v".into(), "0".into()]),
+        }
+    }
 
+    args.extend(["-y".into(), output.to_string_lossy().into_owned()]);
+    args
+}
+
+async fn run_ffmpeg(args: &[String]) -> Result<(), Error> {
     let status = tokio::process::Command::new("ffmpeg")
-        .args([
-            "-ss",
-            &start_time_str,
-            "-t",
-            &duration_str,
-            "-i",
-            input_path.to_str().unwrap(),
-            "-acodec",
-            "mp3",
-            "-y",
-            output_path.to_str().unwrap(),
-        ])
+        .args(args)
         .status()
         .await?;
 
@@ -40,14 +250,152 @@ async fn edit_audio_clip(
     Ok(())
 }
 
+/// Work out bitrates (kbps) that fit `size_limit` bytes for the given duration.
+/// Leaves some headroom for container overhead.
+fn bitrates_for_limit(size_limit: u64, duration: f64, format: ClipFormat) -> (u32, Option<u32>) {
+    let total_kbps = (size_limit as f64 * 8.0 * 0.9 / 1000.0 / duration.max(1.0)) as u32;
+    if format.is_video() {
+        let audio = format.default_audio_bitrate().min(total_kbps / 4).max(32);
+        (audio, Some(total_kbps.saturating_sub(audio).max(100)))
+    } else {
+        (total_kbps.clamp(32, format.default_audio_bitrate()), None)
+    }
+}
+
+/// Encode the clip, re-encoding at lower bitrates until it fits under the upload limit
+async fn encode_clip(
+    audio_input: &Path,
+    video_input: Option<&Path>,
+    output: &Path,
+    options: &ClipOptions,
+    size_limit: u64,
+) -> Result<u64, Error> {
+    let mut options = options.clone();
+    run_ffmpeg(&build_ffmpeg_args(
+        audio_input,
+        video_input,
+        output,
+        &options,
+    ))
+    .await?;
+    let mut size = tokio::fs::metadata(output).await?.len();
+
+    let mut budget = size_limit;
+    for attempt in 0..MAX_REENCODE_ATTEMPTS {
+        if size <= size_limit {
+            return Ok(size);
+        }
+        if options.format == ClipFormat::Wav {
+            return Err(format!(
+                "WAV clip is {:.1} MB which is over the {:.0} MB upload limit, try a compressed format or a shorter clip",
+                size as f64 / 1_048_576.0,
+                size_limit as f64 / 1_048_576.0
+            )
+            .into());
+        }
+
+        let (audio_bitrate, video_bitrate) =
+            bitrates_for_limit(budget, options.duration, options.format);
+        log::info!(
+            "Clip is {} bytes (limit {}), re-encoding attempt {} at audio {}k video {:?}k",
+            size,
+            size_limit,
+            attempt + 1,
+            audio_bitrate,
+            video_bitrate
+        );
+        options.audio_bitrate = Some(audio_bitrate);
+        options.video_bitrate = video_bitrate;
+        run_ffmpeg(&build_ffmpeg_args(
+            audio_input,
+            video_input,
+            output,
+            &options,
+        ))
+        .await?;
+        size = tokio::fs::metadata(output).await?.len();
+
+        // Encoders overshoot, aim lower each round
+        budget = budget * 85 / 100;
+    }
+
+    if size <= size_limit {
+        Ok(size)
+    } else {
+        Err(format!(
+            "Clip is still {:.1} MB after re-encoding, try a shorter clip",
+            size as f64 / 1_048_576.0
+        )
+        .into())
+    }
+}
+
+fn upload_limit(ctx: &Context<'_>) -> u64 {
+    let tier = ctx.guild().map(|guild| guild.premium_tier);
+    match tier {
+        Some(serenity::PremiumTier::Tier2) => 50 * 1024 * 1024,
+        Some(serenity::PremiumTier::Tier3) => 100 * 1024 * 1024,
+        _ => DEFAULT_UPLOAD_LIMIT,
+    }
+}
+
+/// Removes downloaded and generated files when the command finishes, even on errors
+struct TempFiles(Vec<PathBuf>);
+
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            if let Err(e) = std::fs::remove_file(path)
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                log::warn!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 #[poise::command(prefix_command, slash_command, category = "Music")]
 pub async fn yt_edit(
     ctx: Context<'_>,
     #[description = "YouTube URL of the video to clip from"] url: String,
-    #[description = "Start time in seconds"] start_time: u32,
-    #[description = "Duration of the clip in seconds (default 30)"] duration: Option<u32>,
+    #[description = "Start time (ss, mm:ss or hh:mm:ss)"] start: String,
+    #[description = "End time (ss, mm:ss or hh:mm:ss), defaults to 30 seconds after start"]
+    end: Option<String>,
+    #[description = "Output format (default mp3)"] format: Option<ClipFormat>,
+    #[description = "Fade in length in seconds"] fade_in: Option<f64>,
+    #[description = "Fade out length in seconds"] fade_out: Option<f64>,
+    #[description = "Normalize loudness"] normalize: Option<bool>,
 ) -> Result<(), Error> {
-    let duration = duration.unwrap_or(30).min(30);
+    let format = format.unwrap_or(ClipFormat::Mp3);
+    let start_time = parse_timestamp(&start)?;
+    let max_duration = if format.is_video() {
+        MAX_VIDEO_CLIP_SECS
+    } else {
+        MAX_AUDIO_CLIP_SECS
+    };
+    let duration = match end {
+        Some(ref end) => {
+            let end_time = parse_timestamp(end)?;
+            if end_time <= start_time {
+                ctx.say("❌ End time must be after the start time.").await?;
+                return Ok(());
+            }
+            end_time - start_time
+        }
+        None => DEFAULT_CLIP_SECS,
+    };
+    if duration > max_duration {
+        ctx.say(format!(
+            "❌ Clips can be at most {} seconds long for {}.",
+            max_duration,
+            format.extension()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
 
     let libraries_dir = PathBuf::from("/usr/bin");
     let output_dir = PathBuf::from("output");
@@ -66,35 +414,93 @@ pub async fn yt_edit(
     log::info!("Fetched video info: {:?}", video.title);
     log::debug!("Fetched video info: {:?}", video.extractor_info);
 
-    let audio_format = video.best_audio_format().unwrap();
-
-    log::info!("Using audio format: {:?}", audio_format);
-
     // Generate random filename to avoid conflicts
     let random_id = Uuid::new_v4();
-    let audio_filename = format!("clip_{}.mp3", random_id);
-    let clip_filename = format!("edited_clip_{}.mp3", random_id);
+    let mut temp_files = TempFiles(Vec::new());
 
-    log::info!("Downloading audio to temporary file: {}", audio_filename);
+    let audio_format = video
+        .best_audio_format()
+        .ok_or("No audio format available")?;
+    log::info!("Using audio format: {:?}", audio_format);
 
+    let audio_filename = format!("clip_{}.audio", random_id);
+    log::info!("Downloading audio to temporary file: {}", audio_filename);
+    temp_files.0.push(output_dir.join(&audio_filename));
     let audio_path: PathBuf = fetcher
         .download_format(audio_format, &audio_filename)
         .await?;
+    temp_files.0.push(audio_path.clone());
+
+    let video_path = if format.is_video() {
+        let video_format = video
+            .best_video_format()
+            .ok_or("No video format available")?;
+        log::info!("Using video format: {:?}", video_format);
 
+        let video_filename = format!("clip_{}.video", random_id);
+        temp_files.0.push(output_dir.join(&video_filename));
+        let path: PathBuf = fetcher
+            .download_format(video_format, &video_filename)
+            .await?;
+        temp_files.0.push(path.clone());
+        Some(path)
+    } else {
+        None
+    };
+
+    let clip_filename = format!("edited_clip_{}.{}", random_id, format.extension());
     let clip_path = output_dir.join(&clip_filename);
-    log::info!("Editing audio clip to file: {}", clip_path.display());
-    edit_audio_clip(&audio_path, &clip_path, start_time, duration).await?;
+    temp_files.0.push(clip_path.clone());
+
+    let options = ClipOptions {
+        start: start_time,
+        duration,
+        format,
+        fade_in,
+        fade_out,
+        normalize: normalize.unwrap_or(false),
+        audio_bitrate: None,
+        video_bitrate: None,
+    };
+    log::info!(
+        "Editing clip to file: {} ({:?})",
+        clip_path.display(),
+        options
+    );
+    let size = encode_clip(
+        &audio_path,
+        video_path.as_deref(),
+        &clip_path,
+        &options,
+        upload_limit(&ctx),
+    )
+    .await?;
+
+    let mut effects = Vec::new();
+    if let Some(fade_in) = options.fade_in.filter(|f| *f > 0.0) {
+        effects.push(format!("fade in {}s", fade_in));
+    }
+    if let Some(fade_out) = options.fade_out.filter(|f| *f > 0.0) {
+        effects.push(format!("fade out {}s", fade_out));
+    }
+    if options.normalize {
+        effects.push("normalized".to_string());
+    }
 
     let reply = {
-        let embed = serenity::CreateEmbed::new()
+        let mut embed = serenity::CreateEmbed::new()
             .title(video.title.to_string())
             .description(format!(
-                "Here is your audio clip from {} (start: {}s, duration: {}s)",
-                video.title, start_time, duration
+                "Here is your {} clip from {} ({} - {})",
+                format.extension(),
+                video.title,
+                format_timestamp(start_time),
+                format_timestamp(start_time + duration)
             ))
             .fields(vec![
                 ("Original Video", format!("[Link]({})", url), false),
-                ("Duration", format!("{} seconds", duration), true),
+                ("Duration", format!("{:.1} seconds", duration), true),
+                ("Size", format!("{:.2} MB", size as f64 / 1_048_576.0), true),
                 ("Views", format!("{}", video.view_count), true),
                 ("Likes", format!("{}", video.like_count.unwrap_or(0)), true),
                 (
@@ -110,10 +516,13 @@ pub async fn yt_edit(
                 "Requested by {}",
                 ctx.author().name
             )));
+        if !effects.is_empty() {
+            embed = embed.field("Effects", effects.join(", "), true);
+        }
 
         poise::CreateReply::default().embed(embed)
     };
-    // Reply with the audio clip attached
+    // Reply with the clip attached, temp files are removed when this returns
     ctx.send(reply.attachment(CreateAttachment::path(&clip_path).await?))
         .await?;
 
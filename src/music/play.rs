@@ -2,10 +2,13 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use crate::env::FOOTER_URL;
-use crate::{Context, Error, HTTP_CLIENT, colors};
+use crate::music::control::{self, TrackMetadata};
+use crate::music::history::{history, top};
+use crate::music::library::{local, rescan};
+use crate::music::lyrics::lyrics;
+use crate::{Context, Error, colors};
 
 use songbird::events::{Event, EventContext, EventHandler as VoiceEventHandler, TrackEvent};
-use songbird::input::{Compose, YoutubeDl};
 use songbird::tracks::PlayMode;
 
 use poise::serenity_prelude as serenity;
@@ -13,7 +16,9 @@ use poise::serenity_prelude as serenity;
 #[poise::command(
     prefix_command,
     slash_command,
-    subcommands("join", "leave", "queue"),
+    subcommands(
+        "join", "leave", "queue", "local", "lyrics", "history", "top", "rescan"
+    ),
     subcommand_required,
     category = "Music"
 )]
@@ -25,6 +30,7 @@ struct TrackErrorNotifier;
 struct TrackStartNotifier {
     pub ctx: serenity::Context,
     pub http: Arc<serenity::Http>,
+    pub guild_id: serenity::GuildId,
     pub channel_id: serenity::ChannelId,
     pub embed: serenity::CreateEmbed,
 }
@@ -113,8 +119,10 @@ impl VoiceEventHandler for TrackStartNotifier {
                             ),
                         )
                         .await;
+                    control::notify(self.guild_id);
                     break;
                 }
+                control::notify(self.guild_id);
                 let _ = press
                     .create_response(
                         &self.ctx,
@@ -201,6 +209,7 @@ pub async fn leave(ctx: Context<'_>) -> Result<(), Error> {
             let handler = handler_lock.lock().await;
             let queue = handler.queue();
             queue.stop();
+            control::notify(guild_id);
             color = colors::GREEN;
             description = "Left voice channel and cleared the queue".to_string()
         }
@@ -224,9 +233,6 @@ pub async fn leave(ctx: Context<'_>) -> Result<(), Error> {
 
 #[poise::command(prefix_command, slash_command, category = "Music")]
 pub async fn queue(ctx: Context<'_>, url: String) -> Result<(), Error> {
-    let do_search = !url.starts_with("http");
-    let http_client = HTTP_CLIENT.get().unwrap();
-
     let manager = songbird::get(ctx.serenity_context())
         .await
         .expect("Songbird Voice client placed in at initialisation.")
@@ -238,12 +244,9 @@ pub async fn queue(ctx: Context<'_>, url: String) -> Result<(), Error> {
     if let Some(handler_lock) = manager.get(guild_id) {
         let mut handler = handler_lock.lock().await;
 
-        let mut src = if do_search {
-            YoutubeDl::new_search(http_client.clone(), url)
-        } else {
-            YoutubeDl::new(http_client.clone(), url)
-        };
-        let metadata = src.aux_metadata().await?;
+        let (src, metadata) = control::resolve(url).await?;
+        let track_metadata =
+            TrackMetadata::from_aux(&metadata, &ctx.author().name, Some(ctx.author().id.get()));
 
         let seconds = metadata.duration.unwrap_or_default().as_secs();
         let minutes = seconds / 60;
@@ -271,13 +274,15 @@ pub async fn queue(ctx: Context<'_>, url: String) -> Result<(), Error> {
 
         let played_embed = embed.clone().image(metadata.thumbnail.unwrap());
 
-        let track_handle = handler.enqueue_input(src.clone().into()).await;
+        let track_handle =
+            control::enqueue_on_call(&mut handler, guild_id, src.into(), track_metadata).await;
         let _ = track_handle.pause();
         let _ = track_handle.add_event(
             Event::Track(TrackEvent::Playable),
             TrackStartNotifier {
                 ctx: ctx.serenity_context().clone(),
                 http,
+                guild_id,
                 channel_id: ctx.channel_id(),
                 embed: played_embed,
             },
@@ -286,6 +291,7 @@ pub async fn queue(ctx: Context<'_>, url: String) -> Result<(), Error> {
             Some(track_handle) => track_handle.play(),
             None => Ok(()),
         };
+        control::notify(guild_id);
 
         reply = poise::CreateReply::default().embed(embed);
     } else {
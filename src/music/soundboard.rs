@@ -0,0 +1,280 @@
+use std::path::PathBuf;
+
+use crate::db::Table;
+use crate::env::FOOTER_URL;
+use crate::{Context, Error, SOUNDS, colors};
+
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+
+const SOUNDS_DIR: &str = "sounds";
+const MAX_SOUND_BYTES: u32 = 10 * 1024 * 1024;
+const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "ogg", "opus", "wav", "m4a", "flac", "webm"];
+
+/// Keyed `<guild id>_<name>`
+const SOUND_CLIPS: Table<SoundClip> = Table::new(SOUNDS);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoundClip {
+    pub name: String,
+    pub guild_id: u64,
+    pub file_name: String,
+    pub created_by: u64,
+    pub created_at: i64, // Unix timestamp
+    pub size_bytes: u64,
+    pub source: String,
+    pub plays: u64,
+}
+
+fn sound_key(guild_id: u64, name: &str) -> String {
+    crate::db::key(&[&guild_id, &name])
+}
+
+fn normalize_name(name: &str) -> Result<String, String> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() || name.len() > 32 {
+        return Err("Sound names must be 1-32 characters long".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err("Sound names may only contain letters, numbers, `-` and `_`".to_string());
+    }
+    Ok(name)
+}
+
+fn audio_extension(file_name: &str) -> Option<String> {
+    let extension = file_name.rsplit_once('.')?.1.to_lowercase();
+    AUDIO_EXTENSIONS
+        .contains(&extension.as_str())
+        .then_some(extension)
+}
+
+pub fn load_sound(guild_id: u64, name: &str) -> Result<Option<SoundClip>, Error> {
+    Ok(SOUND_CLIPS.get(&sound_key(guild_id, name))?)
+}
+
+pub fn save_sound(sound: &SoundClip) -> Result<(), Error> {
+    SOUND_CLIPS.insert(&sound_key(sound.guild_id, &sound.name), sound)?;
+    Ok(())
+}
+
+pub fn list_sounds(guild_id: u64) -> Result<Vec<SoundClip>, Error> {
+    let prefix = sound_key(guild_id, "");
+    let sounds = SOUND_CLIPS
+        .prefix(&prefix)?
+        .into_iter()
+        .map(|(_, sound)| sound)
+        .collect();
+    Ok(sounds)
+}
+
+async fn autocomplete_sound(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Vec::new();
+    };
+    let partial = partial.to_lowercase();
+    list_sounds(guild_id.get())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|sound| sound.name)
+        .filter(|name| name.contains(&partial))
+        .take(25)
+        .collect()
+}
+
+/// Find the most recent clip posted by `yt_edit` in this channel
+async fn latest_clip(ctx: Context<'_>) -> Result<Option<serenity::Attachment>, Error> {
+    let bot_id = ctx.serenity_context().cache.current_user().id;
+    let messages = ctx
+        .channel_id()
+        .messages(ctx, serenity::GetMessages::new().limit(50))
+        .await?;
+
+    Ok(messages
+        .into_iter()
+        .filter(|message| message.author.id == bot_id)
+        .flat_map(|message| message.attachments)
+        .find(|attachment| {
+            attachment.filename.starts_with("edited_clip_")
+                && audio_extension(&attachment.filename).is_some()
+        }))
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("save", "play", "list"),
+    subcommand_required,
+    guild_only,
+    category = "Music"
+)]
+pub async fn sound(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Save a sound from an uploaded audio file, or the latest yt_edit clip in this channel
+#[poise::command(prefix_command, slash_command, guild_only, category = "Music")]
+pub async fn save(
+    ctx: Context<'_>,
+    #[description = "Name of the sound"] name: String,
+    #[description = "Audio file (defaults to the latest yt_edit clip)"] file: Option<
+        serenity::Attachment,
+    >,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let name = match normalize_name(&name) {
+        Ok(name) => name,
+        Err(e) => {
+            ctx.say(format!("❌ {}", e)).await?;
+            return Ok(());
+        }
+    };
+
+    if load_sound(guild_id, &name)?.is_some() {
+        ctx.say(format!("❌ A sound named **{}** already exists.", name))
+            .await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
+
+    let (attachment, source) = match file {
+        Some(attachment) => (attachment, "upload"),
+        None => match latest_clip(ctx).await? {
+            Some(attachment) => (attachment, "yt_edit"),
+            None => {
+                ctx.say("❌ Attach an audio file or run `/yt_edit` in this channel first.")
+                    .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    let Some(extension) = audio_extension(&attachment.filename) else {
+        ctx.say(format!(
+            "❌ Unsupported file type. Use one of: {}",
+            AUDIO_EXTENSIONS.join(", ")
+        ))
+        .await?;
+        return Ok(());
+    };
+    if attachment.size > MAX_SOUND_BYTES {
+        ctx.say("❌ Sounds can be at most 10 MB.").await?;
+        return Ok(());
+    }
+
+    let bytes = attachment.download().await?;
+    let file_name = format!("{}_{}.{}", guild_id, name, extension);
+    tokio::fs::create_dir_all(SOUNDS_DIR).await?;
+    tokio::fs::write(PathBuf::from(SOUNDS_DIR).join(&file_name), &bytes).await?;
+
+    let sound = SoundClip {
+        name: name.clone(),
+        guild_id,
+        file_name,
+        created_by: ctx.author().id.get(),
+        created_at: chrono::Utc::now().timestamp(),
+        size_bytes: bytes.len() as u64,
+        source: source.to_string(),
+        plays: 0,
+    };
+    save_sound(&sound)?;
+
+    let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
+    let embed = serenity::CreateEmbed::new()
+        .title("Saved Sound")
+        .description(format!("Play it with `/sound play {}`", name))
+        .field("Name", &name, true)
+        .field("Source", source, true)
+        .field(
+            "Size",
+            format!("{:.2} MB", sound.size_bytes as f64 / 1_048_576.0),
+            true,
+        )
+        .footer(footer)
+        .color(colors::SUCCESS)
+        .timestamp(serenity::model::Timestamp::now());
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Play a saved sound over whatever is currently playing
+#[poise::command(prefix_command, slash_command, guild_only, category = "Music")]
+pub async fn play(
+    ctx: Context<'_>,
+    #[description = "Name of the sound"]
+    #[autocomplete = "autocomplete_sound"]
+    name: String,
+    #[description = "Volume (0.0 - 2.0, default 1.0)"] volume: Option<f32>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let name = name.trim().to_lowercase();
+
+    let Some(mut sound) = load_sound(guild_id.get(), &name)? else {
+        ctx.say(format!("❌ No sound named **{}**.", name)).await?;
+        return Ok(());
+    };
+
+    let manager = songbird::get(ctx.serenity_context())
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .clone();
+
+    let Some(handler_lock) = manager.get(guild_id) else {
+        ctx.say("❌ I'm not in a voice channel. Use `/music join` first.")
+            .await?;
+        return Ok(());
+    };
+
+    let path = PathBuf::from(SOUNDS_DIR).join(&sound.file_name);
+    if !path.exists() {
+        ctx.say(format!("❌ The file for **{}** is missing.", name))
+            .await?;
+        return Ok(());
+    }
+
+    {
+        let mut handler = handler_lock.lock().await;
+        // Played directly on the driver so it mixes with the queue instead of joining it
+        let track = songbird::tracks::Track::from(songbird::input::File::new(path))
+            .volume(volume.unwrap_or(1.0).clamp(0.0, 2.0));
+        handler.play(track);
+    }
+
+    sound.plays += 1;
+    save_sound(&sound)?;
+
+    ctx.say(format!("🔊 Playing **{}**", name)).await?;
+    Ok(())
+}
+
+/// List saved sounds in this server
+#[poise::command(prefix_command, slash_command, guild_only, category = "Music")]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let mut sounds = list_sounds(ctx.guild_id().unwrap().get())?;
+    sounds.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let description = if sounds.is_empty() {
+        "No sounds saved yet. Use `/sound save` to add one.".to_string()
+    } else {
+        sounds
+            .iter()
+            .map(|sound| format!("• `{}` — {} plays", sound.name, sound.plays))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("Soundboard ({})", sounds.len()))
+        .description(crate::split_string_chunks(&description, 4096).remove(0))
+        .footer(footer)
+        .color(colors::PRIMARY)
+        .timestamp(serenity::model::Timestamp::now());
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
@@ -1,7 +1,16 @@
-use crate::{Context, Error, KV_DATABASE, PERMISSIONS};
+use crate::audit::{self, AuditEntry};
+use crate::db::{Lookups, Table};
+use crate::{Context, Error, PERMISSIONS};
 use poise::serenity_prelude as serenity;
-use redb::ReadableDatabase;
+use redb::{ReadableDatabase, ReadableTable};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tokio::time::{Duration, interval};
+
+pub mod policy;
+
+/// Keyed `<guild id>_<user|role>_<id>`, legacy unscoped grants were keyed by user id alone
+const GRANTS: Table<UserPermissions> = Table::new(PERMISSIONS);
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -19,6 +28,19 @@ impl Permission {
             Permission::Trusted => "trusted",
         }
     }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Permission::Admin => 3,
+            Permission::Mod => 2,
+            Permission::Trusted => 1,
+        }
+    }
+
+    /// Higher levels include the lower ones, an admin also passes mod and trusted checks
+    pub fn satisfies(&self, required: &Permission) -> bool {
+        self.rank() >= required.rank()
+    }
 }
 
 impl std::str::FromStr for Permission {
@@ -34,69 +56,426 @@ impl std::str::FromStr for Permission {
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetKind {
+    #[default]
+    User,
+    Role,
+}
+
+impl TargetKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TargetKind::User => "user",
+            TargetKind::Role => "role",
+        }
+    }
+
+    pub fn mention(&self, id: u64) -> String {
+        match self {
+            TargetKind::User => format!("<@{}>", id),
+            TargetKind::Role => format!("<@&{}>", id),
+        }
+    }
+}
+
+/// A granted permission, optionally revoked automatically at `expires_at`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "GrantEntry")]
+struct PermissionGrant {
+    permission: Permission,
+    /// Unix timestamp, permanent when unset
+    expires_at: Option<i64>,
+}
+
+impl PermissionGrant {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+// Grants used to be stored as bare permission names
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GrantEntry {
+    Plain(Permission),
+    Timed {
+        permission: Permission,
+        #[serde(default)]
+        expires_at: Option<i64>,
+    },
+}
+
+impl From<GrantEntry> for PermissionGrant {
+    fn from(entry: GrantEntry) -> Self {
+        match entry {
+            GrantEntry::Plain(permission) => PermissionGrant {
+                permission,
+                expires_at: None,
+            },
+            GrantEntry::Timed {
+                permission,
+                expires_at,
+            } => PermissionGrant {
+                permission,
+                expires_at,
+            },
+        }
+    }
+}
+
+/// Permissions granted to a user or a role within a guild
 #[derive(Debug, Serialize, Deserialize)]
 struct UserPermissions {
-    user_id: u64,
-    permissions: Vec<Permission>,
+    /// Unset for grants made before permissions were scoped to a guild
+    #[serde(default)]
+    guild_id: Option<u64>,
+    #[serde(default)]
+    kind: TargetKind,
+    #[serde(alias = "user_id")]
+    id: u64,
+    permissions: Vec<PermissionGrant>,
+}
+
+impl UserPermissions {
+    /// How the grantee shows up in the audit log
+    fn audit_target(&self) -> String {
+        format!("{}:{}", self.kind.as_str(), self.id)
+    }
+
+    fn position(&self, permission: &Permission) -> Option<usize> {
+        self.permissions
+            .iter()
+            .position(|grant| grant.permission == *permission)
+    }
+}
+
+fn permission_key(guild_id: u64, kind: TargetKind, id: u64) -> String {
+    crate::db::key(&[&guild_id, &kind.as_str(), &id])
 }
 
-// Helper function to check if a user has permission
-pub async fn has_permission(user_id: u64, permission: Permission) -> Result<bool, Error> {
-    let key = user_id.to_string();
-    match crate::db::read_entry(PERMISSIONS, &key)? {
-        Some(value) => {
-            let perms: UserPermissions = serde_json::from_str(&value)?;
-            Ok(perms.permissions.contains(&permission))
+fn load_permissions(guild_id: u64, kind: TargetKind, id: u64) -> Result<UserPermissions, Error> {
+    match GRANTS.get(&permission_key(guild_id, kind, id))? {
+        Some(perms) => Ok(perms),
+        None => Ok(UserPermissions {
+            guild_id: Some(guild_id),
+            kind,
+            id,
+            permissions: Vec::new(),
+        }),
+    }
+}
+
+fn save_permissions(perms: &UserPermissions) -> Result<(), Error> {
+    let guild_id = perms.guild_id.ok_or("Permissions must belong to a guild")?;
+    let key = permission_key(guild_id, perms.kind, perms.id);
+    // Remove entry if no permissions left
+    if perms.permissions.is_empty() {
+        GRANTS.remove(&key)?;
+    } else {
+        GRANTS.insert(&key, perms)?;
+    }
+    Ok(())
+}
+
+/// Permissions a member holds in a guild, granted directly or through any of their roles
+pub fn member_permissions(
+    guild_id: u64,
+    user_id: u64,
+    role_ids: &[u64],
+) -> Result<Vec<Permission>, Error> {
+    let now = chrono::Utc::now().timestamp();
+    let mut permissions = Vec::new();
+    let grants = std::iter::once(load_permissions(guild_id, TargetKind::User, user_id)?)
+        .chain(
+            role_ids
+                .iter()
+                .map(|role_id| load_permissions(guild_id, TargetKind::Role, *role_id))
+                .collect::<Result<Vec<_>, _>>()?,
+        )
+        .flat_map(|perms| perms.permissions);
+    // Expired grants don't count even before the expiry task removes them
+    for grant in grants {
+        if !grant.is_expired(now) && !permissions.contains(&grant.permission) {
+            permissions.push(grant.permission);
         }
-        None => Ok(false),
     }
+    Ok(permissions)
+}
+
+// Helper function to check if a member has at least the given permission level
+pub async fn has_permission(
+    guild_id: u64,
+    user_id: u64,
+    role_ids: &[u64],
+    permission: Permission,
+) -> Result<bool, Error> {
+    if user_id == *crate::env::AUTHOR_ID {
+        return Ok(true);
+    }
+    Ok(member_permissions(guild_id, user_id, role_ids)?
+        .iter()
+        .any(|perm| perm.satisfies(&permission)))
+}
+
+/// Guilds with any grant in them, for resolving permissions outside of a guild
+pub fn granted_guilds() -> Result<Vec<u64>, Error> {
+    let mut guilds: Vec<u64> = GRANTS
+        .values()?
+        .into_iter()
+        .filter_map(|perms| perms.guild_id)
+        .collect();
+    guilds.sort_unstable();
+    guilds.dedup();
+    Ok(guilds)
+}
+
+/// Role ids of the invoking member, empty outside of guilds
+pub async fn author_role_ids(ctx: Context<'_>) -> Vec<u64> {
+    ctx.author_member()
+        .await
+        .map(|member| member.roles.iter().map(|role| role.get()).collect())
+        .unwrap_or_default()
 }
 
-// Helper function to check if user is admin or AUTHOR_ID
-async fn is_admin_or_author(ctx: Context<'_>) -> Result<bool, Error> {
+// Grants are per guild, so outside of one only AUTHOR_ID passes
+async fn author_has_permission(ctx: Context<'_>, permission: Permission) -> Result<bool, Error> {
     let user_id = ctx.author().id.get();
     if user_id == *crate::env::AUTHOR_ID {
         return Ok(true);
     }
-    has_permission(user_id, Permission::Admin).await
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(false);
+    };
+    let role_ids = author_role_ids(ctx).await;
+    has_permission(guild_id.get(), user_id, &role_ids, permission).await
 }
 
 // Check function for poise commands - must return Result<bool, Error>
 pub async fn check_admin(ctx: Context<'_>) -> Result<bool, Error> {
-    is_admin_or_author(ctx).await
+    author_has_permission(ctx, Permission::Admin).await
 }
 
 // Check function for moderator permission
 pub async fn check_mod(ctx: Context<'_>) -> Result<bool, Error> {
-    let user_id = ctx.author().id.get();
-    if user_id == *crate::env::AUTHOR_ID {
-        return Ok(true);
-    }
-    Ok(has_permission(user_id, Permission::Admin).await?
-        || has_permission(user_id, Permission::Mod).await?)
+    author_has_permission(ctx, Permission::Mod).await
 }
 
 // Check function for trusted permission
 pub async fn check_trusted(ctx: Context<'_>) -> Result<bool, Error> {
-    let user_id = ctx.author().id.get();
-    if user_id == *crate::env::AUTHOR_ID {
-        return Ok(true);
+    author_has_permission(ctx, Permission::Trusted).await
+}
+
+fn legacy_permissions<T: ReadableTable<&'static str, &'static str>>(
+    table: &T,
+) -> Result<Vec<(String, UserPermissions)>, Error> {
+    Ok(table
+        .range::<&str>(..)?
+        .filter_map(|item| {
+            let (key, value) = item.ok()?;
+            serde_json::from_str::<UserPermissions>(value.value())
+                .ok()
+                .filter(|perms| perms.guild_id.is_none())
+                .map(|perms| (key.value().to_string(), perms))
+        })
+        .collect())
+}
+
+/// Every guild the bot is in, `get_guilds` only returns one page of them
+async fn all_guilds(http: &serenity::Http) -> Result<Vec<serenity::GuildInfo>, serenity::Error> {
+    const PAGE_SIZE: u64 = 200;
+    let mut guilds: Vec<serenity::GuildInfo> = Vec::new();
+    loop {
+        let after = guilds
+            .last()
+            .map(|guild| serenity::GuildPagination::After(guild.id));
+        let page = http.get_guilds(after, Some(PAGE_SIZE)).await?;
+        let last_page = (page.len() as u64) < PAGE_SIZE;
+        guilds.extend(page);
+        if last_page {
+            return Ok(guilds);
+        }
     }
-    Ok(has_permission(user_id, Permission::Admin).await?
-        || has_permission(user_id, Permission::Mod).await?
-        || has_permission(user_id, Permission::Trusted).await?)
 }
 
-#[poise::command(
-    prefix_command,
-    slash_command,
-    check = "check_admin",
-    category = "Permissions"
-)]
-pub async fn addperm(
+/// Guilds each user with legacy grants is a member of
+pub fn lookup_member_guilds(db: &redb::Database) -> Result<Lookups, Error> {
+    let users: BTreeSet<u64> = {
+        let tx = db.begin_read()?;
+        legacy_permissions(&tx.open_table(PERMISSIONS)?)?
+            .iter()
+            .map(|(_, perms)| perms.id)
+            .collect()
+    };
+
+    let mut memberships = Lookups::new();
+    if users.is_empty() {
+        return Ok(memberships);
+    }
+    let http = serenity::Http::new(&crate::env::DISCORD_TOKEN);
+    let guilds = crate::db::block_on(all_guilds(&http))?;
+    for user_id in users {
+        let mut member_of = Vec::new();
+        for guild in &guilds {
+            match crate::db::block_on(http.get_member(guild.id, user_id.into())) {
+                Ok(_) => member_of.push(guild.id.get()),
+                Err(e) if crate::db::is_not_found(&e) => {}
+                // Anything else may work next time, so the migration waits for it
+                Err(e) => return Err(e.into()),
+            }
+        }
+        memberships.insert(user_id, member_of);
+    }
+    Ok(memberships)
+}
+
+/// Move grants made before permissions were per guild into every guild the user is a member of.
+/// Grants of users in none of the bot's guilds are kept as they are, unscoped grants never apply.
+pub fn migrate_legacy_permissions(
+    tx: &redb::WriteTransaction,
+    memberships: &Lookups,
+) -> Result<(), Error> {
+    let mut table = tx.open_table(PERMISSIONS)?;
+    for (old_key, perms) in legacy_permissions(&table)? {
+        let guilds = memberships
+            .get(&perms.id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if guilds.is_empty() {
+            log::warn!(
+                "Kept permissions of user {}, they are in none of the bot's guilds",
+                perms.id
+            );
+            continue;
+        }
+
+        for &guild_id in guilds {
+            let key = permission_key(guild_id, TargetKind::User, perms.id);
+            let existing = table
+                .get(key.as_str())?
+                .map(|value| value.value().to_string());
+            let mut scoped = match existing {
+                Some(value) => serde_json::from_str(&value)?,
+                None => UserPermissions {
+                    guild_id: Some(guild_id),
+                    kind: TargetKind::User,
+                    id: perms.id,
+                    permissions: Vec::new(),
+                },
+            };
+            for grant in &perms.permissions {
+                if scoped.position(&grant.permission).is_none() {
+                    scoped.permissions.push(grant.clone());
+                }
+            }
+            table.insert(key.as_str(), serde_json::to_string(&scoped)?.as_str())?;
+            log::info!(
+                "Migrated permissions for user {} to guild {}",
+                perms.id,
+                guild_id
+            );
+        }
+        table.remove(old_key.as_str())?;
+    }
+
+    Ok(())
+}
+
+/// Remove expired grants, returns the revoked permissions
+fn revoke_expired() -> Result<Vec<(UserPermissions, Vec<Permission>)>, Error> {
+    let now = chrono::Utc::now().timestamp();
+    let expiring = GRANTS
+        .values()?
+        .into_iter()
+        .filter(|perms| perms.guild_id.is_some())
+        .filter(|perms| perms.permissions.iter().any(|grant| grant.is_expired(now)));
+
+    let mut revoked = Vec::new();
+    for mut perms in expiring {
+        let (expired, active): (Vec<_>, Vec<_>) = perms
+            .permissions
+            .into_iter()
+            .partition(|grant| grant.is_expired(now));
+        let before = perms.permissions.clone();
+        perms.permissions = active;
+        save_permissions(&perms)?;
+        audit::record(
+            AuditEntry::system("permission.expire", &perms.audit_target())
+                .guild(perms.guild_id)
+                .before(&before)
+                .after(&perms.permissions),
+        );
+        revoked.push((
+            perms,
+            expired.into_iter().map(|grant| grant.permission).collect(),
+        ));
+    }
+    Ok(revoked)
+}
+
+pub async fn start_expiry_checker() {
+    let mut interval = interval(Duration::from_secs(60));
+
+    log::info!("Permission expiry checker started");
+
+    loop {
+        interval.tick().await;
+
+        match revoke_expired() {
+            Ok(revoked) => {
+                for (perms, expired) in revoked {
+                    for permission in expired {
+                        log::info!(
+                            "Revoked expired {} permission from {} {} in guild {}",
+                            permission.as_str(),
+                            perms.kind.as_str(),
+                            perms.id,
+                            perms.guild_id.unwrap_or_default()
+                        );
+                    }
+                }
+            }
+            Err(e) => log::error!("Error in permission expiry checker: {:?}", e),
+        }
+    }
+}
+
+/// Parse durations like `30m`, `12h`, `2d` or `1w2d`
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in input.trim().to_lowercase().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604800,
+            ' ' => continue,
+            _ => return None,
+        };
+        total = total.checked_add(number.parse::<u64>().ok()?.checked_mul(unit)?)?;
+        number.clear();
+    }
+    // A trailing number without a unit is ambiguous
+    if !number.is_empty() || total == 0 {
+        return None;
+    }
+    Some(Duration::from_secs(total))
+}
+
+/// Grant a permission to a user or role
+async fn grant_permission(
     ctx: Context<'_>,
-    #[description = "User to grant permission"] user: serenity::User,
-    #[description = "Permission level (admin, mod, trusted)"] permission: String,
+    kind: TargetKind,
+    id: u64,
+    permission: String,
+    duration: Option<String>,
 ) -> Result<(), Error> {
     let perm = match permission.parse::<Permission>() {
         Ok(p) => p,
@@ -107,36 +486,119 @@ pub async fn addperm(
         }
     };
 
-    let user_id = user.id.get();
-    let key = user_id.to_string();
+    let expires_at = match duration.as_deref().map(parse_duration) {
+        None => None,
+        Some(Some(duration)) => Some(chrono::Utc::now().timestamp() + duration.as_secs() as i64),
+        Some(None) => {
+            ctx.say("❌ Invalid duration. Use something like 30m, 12h, 3d or 1w.")
+                .await?;
+            return Ok(());
+        }
+    };
 
-    // Read current permissions
-    let mut perms = match crate::db::read_entry(PERMISSIONS, &key)? {
-        Some(value) => serde_json::from_str::<UserPermissions>(&value)?,
-        None => UserPermissions {
-            user_id,
-            permissions: Vec::new(),
-        },
+    let guild_id = ctx.guild_id().unwrap().get();
+    let mut perms = load_permissions(guild_id, kind, id)?;
+    let before = perms.permissions.clone();
+    let grant = PermissionGrant {
+        permission: perm.clone(),
+        expires_at,
+    };
+    let expiry = match expires_at {
+        Some(expires_at) => format!(" until <t:{}:f>", expires_at),
+        None => String::new(),
     };
 
-    // Add permission if not already present
-    if !perms.permissions.contains(&perm) {
-        perms.permissions.push(perm.clone());
+    match perms.position(&perm) {
+        // Granting again replaces the expiry, so a timed grant can be extended or made permanent
+        Some(pos) if perms.permissions[pos].expires_at != expires_at => {
+            perms.permissions[pos] = grant;
+            save_permissions(&perms)?;
+            audit::record(
+                AuditEntry::discord(ctx, "permission.grant", &perms.audit_target())
+                    .before(&before)
+                    .after(&perms.permissions),
+            );
 
-        // Write back to database
-        let value = serde_json::to_string(&perms)?;
-        crate::db::write_entry(PERMISSIONS, &key, &value)?;
+            ctx.say(format!(
+                "✅ Updated **{}** permission for {}{}",
+                perm.as_str(),
+                kind.mention(id),
+                expiry
+            ))
+            .await?;
+        }
+        Some(_) => {
+            ctx.say(format!(
+                "ℹ️ {} already has **{}** permission",
+                kind.mention(id),
+                perm.as_str()
+            ))
+            .await?;
+        }
+        None => {
+            perms.permissions.push(grant);
+            save_permissions(&perms)?;
+            audit::record(
+                AuditEntry::discord(ctx, "permission.grant", &perms.audit_target())
+                    .before(&before)
+                    .after(&perms.permissions),
+            );
+
+            ctx.say(format!(
+                "✅ Granted **{}** permission to {}{}",
+                perm.as_str(),
+                kind.mention(id),
+                expiry
+            ))
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Revoke a permission from a user or role
+async fn revoke_permission(
+    ctx: Context<'_>,
+    kind: TargetKind,
+    id: u64,
+    permission: String,
+) -> Result<(), Error> {
+    let perm = match permission.parse::<Permission>() {
+        Ok(p) => p,
+        Err(_) => {
+            ctx.say("❌ Invalid permission level. Use: admin, mod, or trusted")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let guild_id = ctx.guild_id().unwrap().get();
+    let mut perms = load_permissions(guild_id, kind, id)?;
+
+    if perms.permissions.is_empty() {
+        ctx.say(format!("ℹ️ {} has no permissions", kind.mention(id)))
+            .await?;
+    } else if let Some(pos) = perms.position(&perm) {
+        let before = perms.permissions.clone();
+        perms.permissions.remove(pos);
+        save_permissions(&perms)?;
+        audit::record(
+            AuditEntry::discord(ctx, "permission.revoke", &perms.audit_target())
+                .before(&before)
+                .after(&perms.permissions),
+        );
 
         ctx.say(format!(
-            "✅ Granted **{}** permission to <@{}>",
+            "✅ Revoked **{}** permission from {}",
             perm.as_str(),
-            user.id
+            kind.mention(id)
         ))
         .await?;
     } else {
         ctx.say(format!(
-            "ℹ️ <@{}> already has **{}** permission",
-            user.id,
+            "ℹ️ {} doesn't have **{}** permission",
+            kind.mention(id),
             perm.as_str()
         ))
         .await?;
@@ -145,129 +607,120 @@ pub async fn addperm(
     Ok(())
 }
 
+/// The user or role a command targets, replies and returns `None` unless exactly one was given
+async fn permission_target(
+    ctx: Context<'_>,
+    user: Option<serenity::User>,
+    role: Option<serenity::Role>,
+) -> Result<Option<(TargetKind, u64)>, Error> {
+    match (user, role) {
+        (Some(user), None) => Ok(Some((TargetKind::User, user.id.get()))),
+        (None, Some(role)) => Ok(Some((TargetKind::Role, role.id.get()))),
+        _ => {
+            ctx.say("❌ Pick either a user or a role.").await?;
+            Ok(None)
+        }
+    }
+}
+
+// Discord wants required options first, so the permission comes before the optional target
 #[poise::command(
     prefix_command,
     slash_command,
     check = "check_admin",
+    guild_only,
     category = "Permissions"
 )]
-pub async fn removeperm(
+pub async fn addperm(
     ctx: Context<'_>,
-    #[description = "User to revoke permission from"] user: serenity::User,
     #[description = "Permission level (admin, mod, trusted)"] permission: String,
+    #[description = "User to grant permission"] user: Option<serenity::User>,
+    #[description = "Role to grant permission"] role: Option<serenity::Role>,
+    #[description = "Revoke automatically after this long, e.g. 3d or 12h"] duration: Option<
+        String,
+    >,
 ) -> Result<(), Error> {
-    let perm = match permission.parse::<Permission>() {
-        Ok(p) => p,
-        Err(_) => {
-            ctx.say("❌ Invalid permission level. Use: admin, mod, or trusted")
-                .await?;
-            return Ok(());
-        }
+    let Some((kind, id)) = permission_target(ctx, user, role).await? else {
+        return Ok(());
     };
+    grant_permission(ctx, kind, id, permission, duration).await
+}
 
-    let user_id = user.id.get();
-    let db = KV_DATABASE.get().unwrap();
-
-    // Read current permissions
-    let tx_read = db.begin_read()?;
-    let table_read = tx_read.open_table(PERMISSIONS)?;
-    let key = user_id.to_string();
-
-    if let Some(value) = table_read.get(key.as_str())? {
-        let mut perms: UserPermissions = serde_json::from_str(value.value())?;
-        drop(table_read);
-        drop(tx_read);
-
-        if let Some(pos) = perms.permissions.iter().position(|p| p == &perm) {
-            perms.permissions.remove(pos);
-
-            // Write back to database
-            let tx = db.begin_write()?;
-            {
-                let mut table = tx.open_table(PERMISSIONS)?;
-                if perms.permissions.is_empty() {
-                    // Remove entry if no permissions left
-                    table.remove(key.as_str())?;
-                } else {
-                    let value = serde_json::to_string(&perms)?;
-                    table.insert(key.as_str(), value.as_str())?;
-                }
-            }
-            tx.commit()?;
-
-            ctx.say(format!(
-                "✅ Revoked **{}** permission from <@{}>",
-                perm.as_str(),
-                user.id
-            ))
-            .await?;
-        } else {
-            ctx.say(format!(
-                "ℹ️ <@{}> doesn't have **{}** permission",
-                user.id,
-                perm.as_str()
-            ))
-            .await?;
-        }
-    } else {
-        ctx.say(format!("ℹ️ <@{}> has no permissions", user.id))
-            .await?;
-    }
-
-    Ok(())
+#[poise::command(
+    prefix_command,
+    slash_command,
+    check = "check_admin",
+    guild_only,
+    category = "Permissions"
+)]
+pub async fn removeperm(
+    ctx: Context<'_>,
+    #[description = "Permission level (admin, mod, trusted)"] permission: String,
+    #[description = "User to revoke permission from"] user: Option<serenity::User>,
+    #[description = "Role to revoke permission from"] role: Option<serenity::Role>,
+) -> Result<(), Error> {
+    let Some((kind, id)) = permission_target(ctx, user, role).await? else {
+        return Ok(());
+    };
+    revoke_permission(ctx, kind, id, permission).await
 }
 
-#[poise::command(prefix_command, slash_command, category = "Permissions")]
+#[poise::command(prefix_command, slash_command, guild_only, category = "Permissions")]
 pub async fn listperms(ctx: Context<'_>) -> Result<(), Error> {
-    let all_perms = crate::db::read_table(PERMISSIONS, |_, value| {
-        serde_json::from_str::<UserPermissions>(value).ok()
-    })?;
+    let prefix = crate::db::key(&[&ctx.guild_id().unwrap().get(), &""]);
+    let all_perms: Vec<UserPermissions> = GRANTS
+        .prefix(&prefix)?
+        .into_iter()
+        .map(|(_, perms)| perms)
+        .collect();
 
     if all_perms.is_empty() {
-        ctx.say("No permissions have been assigned.").await?;
+        ctx.say("No permissions have been assigned in this server.")
+            .await?;
         return Ok(());
     }
 
-    // Group by permission type
-    let mut admins = Vec::new();
-    let mut mods = Vec::new();
-    let mut trusted = Vec::new();
-
-    for perm in all_perms {
-        if perm.permissions.contains(&Permission::Admin) {
-            admins.push(perm.user_id);
-        }
-        if perm.permissions.contains(&Permission::Mod) {
-            mods.push(perm.user_id);
-        }
-        if perm.permissions.contains(&Permission::Trusted) {
-            trusted.push(perm.user_id);
-        }
-    }
-
+    let now = chrono::Utc::now().timestamp();
     let mut response = String::from("**Permissions:**\n");
 
-    if !admins.is_empty() {
-        response.push_str("\n**Admins:**\n");
-        for user_id in admins {
-            response.push_str(&format!("• <@{}>\n", user_id));
-        }
-    }
-
-    if !mods.is_empty() {
-        response.push_str("\n**Moderators:**\n");
-        for user_id in mods {
-            response.push_str(&format!("• <@{}>\n", user_id));
+    for (permission, heading) in [
+        (Permission::Admin, "Admins"),
+        (Permission::Mod, "Moderators"),
+        (Permission::Trusted, "Trusted"),
+    ] {
+        let holders: Vec<String> = all_perms
+            .iter()
+            .filter_map(|perms| {
+                let grant = &perms.permissions[perms.position(&permission)?];
+                if grant.is_expired(now) {
+                    return None;
+                }
+                Some(match grant.expires_at {
+                    Some(expires_at) => format!(
+                        "{} (expires <t:{}:R>)",
+                        perms.kind.mention(perms.id),
+                        expires_at
+                    ),
+                    None => perms.kind.mention(perms.id),
+                })
+            })
+            .collect();
+        if holders.is_empty() {
+            continue;
         }
-    }
 
-    if !trusted.is_empty() {
-        response.push_str("\n**Trusted:**\n");
-        for user_id in trusted {
-            response.push_str(&format!("• <@{}>\n", user_id));
+        response.push_str(&format!("\n**{}:**\n", heading));
+        for holder in holders {
+            response.push_str(&format!("• {}\n", holder));
         }
     }
 
-    ctx.say(response).await?;
+    // Mentions are only for display, nobody should get pinged by the list
+    ctx.send(
+        poise::CreateReply::default()
+            .content(response)
+            .allowed_mentions(serenity::CreateAllowedMentions::new()),
+    )
+    .await?;
     Ok(())
 }
@@ -0,0 +1,351 @@
+use super::{Permission, author_role_ids, check_admin, member_permissions};
+use crate::audit::{self, AuditEntry};
+use crate::db::Table;
+use crate::env::FOOTER_URL;
+use crate::{COMMAND_PATHS, COMMAND_POLICIES, Context, Error, colors};
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+
+/// Keyed `<guild id>_<command path>`
+const POLICIES: Table<CommandPolicy> = Table::new(COMMAND_POLICIES);
+
+// Kept out of reach of policies so admins can't lock themselves out of editing them
+const EXEMPT_COMMANDS: [&str; 1] = ["perms"];
+
+/// Per-guild restrictions for a command path, on top of the command's own checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandPolicy {
+    pub guild_id: u64,
+    /// Qualified command path, e.g. `music queue`, also covers its subcommands
+    pub command: String,
+    #[serde(default)]
+    pub min_permission: Option<Permission>,
+    #[serde(default)]
+    pub allowed_roles: Vec<u64>,
+    #[serde(default)]
+    pub allowed_channels: Vec<u64>,
+}
+
+impl CommandPolicy {
+    pub fn new(guild_id: u64, command: &str) -> Self {
+        CommandPolicy {
+            guild_id,
+            command: command.to_string(),
+            min_permission: None,
+            allowed_roles: Vec::new(),
+            allowed_channels: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min_permission.is_none()
+            && self.allowed_roles.is_empty()
+            && self.allowed_channels.is_empty()
+    }
+
+    /// Channels restrict everyone, the level and roles are alternatives when both are set
+    pub fn allows(&self, permissions: &[Permission], role_ids: &[u64], channel_id: u64) -> bool {
+        if !self.allowed_channels.is_empty() && !self.allowed_channels.contains(&channel_id) {
+            return false;
+        }
+        let by_level = self
+            .min_permission
+            .as_ref()
+            .map(|min| permissions.iter().any(|perm| perm.satisfies(min)));
+        let by_role = (!self.allowed_roles.is_empty()).then(|| {
+            role_ids
+                .iter()
+                .any(|role| self.allowed_roles.contains(role))
+        });
+        match (by_level, by_role) {
+            (None, None) => true,
+            (by_level, by_role) => by_level.unwrap_or(false) || by_role.unwrap_or(false),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(ref min) = self.min_permission {
+            lines.push(format!("Level: **{}** or higher", min.as_str()));
+        }
+        if !self.allowed_roles.is_empty() {
+            let roles: Vec<String> = self
+                .allowed_roles
+                .iter()
+                .map(|id| format!("<@&{}>", id))
+                .collect();
+            lines.push(format!("Roles: {}", roles.join(", ")));
+        }
+        if !self.allowed_channels.is_empty() {
+            let channels: Vec<String> = self
+                .allowed_channels
+                .iter()
+                .map(|id| format!("<#{}>", id))
+                .collect();
+            lines.push(format!("Channels: {}", channels.join(", ")));
+        }
+        if lines.is_empty() {
+            "No restrictions".to_string()
+        } else {
+            lines.join("\n")
+        }
+    }
+}
+
+/// Whether policies are ignored for a command path
+pub fn is_exempt(command: &str) -> bool {
+    EXEMPT_COMMANDS.contains(&command.split(' ').next().unwrap_or_default())
+}
+
+fn policy_key(guild_id: u64, command: &str) -> String {
+    crate::db::key(&[&guild_id, &command])
+}
+
+/// Lowercase with single spaces, so `Music  Queue` and `music queue` are the same path
+pub fn normalize_command(command: &str) -> String {
+    command
+        .trim()
+        .trim_start_matches(['/', '~'])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Every command path the bot registered, including parents of subcommands
+pub fn command_paths() -> &'static [String] {
+    COMMAND_PATHS.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+pub fn collect_command_paths(
+    commands: &[poise::Command<crate::Data, Error>],
+    paths: &mut Vec<String>,
+) {
+    for command in commands {
+        paths.push(command.qualified_name.clone());
+        collect_command_paths(&command.subcommands, paths);
+    }
+}
+
+pub fn load_policy(guild_id: u64, command: &str) -> Result<Option<CommandPolicy>, Error> {
+    Ok(POLICIES.get(&policy_key(guild_id, command))?)
+}
+
+/// Store a policy, policies without any restriction are removed
+pub fn save_policy(policy: &CommandPolicy) -> Result<(), Error> {
+    let key = policy_key(policy.guild_id, &policy.command);
+    if policy.is_empty() {
+        POLICIES.remove(&key)?;
+    } else {
+        POLICIES.insert(&key, policy)?;
+    }
+    Ok(())
+}
+
+pub fn delete_policy(guild_id: u64, command: &str) -> Result<(), Error> {
+    POLICIES.remove(&policy_key(guild_id, command))?;
+    Ok(())
+}
+
+pub fn list_policies(guild_id: u64) -> Result<Vec<CommandPolicy>, Error> {
+    let prefix = policy_key(guild_id, "");
+    let policies = POLICIES
+        .prefix(&prefix)?
+        .into_iter()
+        .map(|(_, policy)| policy)
+        .collect();
+    Ok(policies)
+}
+
+/// Global check run before every command, enforcing the policies of the command and its parents
+pub async fn command_check(ctx: Context<'_>) -> Result<bool, Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(true);
+    };
+    let user_id = ctx.author().id.get();
+    let path = ctx.command().qualified_name.clone();
+    if user_id == *crate::env::AUTHOR_ID || is_exempt(&path) {
+        return Ok(true);
+    }
+
+    // `music queue` is covered by policies on both `music` and `music queue`
+    let words: Vec<&str> = path.split(' ').collect();
+    let mut policies = Vec::new();
+    for len in 1..=words.len() {
+        if let Some(policy) = load_policy(guild_id.get(), &words[..len].join(" "))? {
+            policies.push(policy);
+        }
+    }
+    if policies.is_empty() {
+        return Ok(true);
+    }
+
+    let role_ids = author_role_ids(ctx).await;
+    let permissions = member_permissions(guild_id.get(), user_id, &role_ids)?;
+    let channel_id = ctx.channel_id().get();
+    if policies
+        .iter()
+        .all(|policy| policy.allows(&permissions, &role_ids, channel_id))
+    {
+        return Ok(true);
+    }
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format!(
+                "❌ You can't use `{}` here. Ask a server admin if you think you should.",
+                path
+            ))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(false)
+}
+
+async fn autocomplete_command(_ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let partial = normalize_command(partial);
+    command_paths()
+        .iter()
+        .filter(|path| path.contains(&partial))
+        .take(25)
+        .cloned()
+        .collect()
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("command"),
+    subcommand_required,
+    guild_only,
+    category = "Permissions"
+)]
+pub async fn perms(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// View or edit who can use a command in this server
+#[poise::command(
+    prefix_command,
+    slash_command,
+    check = "check_admin",
+    guild_only,
+    category = "Permissions"
+)]
+pub async fn command(
+    ctx: Context<'_>,
+    #[description = "Command path, e.g. `music queue` (omit to list all policies)"]
+    #[autocomplete = "autocomplete_command"]
+    command: Option<String>,
+    #[description = "Minimum permission level (admin, mod, trusted, none)"] level: Option<String>,
+    #[description = "Allow a role to use the command"] add_role: Option<serenity::Role>,
+    #[description = "Stop allowing a role"] remove_role: Option<serenity::Role>,
+    #[description = "Only allow the command in a channel"] add_channel: Option<
+        serenity::GuildChannel,
+    >,
+    #[description = "Stop allowing a channel"] remove_channel: Option<serenity::GuildChannel>,
+    #[description = "Remove every restriction on the command"] reset: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
+
+    let Some(command) = command else {
+        let mut policies = list_policies(guild_id)?;
+        policies.sort_by(|a, b| a.command.cmp(&b.command));
+
+        let mut embed = serenity::CreateEmbed::new()
+            .title(format!("Command Policies ({})", policies.len()))
+            .footer(footer)
+            .color(colors::PRIMARY)
+            .timestamp(serenity::model::Timestamp::now());
+        if policies.is_empty() {
+            embed = embed.description("No command restrictions are set in this server.");
+        }
+        // Embeds are limited to 25 fields
+        for policy in policies.iter().take(25) {
+            embed = embed.field(format!("/{}", policy.command), policy.describe(), false);
+        }
+        ctx.send(poise::CreateReply::default().embed(embed)).await?;
+        return Ok(());
+    };
+
+    let command = normalize_command(&command);
+    if !command_paths().contains(&command) {
+        ctx.say(format!("❌ Unknown command `{}`.", command))
+            .await?;
+        return Ok(());
+    }
+    if is_exempt(&command) {
+        ctx.say(format!("❌ `{}` can't be restricted.", command))
+            .await?;
+        return Ok(());
+    }
+
+    let level = match level.as_deref().map(str::trim) {
+        None => None,
+        Some(level) if level.eq_ignore_ascii_case("none") => Some(None),
+        Some(level) => match level.parse::<Permission>() {
+            Ok(perm) => Some(Some(perm)),
+            Err(_) => {
+                ctx.say("❌ Invalid permission level. Use: admin, mod, trusted or none")
+                    .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    let existing = load_policy(guild_id, &command)?;
+    let mut policy = match existing {
+        Some(ref policy) if !reset.unwrap_or(false) => policy.clone(),
+        _ => CommandPolicy::new(guild_id, &command),
+    };
+    let edited = reset.unwrap_or(false)
+        || level.is_some()
+        || add_role.is_some()
+        || remove_role.is_some()
+        || add_channel.is_some()
+        || remove_channel.is_some();
+
+    if let Some(level) = level {
+        policy.min_permission = level;
+    }
+    if let Some(role) = add_role
+        && !policy.allowed_roles.contains(&role.id.get())
+    {
+        policy.allowed_roles.push(role.id.get());
+    }
+    if let Some(role) = remove_role {
+        policy.allowed_roles.retain(|id| *id != role.id.get());
+    }
+    if let Some(channel) = add_channel
+        && !policy.allowed_channels.contains(&channel.id.get())
+    {
+        policy.allowed_channels.push(channel.id.get());
+    }
+    if let Some(channel) = remove_channel {
+        policy.allowed_channels.retain(|id| *id != channel.id.get());
+    }
+
+    if edited {
+        save_policy(&policy)?;
+        audit::record(
+            AuditEntry::discord(ctx, "command_policy.update", &command)
+                .before(&existing)
+                .after((!policy.is_empty()).then_some(&policy)),
+        );
+    }
+
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("/{}", command))
+        .description(policy.describe())
+        .footer(footer)
+        .color(if edited {
+            colors::SUCCESS
+        } else {
+            colors::PRIMARY
+        })
+        .timestamp(serenity::model::Timestamp::now());
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
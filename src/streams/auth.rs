@@ -0,0 +1,51 @@
+use crate::Error;
+use std::future::Future;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+/// Client-credentials app token shared by every check against a platform
+pub struct TokenCache {
+    token: Mutex<Option<CachedToken>>,
+}
+
+struct CachedToken {
+    access_token: String,
+    expires_at: Instant,
+}
+
+impl TokenCache {
+    pub fn new() -> Self {
+        TokenCache {
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Return the cached token, fetching a new one when it is missing or about to expire.
+    /// `fetch` resolves to the access token and its lifetime.
+    pub async fn get<F, Fut>(&self, fetch: F) -> Result<String, Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(String, Duration), Error>>,
+    {
+        // Holding the lock while fetching stops concurrent checks requesting tokens at once
+        let mut token = self.token.lock().await;
+        if let Some(ref cached) = *token
+            && cached.expires_at > Instant::now()
+        {
+            return Ok(cached.access_token.clone());
+        }
+
+        let (access_token, expires_in) = fetch().await?;
+        // Refresh a minute early so a token never expires mid request
+        *token = Some(CachedToken {
+            access_token: access_token.clone(),
+            expires_at: Instant::now() + expires_in.saturating_sub(Duration::from_secs(60)),
+        });
+        Ok(access_token)
+    }
+
+    /// Drop the cached token, e.g. after the API rejected it
+    pub async fn invalidate(&self) {
+        *self.token.lock().await = None;
+    }
+}
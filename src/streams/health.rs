@@ -0,0 +1,148 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use tokio::time::{Duration, Instant};
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+
+struct Backoff {
+    failures: u32,
+    retry_at: Instant,
+}
+
+/// Channels (`platform:channel`) that failed recently and when to try them again
+static BACKOFF: LazyLock<Mutex<HashMap<String, Backoff>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Platforms that hit their rate limit and when it resets
+static RATE_LIMITS: LazyLock<Mutex<HashMap<&'static str, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static HEALTH: LazyLock<Mutex<CheckerHealth>> =
+    LazyLock::new(|| Mutex::new(CheckerHealth::default()));
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CheckerHealth {
+    pub interval_secs: u64,
+    pub last_run: Option<String>,
+    pub last_run_ms: u64,
+    pub followed_channels: usize,
+    pub checks_total: u64,
+    pub errors_total: u64,
+    pub backing_off: Vec<String>,
+    pub rate_limited: Vec<String>,
+}
+
+pub fn should_check(key: &str) -> bool {
+    BACKOFF
+        .lock()
+        .unwrap()
+        .get(key)
+        .is_none_or(|backoff| backoff.retry_at <= Instant::now())
+}
+
+pub fn record_success(key: &str) {
+    BACKOFF.lock().unwrap().remove(key);
+    HEALTH.lock().unwrap().checks_total += 1;
+}
+
+/// Push the next attempt back exponentially from the poll interval, returns the delay
+pub fn record_failure(key: &str, interval: Duration) -> Duration {
+    let mut backoff = BACKOFF.lock().unwrap();
+    let entry = backoff.entry(key.to_string()).or_insert(Backoff {
+        failures: 0,
+        retry_at: Instant::now(),
+    });
+    entry.failures += 1;
+    let delay = interval
+        .saturating_mul(2u32.saturating_pow(entry.failures.min(16)))
+        .min(MAX_BACKOFF);
+    entry.retry_at = Instant::now() + delay;
+
+    let mut health = HEALTH.lock().unwrap();
+    health.checks_total += 1;
+    health.errors_total += 1;
+    delay
+}
+
+/// Remember when a platform's rate limit resets from its response headers.
+/// Understands `Retry-After` and the `Ratelimit-Remaining`/`Ratelimit-Reset` pair used by Helix.
+pub fn record_rate_limit(platform: &'static str, response: &reqwest::Response) {
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<i64>().ok())
+    };
+    let reset_in = || {
+        header("ratelimit-reset")
+            .map(|reset| (reset - chrono::Utc::now().timestamp()).max(1) as u64)
+            .map(Duration::from_secs)
+    };
+
+    let wait = if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        Some(
+            header("retry-after")
+                .map(|secs| Duration::from_secs(secs.max(1) as u64))
+                .or_else(reset_in)
+                .unwrap_or(Duration::from_secs(60)),
+        )
+    } else if header("ratelimit-remaining") == Some(0) {
+        reset_in()
+    } else {
+        None
+    };
+
+    // The headers come from the platform, so an absurd reset time is capped like any backoff
+    if let Some(wait) = wait.map(|wait| wait.min(MAX_BACKOFF))
+        && let Some(until) = Instant::now().checked_add(wait)
+    {
+        log::warn!(
+            "{} rate limit reached, pausing checks for {:?}",
+            platform,
+            wait
+        );
+        RATE_LIMITS.lock().unwrap().insert(platform, until);
+    }
+}
+
+pub fn is_rate_limited(platform: &str) -> bool {
+    RATE_LIMITS
+        .lock()
+        .unwrap()
+        .get(platform)
+        .is_some_and(|until| *until > Instant::now())
+}
+
+pub fn record_run(interval: Duration, followed_channels: usize, started: Instant) {
+    let mut health = HEALTH.lock().unwrap();
+    health.interval_secs = interval.as_secs();
+    health.followed_channels = followed_channels;
+    health.last_run = Some(chrono::Utc::now().to_rfc3339());
+    health.last_run_ms = started.elapsed().as_millis() as u64;
+}
+
+/// Current checker health for the web stats endpoint
+pub fn snapshot() -> CheckerHealth {
+    let now = Instant::now();
+    let mut health = HEALTH.lock().unwrap().clone();
+
+    health.backing_off = BACKOFF
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, backoff)| backoff.retry_at > now)
+        .map(|(key, _)| key.clone())
+        .collect();
+    health.backing_off.sort();
+    health.rate_limited = RATE_LIMITS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, until)| **until > now)
+        .map(|(platform, _)| platform.to_string())
+        .collect();
+
+    health
+}
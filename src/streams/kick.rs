@@ -0,0 +1,159 @@
+use super::auth::TokenCache;
+use super::health;
+use super::provider::{LiveStatus, StreamProvider};
+use crate::{Error, HTTP_CLIENT};
+use poise::serenity_prelude as serenity;
+use serde::Deserialize;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+static TOKEN: LazyLock<TokenCache> = LazyLock::new(TokenCache::new);
+
+#[derive(Debug, Deserialize)]
+struct KickOAuthResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KickApiResponse {
+    data: Vec<KickChannelData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KickChannelData {
+    slug: String,
+    channel_description: Option<String>,
+    stream: Option<KickStream>,
+    stream_title: Option<String>,
+    category: Option<KickCategory>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KickStream {
+    is_live: bool,
+    start_time: Option<String>,
+    viewer_count: u32,
+    thumbnail: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KickCategory {
+    name: String,
+}
+
+pub struct Kick;
+
+async fn get_kick_oauth_token() -> Result<String, Error> {
+    TOKEN.get(request_kick_oauth_token).await
+}
+
+async fn request_kick_oauth_token() -> Result<(String, Duration), Error> {
+    let client = HTTP_CLIENT.get().unwrap();
+
+    let params = [
+        ("grant_type", "client_credentials"),
+        ("client_id", &*crate::env::KICK_CLIENT_ID),
+        ("client_secret", &*crate::env::KICK_CLIENT_SECRET),
+    ];
+
+    let response = client
+        .post("https://id.kick.com/oauth/token")
+        .form(&params)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(format!("OAuth token request failed: {}", response.status()).into());
+    }
+
+    let oauth_response: KickOAuthResponse = response.json().await?;
+    let expires_in = Duration::from_secs(oauth_response.expires_in.unwrap_or(3600));
+    Ok((oauth_response.access_token, expires_in))
+}
+
+async fn check_kick_stream(channel_name: &str) -> Result<Option<KickChannelData>, Error> {
+    let access_token = get_kick_oauth_token().await?;
+    let url = format!(
+        "https://api.kick.com/public/v1/channels?slug={}",
+        channel_name
+    );
+
+    log::debug!("Checking Kick channel: {}", url);
+
+    let response = HTTP_CLIENT
+        .get()
+        .unwrap()
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await?;
+
+    log::debug!("Received response with status: {}", response.status());
+    health::record_rate_limit("kick", &response);
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        TOKEN.invalidate().await;
+        return Err("Kick rejected the OAuth token".into());
+    }
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err("Kick rate limit reached".into());
+    }
+
+    if response.status().is_success() {
+        let api_response: KickApiResponse = response.json().await?;
+        Ok(api_response.data.into_iter().next())
+    } else {
+        Ok(None)
+    }
+}
+
+#[serenity::async_trait]
+impl StreamProvider for Kick {
+    fn platform(&self) -> &'static str {
+        "kick"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Kick"
+    }
+
+    fn parse_url(&self, url: &str) -> Option<String> {
+        // Parse kick.com URLs like https://kick.com/channelname
+        if url.contains("kick.com/")
+            && let Some(channel) = url.split("kick.com/").nth(1)
+        {
+            let channel_name = channel.trim_end_matches('/').to_string();
+            if !channel_name.is_empty() {
+                return Some(channel_name);
+            }
+        }
+        None
+    }
+
+    fn channel_url(&self, channel: &str) -> String {
+        format!("https://kick.com/{}", channel)
+    }
+
+    async fn check(&self, channel: &str) -> Result<Option<LiveStatus>, Error> {
+        let Some(data) = check_kick_stream(channel).await? else {
+            return Err(format!("Channel {} not found on Kick", channel).into());
+        };
+
+        let Some(stream) = data.stream.filter(|stream| stream.is_live) else {
+            return Ok(None);
+        };
+
+        Ok(Some(LiveStatus {
+            url: self.channel_url(channel),
+            channel_name: data.slug,
+            title: data.stream_title,
+            description: data.channel_description.filter(|desc| !desc.is_empty()),
+            thumbnail: stream.thumbnail,
+            viewer_count: Some(stream.viewer_count),
+            category: data.category.map(|category| category.name),
+            vod_url: None,
+            started_at: stream.start_time.unwrap_or_default(),
+        }))
+    }
+}
@@ -1,111 +1,236 @@
+use crate::audit::{self, AuditEntry};
 use crate::colors;
-use crate::{Context, Error, HTTP_CLIENT, KV_DATABASE, LIVE_STREAMS_STATE, STREAMS};
+use crate::db::{DbError, Lookups, Table};
+use crate::{Context, Error, LIVE_STREAMS_STATE, STREAMS};
+use futures::StreamExt;
 use poise::serenity_prelude as serenity;
-use redb::ReadableDatabase;
+use provider::{LiveStatus, StreamProvider, parse_stream_url};
+use redb::{ReadableDatabase, ReadableTable};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
-use tokio::time::{Duration, interval};
+use tokio::time::{Duration, Instant, MissedTickBehavior, interval};
 
-#[derive(Debug, Serialize, Deserialize)]
-struct StreamFollow {
-    user_id: u64,
-    url: String,
-    channel_id: u64,
-    platform: String,
-    channel_name: String,
+mod auth;
+pub mod health;
+mod kick;
+pub mod provider;
+mod twitch;
+mod youtube;
+
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+const MIN_INTERVAL_SECS: u64 = 15;
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Keyed by [`StreamFollow::key`]
+pub const FOLLOWS: Table<StreamFollow> = Table::new(STREAMS);
+/// Session tracking of live follows, under the same key as the follow
+pub const LIVE_STATES: Table<LiveStreamState> = Table::new(LIVE_STREAMS_STATE);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamFollow {
+    /// Unset for follows created before they were scoped to a guild
+    #[serde(default)]
+    pub guild_id: Option<u64>,
+    pub user_id: u64,
+    pub url: String,
+    pub channel_id: u64,
+    pub platform: String,
+    pub channel_name: String,
+    #[serde(default)]
+    pub mention_role: Option<u64>,
+    #[serde(default)]
+    pub mention_here: bool,
+    /// Announcement text supporting `{streamer}`, `{title}`, `{category}` and `{url}`
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Publish notifications to following servers when posted in an announcement channel
+    #[serde(default)]
+    pub crosspost: bool,
 }
 
-#[derive(Debug, Deserialize)]
-struct KickOAuthResponse {
-    access_token: String,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveStreamState {
+    pub message_id: u64,
+    pub start_time: String,
+    /// Unix timestamp the session was first seen, used when `start_time` isn't a date
+    #[serde(default)]
+    pub first_seen: Option<i64>,
+    #[serde(default)]
+    pub peak_viewers: u32,
+    #[serde(default)]
+    pub channel_name: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub vod_url: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-struct KickApiResponse {
-    data: Vec<KickChannelData>,
+impl LiveStreamState {
+    fn new(message_id: u64, status: &LiveStatus) -> Self {
+        let mut state = LiveStreamState {
+            message_id,
+            start_time: status.started_at.clone(),
+            first_seen: Some(chrono::Utc::now().timestamp()),
+            peak_viewers: 0,
+            channel_name: None,
+            title: None,
+            category: None,
+            url: None,
+            vod_url: None,
+        };
+        state.observe(status);
+        state
+    }
+
+    /// Track the peak and the latest details so the ended summary reflects the whole session
+    fn observe(&mut self, status: &LiveStatus) {
+        self.peak_viewers = self.peak_viewers.max(status.viewer_count.unwrap_or(0));
+        self.channel_name = Some(status.channel_name.clone());
+        self.title = status.title.clone().or(self.title.take());
+        self.category = status.category.clone().or(self.category.take());
+        self.url = Some(status.url.clone());
+        self.vod_url = status.vod_url.clone().or(self.vod_url.take());
+    }
+
+    fn started(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::parse_from_rfc3339(&self.start_time)
+            .map(|time| time.with_timezone(&chrono::Utc))
+            .or_else(|_| {
+                chrono::NaiveDateTime::parse_from_str(&self.start_time, "%Y-%m-%d %H:%M:%S")
+                    .map(|time| time.and_utc())
+            })
+            .ok()
+            .or_else(|| {
+                self.first_seen
+                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+            })
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct KickChannelData {
-    broadcaster_user_id: u64,
-    slug: String,
-    channel_description: Option<String>,
-    banner_picture: Option<String>,
-    stream: Option<KickStream>,
-    stream_title: Option<String>,
-    category: Option<KickCategory>,
+impl StreamFollow {
+    /// Key in `STREAMS`, also used for the follow's `LIVE_STREAMS_STATE` entry
+    pub fn key(&self) -> String {
+        match self.guild_id {
+            Some(guild_id) => crate::db::key(&[
+                &guild_id,
+                &self.platform,
+                &self.channel_name,
+                &self.channel_id,
+            ]),
+            None => crate::db::key(&[&self.platform, &self.channel_name]),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct KickStream {
-    is_live: bool,
-    is_mature: Option<bool>,
-    language: Option<String>,
-    start_time: Option<String>,
-    viewer_count: u32,
-    thumbnail: Option<String>,
+pub fn guild_follows(guild_id: u64) -> Result<Vec<StreamFollow>, Error> {
+    let follows = FOLLOWS
+        .prefix(&crate::db::key(&[&guild_id, &""]))?
+        .into_iter()
+        .map(|(_, follow)| follow)
+        .collect();
+    Ok(follows)
 }
 
-#[derive(Debug, Deserialize)]
-struct KickCategory {
-    id: u64,
-    name: String,
-    thumbnail: Option<String>,
+pub fn all_follows() -> Result<Vec<StreamFollow>, Error> {
+    Ok(FOLLOWS.values()?)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct LiveStreamState {
-    message_id: u64,
-    start_time: String,
+pub fn load_follow(key: &str) -> Result<Option<StreamFollow>, Error> {
+    Ok(FOLLOWS.get(key)?)
 }
 
-fn parse_kick_url(url: &str) -> Option<String> {
-    // Parse kick.com URLs like https://kick.com/channelname
-    if url.contains("kick.com/")
-        && let Some(channel) = url.split("kick.com/").nth(1)
-    {
-        let channel_name = channel.trim_end_matches('/').to_string();
-        if !channel_name.is_empty() {
-            return Some(channel_name);
-        }
+pub fn save_follow(follow: &StreamFollow) -> Result<(), Error> {
+    FOLLOWS.insert(&follow.key(), follow)?;
+    Ok(())
+}
+
+/// Remove a follow along with its live session tracking
+pub fn delete_follow(key: &str) -> Result<(), Error> {
+    FOLLOWS.remove(key)?;
+    LIVE_STATES.remove(key)?;
+    Ok(())
+}
+
+pub fn live_state(key: &str) -> Result<Option<LiveStreamState>, Error> {
+    match LIVE_STATES.get(key) {
+        // A state that no longer decodes starts a new session rather than failing the check
+        Err(DbError::Serialization(_)) => Ok(None),
+        result => Ok(result?),
     }
-    None
+}
+
+fn supported_platforms() -> String {
+    provider::providers()
+        .iter()
+        .map(|provider| provider.display_name())
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 #[poise::command(
     prefix_command,
     slash_command,
     check = "crate::permissions::check_trusted",
+    guild_only,
     category = "Streams"
 )]
 pub async fn follow(
     ctx: Context<'_>,
     #[description = "Stream URL to follow"] url: String,
+    #[description = "Role to mention when the stream goes live"] role: Option<serenity::Role>,
+    #[description = "Mention @here when the stream goes live"] here: Option<bool>,
+    #[description = "Announcement message, supports {streamer}, {title}, {category} and {url}"]
+    message: Option<String>,
+    #[description = "Publish notifications when this is an announcement channel"] crosspost: Option<
+        bool,
+    >,
 ) -> Result<(), Error> {
     // Parse the URL to determine platform and channel
-    let (platform, channel_name) = if let Some(channel) = parse_kick_url(&url) {
-        ("kick", channel)
-    } else {
-        ctx.say("❌ Unsupported URL. Currently only kick.com URLs are supported.")
-            .await?;
+    let Some((provider, channel_name)) = parse_stream_url(&url) else {
+        ctx.say(format!(
+            "❌ Unsupported URL. Supported platforms: {}",
+            supported_platforms()
+        ))
+        .await?;
         return Ok(());
     };
+    let platform = provider.platform();
 
+    let guild_id = ctx.guild_id().unwrap().get();
     let user_id = ctx.author().id.get();
     let channel_id = ctx.channel_id().get();
 
     let follow = StreamFollow {
+        guild_id: Some(guild_id),
         user_id,
         url: url.clone(),
         channel_id,
         platform: platform.to_string(),
         channel_name: channel_name.clone(),
+        mention_role: role.map(|role| role.id.get()),
+        mention_here: here.unwrap_or(false),
+        message,
+        crosspost: crosspost.unwrap_or(false),
     };
 
-    // Store in database
-    let key = format!("{}:{}", platform, channel_name);
-    let value = serde_json::to_string(&follow)?;
-    crate::db::write_entry(STREAMS, &key, &value)?;
+    // Store in database, following again in the same channel replaces the settings
+    let before = load_follow(&follow.key())?;
+    save_follow(&follow)?;
+    let action = if before.is_some() {
+        "stream.update"
+    } else {
+        "stream.follow"
+    };
+    audit::record(
+        AuditEntry::discord(ctx, action, &follow.key())
+            .before(&before)
+            .after(&follow),
+    );
 
     ctx.say(format!(
         "✅ Now following **{}** on {}. Notifications will be posted in <#{}>",
@@ -120,48 +245,92 @@ pub async fn follow(
     prefix_command,
     slash_command,
     check = "crate::permissions::check_trusted",
+    guild_only,
     category = "Streams"
 )]
 pub async fn unfollow(
     ctx: Context<'_>,
     #[description = "Stream URL to unfollow"] url: String,
+    #[description = "Only stop notifications in this channel"] channel: Option<
+        serenity::GuildChannel,
+    >,
 ) -> Result<(), Error> {
-    let (platform, channel_name) = if let Some(channel) = parse_kick_url(&url) {
-        ("kick", channel)
-    } else {
-        ctx.say("❌ Unsupported URL. Currently only kick.com URLs are supported.")
-            .await?;
+    let Some((provider, channel_name)) = parse_stream_url(&url) else {
+        ctx.say(format!(
+            "❌ Unsupported URL. Supported platforms: {}",
+            supported_platforms()
+        ))
+        .await?;
         return Ok(());
     };
+    let platform = provider.platform();
+
+    let guild_id = ctx.guild_id().unwrap().get();
+    let matching: Vec<StreamFollow> = guild_follows(guild_id)?
+        .into_iter()
+        .filter(|follow| follow.platform == platform && follow.channel_name == channel_name)
+        .filter(|follow| {
+            channel
+                .as_ref()
+                .is_none_or(|channel| channel.id.get() == follow.channel_id)
+        })
+        .collect();
 
-    let key = format!("{}:{}", platform, channel_name);
-    crate::db::delete_entry(STREAMS, &key)?;
+    if matching.is_empty() {
+        ctx.say(format!(
+            "❌ **{}** on {} isn't followed in this server.",
+            channel_name, platform
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    for follow in &matching {
+        delete_follow(&follow.key())?;
+        audit::record(AuditEntry::discord(ctx, "stream.unfollow", &follow.key()).before(follow));
+    }
 
+    let channels = matching
+        .iter()
+        .map(|follow| format!("<#{}>", follow.channel_id))
+        .collect::<Vec<_>>()
+        .join(", ");
     ctx.say(format!(
-        "✅ Unfollowed **{}** on {}",
-        channel_name, platform
+        "✅ Unfollowed **{}** on {} in {}",
+        channel_name, platform, channels
     ))
     .await?;
 
     Ok(())
 }
 
-#[poise::command(prefix_command, slash_command, category = "Streams")]
+#[poise::command(prefix_command, slash_command, guild_only, category = "Streams")]
 pub async fn following(ctx: Context<'_>) -> Result<(), Error> {
-    let follows = crate::db::read_table(STREAMS, |_, value| {
-        serde_json::from_str::<StreamFollow>(value).ok()
-    })?;
+    let follows = guild_follows(ctx.guild_id().unwrap().get())?;
 
     if follows.is_empty() {
-        ctx.say("No streams are currently being followed.").await?;
+        ctx.say("No streams are currently being followed in this server.")
+            .await?;
         return Ok(());
     }
 
     let mut response = String::from("**Following streams:**\n");
     for follow in follows {
+        let mut mentions = Vec::new();
+        if let Some(role_id) = follow.mention_role {
+            mentions.push(format!("<@&{}>", role_id));
+        }
+        if follow.mention_here {
+            mentions.push("@here".to_string());
+        }
+        let pings = if mentions.is_empty() {
+            String::new()
+        } else {
+            format!(", pings {}", mentions.join(" "))
+        };
         response.push_str(&format!(
-            "• **{}** on {} - Notifications in <#{}> (followed by <@{}>)\n",
-            follow.channel_name, follow.platform, follow.channel_id, follow.user_id
+            "• **{}** on {} - Notifications in <#{}>{} (followed by <@{}>)\n",
+            follow.channel_name, follow.platform, follow.channel_id, pings, follow.user_id
         ));
     }
 
@@ -172,37 +341,24 @@ pub async fn following(ctx: Context<'_>) -> Result<(), Error> {
 #[poise::command(prefix_command, slash_command, category = "Streams")]
 pub async fn preview(
     ctx: Context<'_>,
-    #[description = "Kick channel name to preview"] channel_name: String,
+    #[description = "Kick channel name or stream URL to preview"] channel_name: String,
 ) -> Result<(), Error> {
     ctx.defer().await?;
 
-    match check_kick_stream(&channel_name).await {
-        Ok(Some(channel)) => {
-            if let Some(ref stream) = channel.stream {
-                if stream.is_live {
-                    let stream_url = format!("https://kick.com/{}", channel_name);
-                    let embed = create_stream_embed(&channel, &stream_url, stream);
-
-                    ctx.send(poise::CreateReply::default().embed(embed)).await?;
-                } else {
-                    ctx.say(format!(
-                        "❌ **{}** is not currently live on Kick.",
-                        channel_name
-                    ))
-                    .await?;
-                }
-            } else {
-                ctx.say(format!(
-                    "❌ **{}** is not currently live on Kick.",
-                    channel_name
-                ))
-                .await?;
-            }
+    // Bare channel names are treated as Kick channels
+    let (provider, channel_name) = parse_stream_url(&channel_name)
+        .unwrap_or((provider::provider("kick").unwrap(), channel_name));
+
+    match provider.check(&channel_name).await {
+        Ok(Some(status)) => {
+            let embed = create_stream_embed(&status);
+            ctx.send(poise::CreateReply::default().embed(embed)).await?;
         }
         Ok(None) => {
             ctx.say(format!(
-                "❌ Channel **{}** not found on Kick.",
-                channel_name
+                "❌ **{}** is not currently live on {}.",
+                channel_name,
+                provider.display_name()
             ))
             .await?;
         }
@@ -214,267 +370,744 @@ pub async fn preview(
     Ok(())
 }
 
-async fn get_kick_oauth_token() -> Result<String, Error> {
-    let client = HTTP_CLIENT.get().unwrap();
-
-    let params = [
-        ("grant_type", "client_credentials"),
-        ("client_id", &*crate::env::KICK_CLIENT_ID),
-        ("client_secret", &*crate::env::KICK_CLIENT_SECRET),
-    ];
-
-    let response = client
-        .post("https://id.kick.com/oauth/token")
-        .form(&params)
-        .send()
-        .await?;
+fn create_stream_embed(status: &LiveStatus) -> serenity::CreateEmbed {
+    let mut embed = serenity::CreateEmbed::new()
+        .url(&status.url)
+        .color(colors::LIVE)
+        .timestamp(serenity::Timestamp::now());
 
-    if !response.status().is_success() {
-        return Err(format!("OAuth token request failed: {}", response.status()).into());
+    if let Some(ref title) = status.title {
+        embed = embed.title(title);
     }
 
-    let oauth_response: KickOAuthResponse = response.json().await?;
-    Ok(oauth_response.access_token)
-}
+    // Description starts with the channel name
+    let mut description = format!("**{}**", status.channel_name);
+    if let Some(ref channel_desc) = status.description {
+        description.push_str(&format!("\n{}", channel_desc));
+    }
+    embed = embed.description(description);
 
-async fn check_kick_stream(channel_name: &str) -> Result<Option<KickChannelData>, Error> {
-    let access_token = get_kick_oauth_token().await?;
-    let url = format!(
-        "https://api.kick.com/public/v1/channels?slug={}",
-        channel_name
-    );
+    if let Some(ref thumbnail) = status.thumbnail {
+        embed = embed.image(thumbnail);
+    }
 
-    log::debug!("Checking Kick channel: {}", url);
+    if let Some(viewers) = status.viewer_count {
+        embed = embed.field("Viewers", viewers.to_string(), true);
+    }
 
-    let response = HTTP_CLIENT
-        .get()
-        .unwrap()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", access_token))
-        .send()
-        .await?;
+    if let Some(ref category) = status.category {
+        embed = embed.field("Category", category, true);
+    }
 
-    log::debug!("Received response with status: {}", response.status());
+    embed
+}
 
-    if response.status().is_success() {
-        let api_response: KickApiResponse = response.json().await?;
-        Ok(api_response.data.into_iter().next())
+fn format_duration(duration: chrono::Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    if minutes >= 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
     } else {
-        Ok(None)
+        format!("{}m", minutes)
     }
 }
 
-fn create_stream_embed(
-    channel: &KickChannelData,
-    stream_url: &str,
-    stream: &KickStream,
+fn create_ended_embed(
+    follow: &StreamFollow,
+    state: &LiveStreamState,
+    vod_url: Option<&str>,
 ) -> serenity::CreateEmbed {
+    let channel_name = state
+        .channel_name
+        .as_deref()
+        .unwrap_or(&follow.channel_name);
     let mut embed = serenity::CreateEmbed::new()
-        .url(stream_url)
-        .color(colors::LIVE)
+        .title(state.title.as_deref().unwrap_or("Stream ended"))
+        .url(vod_url.or(state.url.as_deref()).unwrap_or(&follow.url))
+        .description(format!("**{}** was live", channel_name))
+        .color(colors::BASE)
         .timestamp(serenity::Timestamp::now());
 
-    // Set title from stream_title
-    if let Some(ref stream_title) = channel.stream_title {
-        embed = embed.title(stream_title);
+    if let Some(started) = state.started() {
+        embed = embed
+            .field(
+                "Duration",
+                format_duration(chrono::Utc::now() - started),
+                true,
+            )
+            .field("Started", format!("<t:{}:f>", started.timestamp()), true);
+    }
+
+    if state.peak_viewers > 0 {
+        embed = embed.field("Peak Viewers", state.peak_viewers.to_string(), true);
+    }
+
+    if let Some(ref category) = state.category {
+        embed = embed.field("Category", category, true);
+    }
+
+    if let Some(vod_url) = vod_url {
+        embed = embed.field("VOD", format!("[Watch the broadcast]({})", vod_url), false);
+    }
+
+    embed
+}
+
+/// Mentions followed by the follow's announcement template, if either is configured
+fn announcement_content(follow: &StreamFollow, status: &LiveStatus) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(role_id) = follow.mention_role {
+        parts.push(format!("<@&{}>", role_id));
     }
+    if follow.mention_here {
+        parts.push("@here".to_string());
+    }
+    if let Some(ref template) = follow.message {
+        parts.push(
+            template
+                .replace("{streamer}", &status.channel_name)
+                .replace("{title}", status.title.as_deref().unwrap_or_default())
+                .replace("{category}", status.category.as_deref().unwrap_or_default())
+                .replace("{url}", &status.url),
+        );
+    }
+
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+fn save_live_state(stream_key: &str, state: &LiveStreamState) -> Result<(), Error> {
+    LIVE_STATES.insert(stream_key, state)?;
+    Ok(())
+}
+
+/// Posts and edits stream notifications, so the session logic below doesn't depend on Discord
+#[serenity::async_trait]
+pub trait Notifier: Send + Sync {
+    /// Post a notification, returns the id of the new message
+    async fn send(
+        &self,
+        channel_id: u64,
+        message: serenity::CreateMessage,
+        crosspost: bool,
+    ) -> Result<u64, Error>;
+
+    /// Replace a notification's embed, `Ok(false)` when the message no longer exists
+    async fn edit(
+        &self,
+        channel_id: u64,
+        message_id: u64,
+        embed: serenity::CreateEmbed,
+    ) -> Result<bool, Error>;
+}
 
-    // Set description starting with slug
-    let mut description = format!("**{}**", channel.slug);
-    if let Some(ref channel_desc) = channel.channel_description
-        && !channel_desc.is_empty()
+#[serenity::async_trait]
+impl Notifier for serenity::Http {
+    async fn send(
+        &self,
+        channel_id: u64,
+        message: serenity::CreateMessage,
+        crosspost: bool,
+    ) -> Result<u64, Error> {
+        let message = serenity::ChannelId::new(channel_id)
+            .send_message(self, message)
+            .await?;
+        if crosspost {
+            crosspost_notification(self, &message).await;
+        }
+        Ok(message.id.get())
+    }
+
+    async fn edit(
+        &self,
+        channel_id: u64,
+        message_id: u64,
+        embed: serenity::CreateEmbed,
+    ) -> Result<bool, Error> {
+        let Ok(mut message) = serenity::ChannelId::new(channel_id)
+            .message(self, message_id)
+            .await
+        else {
+            return Ok(false);
+        };
+        message
+            .edit(self, serenity::EditMessage::new().embed(embed))
+            .await?;
+        Ok(true)
+    }
+}
+
+/// Publish a notification to following servers if it was posted in an announcement channel
+async fn crosspost_notification(http: &serenity::Http, message: &serenity::Message) {
+    let is_announcement = match message.channel_id.to_channel(http).await {
+        Ok(serenity::Channel::Guild(channel)) => channel.kind == serenity::ChannelType::News,
+        Ok(_) => false,
+        Err(e) => {
+            log::warn!("Failed to look up channel for crosspost: {}", e);
+            false
+        }
+    };
+
+    if is_announcement && let Err(e) = message.crosspost(http).await {
+        log::error!("Failed to crosspost stream notification: {}", e);
+    }
+}
+
+/// Edit a finished session's notification into a summary of the stream
+async fn end_session(
+    notifier: &dyn Notifier,
+    provider: &dyn StreamProvider,
+    follow: &StreamFollow,
+    state: &LiveStreamState,
+) {
+    let vod_url = match state.vod_url {
+        Some(ref vod_url) => Some(vod_url.clone()),
+        None => provider
+            .latest_vod(&follow.channel_name)
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to look up VOD for {}: {}", follow.channel_name, e);
+                None
+            }),
+    };
+
+    let embed = create_ended_embed(follow, state, vod_url.as_deref());
+    match notifier
+        .edit(follow.channel_id, state.message_id, embed)
+        .await
     {
-        description.push_str(&format!("\n{}", channel_desc));
+        Ok(true) => {}
+        Ok(false) => log::debug!(
+            "Ended stream notification for {} was deleted",
+            follow.channel_name
+        ),
+        Err(e) => log::warn!("Failed to edit ended stream notification: {}", e),
     }
-    embed = embed.description(description);
+}
 
-    // Set image to thumbnail
-    if let Some(ref thumbnail) = stream.thumbnail {
-        embed = embed.image(thumbnail);
+/// Post or update the live notification for a stream session, returns the session to store
+async fn notify_live(
+    notifier: &dyn Notifier,
+    provider: &dyn StreamProvider,
+    follow: &StreamFollow,
+    existing_state: Option<LiveStreamState>,
+    status: &LiveStatus,
+) -> Option<LiveStreamState> {
+    let embed = create_stream_embed(status);
+    let content = announcement_content(follow, status);
+
+    // Session details carried over when the message has to be re-sent
+    let mut carried_state = None;
+
+    // Check if we already have a message for this stream
+    if let Some(mut state) = existing_state {
+        // Compare start times to see if it's the same stream session
+        if status.started_at == state.start_time {
+            state.observe(status);
+
+            // Same stream session, edit the existing message
+            match notifier
+                .edit(follow.channel_id, state.message_id, embed.clone())
+                .await
+            {
+                Ok(true) => return Some(state),
+                // Message doesn't exist anymore, send a new one below
+                Ok(false) => carried_state = Some(state),
+                Err(e) => {
+                    log::error!("Failed to edit stream notification: {}", e);
+                    // Forget the session so a new notification is sent next time
+                    return None;
+                }
+            }
+        } else {
+            // Different start_time means new stream session, send a new message
+            log::info!(
+                "New stream session detected for {}, creating new notification",
+                follow.channel_name
+            );
+            // The previous session ended between checks
+            end_session(notifier, provider, follow, &state).await;
+        }
     }
 
-    // Add viewer count field
-    embed = embed.field("Viewers", stream.viewer_count.to_string(), true);
+    // Send a new notification and store the message ID with start_time
+    let mut message = serenity::CreateMessage::new().embed(embed);
+    if let Some(content) = content {
+        // Only the configured mentions may ping, not anything pasted into the template
+        let mut allowed_mentions = serenity::CreateAllowedMentions::new()
+            .everyone(follow.mention_here)
+            .empty_users();
+        allowed_mentions = match follow.mention_role {
+            Some(role_id) => allowed_mentions.roles(vec![serenity::RoleId::new(role_id)]),
+            None => allowed_mentions.empty_roles(),
+        };
+        message = message.content(content).allowed_mentions(allowed_mentions);
+    }
 
-    // Add category field
-    if let Some(ref category) = channel.category {
-        embed = embed.field("Category", &category.name, true);
+    match notifier
+        .send(follow.channel_id, message, follow.crosspost)
+        .await
+    {
+        Ok(message_id) => Some(match carried_state {
+            Some(mut state) => {
+                state.message_id = message_id;
+                state
+            }
+            None => LiveStreamState::new(message_id, status),
+        }),
+        Err(e) => {
+            log::error!("Failed to send stream notification: {}", e);
+            // Keep a carried session so its details survive until the next attempt
+            carried_state
+        }
     }
+}
 
-    embed
+/// Apply a follow's current status to its session: post or edit while live, summarize once offline.
+/// Returns the session to store, `None` when there is no session left.
+async fn update_follow(
+    notifier: &dyn Notifier,
+    provider: &dyn StreamProvider,
+    follow: &StreamFollow,
+    state: Option<LiveStreamState>,
+    status: Option<&LiveStatus>,
+) -> Option<LiveStreamState> {
+    match status {
+        Some(status) => notify_live(notifier, provider, follow, state, status).await,
+        None => {
+            // Stream is offline, summarize the session and remove it from the live set
+            if let Some(state) = state {
+                end_session(notifier, provider, follow, &state).await;
+            }
+            None
+        }
+    }
 }
 
-async fn check_and_notify_streams(http: &serenity::Http) -> Result<(), Error> {
-    let db = KV_DATABASE.get().unwrap();
-    let tx = db.begin_read()?;
-    let table = tx.open_table(STREAMS)?;
+/// Load a follow's session, apply its current status and store the result
+async fn refresh_follow(
+    notifier: &dyn Notifier,
+    provider: &dyn StreamProvider,
+    follow: &StreamFollow,
+    status: Option<&LiveStatus>,
+) -> Result<(), Error> {
+    let stream_key = follow.key();
+    let state = live_state(&stream_key)?;
+    let had_state = state.is_some();
 
-    let mut follows: Vec<StreamFollow> = Vec::new();
-    for item in table.range::<&str>(..)? {
-        let (_, value) = item?;
-        if let Ok(follow) = serde_json::from_str::<StreamFollow>(value.value()) {
-            follows.push(follow);
+    match update_follow(notifier, provider, follow, state, status).await {
+        Some(state) => save_live_state(&stream_key, &state)?,
+        None if had_state => {
+            LIVE_STATES.remove(&stream_key)?;
         }
+        None => {}
     }
-    drop(table);
-    drop(tx);
+    Ok(())
+}
 
-    for follow in follows {
-        if follow.platform != "kick" {
+async fn check_and_notify_streams(http: &serenity::Http, period: Duration) -> Result<(), Error> {
+    let started = Instant::now();
+
+    // Group follows so a streamer followed in several channels is only checked once
+    let mut follows: BTreeMap<(String, String), Vec<StreamFollow>> = BTreeMap::new();
+    // Follows the guild migration couldn't scope have no server to manage them from
+    for follow in all_follows()?
+        .into_iter()
+        .filter(|follow| follow.guild_id.is_some())
+    {
+        follows
+            .entry((follow.platform.clone(), follow.channel_name.clone()))
+            .or_default()
+            .push(follow);
+    }
+
+    // Channels due for a check, batched per provider
+    let mut batches: Vec<(&'static dyn StreamProvider, Vec<String>)> = Vec::new();
+    for provider in provider::providers() {
+        if health::is_rate_limited(provider.platform()) {
+            log::debug!(
+                "Skipping {} checks until its rate limit resets",
+                provider.platform()
+            );
             continue;
         }
 
-        match check_kick_stream(&follow.channel_name).await {
-            Ok(Some(channel)) => {
-                let stream_key = format!("{}:{}", follow.platform, follow.channel_name);
+        let channels: Vec<String> = follows
+            .keys()
+            .filter(|(platform, channel_name)| {
+                platform == provider.platform()
+                    && health::should_check(&format!("{}:{}", platform, channel_name))
+            })
+            .map(|(_, channel_name)| channel_name.clone())
+            .collect();
+        for chunk in channels.chunks(provider.batch_size().max(1)) {
+            batches.push((*provider, chunk.to_vec()));
+        }
+    }
 
-                // Check if stream has stream data and is actually live
-                let is_live = if let Some(ref stream) = channel.stream {
-                    stream.is_live
-                } else {
-                    false
-                };
+    for (platform, channel_name) in follows.keys() {
+        if provider::provider(platform).is_none() {
+            log::warn!("Unsupported platform for {}: {}", channel_name, platform);
+        }
+    }
 
-                if is_live {
-                    if let Some(ref stream) = channel.stream {
-                        let discord_channel = serenity::ChannelId::new(follow.channel_id);
-                        let embed = create_stream_embed(&channel, &follow.url, stream);
-                        let current_start_time = stream.start_time.clone().unwrap_or_default();
-
-                        // Load existing state from database
-                        let existing_state: Option<LiveStreamState> = {
-                            let tx = db.begin_read()?;
-                            let table = tx.open_table(LIVE_STREAMS_STATE)?;
-                            if let Some(state_str) = table.get(stream_key.as_str())? {
-                                serde_json::from_str(state_str.value()).ok()
-                            } else {
-                                None
-                            }
-                        };
-
-                        // Check if we already have a message for this stream
-                        if let Some(state) = existing_state {
-                            // Compare start times to see if it's the same stream session
-                            if current_start_time == state.start_time {
-                                // Same stream session, edit the existing message
-                                let message = discord_channel.message(http, state.message_id).await;
-                                if let Ok(mut msg) = message {
-                                    let edit = serenity::EditMessage::new().embed(embed);
-                                    if let Err(e) = msg.edit(http, edit).await {
-                                        log::error!("Failed to edit stream notification: {}", e);
-                                        // If edit fails, remove the entry and send a new one next time
-                                        let tx = db.begin_write()?;
-                                        {
-                                            let mut table = tx.open_table(LIVE_STREAMS_STATE)?;
-                                            table.remove(stream_key.as_str())?;
-                                        }
-                                        tx.commit()?;
-                                    }
-                                } else {
-                                    // Message doesn't exist anymore, remove from tracking and send new one
-                                    let message = serenity::CreateMessage::new().embed(embed);
-
-                                    if let Ok(sent_msg) =
-                                        discord_channel.send_message(http, message).await
-                                    {
-                                        let new_state = LiveStreamState {
-                                            message_id: sent_msg.id.get(),
-                                            start_time: current_start_time,
-                                        };
-                                        let tx = db.begin_write()?;
-                                        {
-                                            let mut table = tx.open_table(LIVE_STREAMS_STATE)?;
-                                            let value = serde_json::to_string(&new_state)?;
-                                            table.insert(stream_key.as_str(), value.as_str())?;
-                                        }
-                                        tx.commit()?;
-                                    } else {
-                                        log::error!("Failed to send stream notification");
-                                    }
-                                }
-                            } else {
-                                // Different start_time means new stream session, send a new message
-                                log::info!(
-                                    "New stream session detected for {}, creating new notification",
-                                    follow.channel_name
-                                );
-
-                                let message = serenity::CreateMessage::new().embed(embed);
-
-                                if let Ok(sent_msg) =
-                                    discord_channel.send_message(http, message).await
-                                {
-                                    let new_state = LiveStreamState {
-                                        message_id: sent_msg.id.get(),
-                                        start_time: current_start_time,
-                                    };
-                                    let tx = db.begin_write()?;
-                                    {
-                                        let mut table = tx.open_table(LIVE_STREAMS_STATE)?;
-                                        let value = serde_json::to_string(&new_state)?;
-                                        table.insert(stream_key.as_str(), value.as_str())?;
-                                    }
-                                    tx.commit()?;
-                                } else {
-                                    log::error!("Failed to send stream notification");
-                                }
-                            }
-                        } else {
-                            // Send a new notification and store the message ID with start_time
-                            let message = serenity::CreateMessage::new().embed(embed);
-
-                            match discord_channel.send_message(http, message).await {
-                                Ok(sent_msg) => {
-                                    let new_state = LiveStreamState {
-                                        message_id: sent_msg.id.get(),
-                                        start_time: current_start_time,
-                                    };
-                                    let tx = db.begin_write()?;
-                                    {
-                                        let mut table = tx.open_table(LIVE_STREAMS_STATE)?;
-                                        let value = serde_json::to_string(&new_state)?;
-                                        table.insert(stream_key.as_str(), value.as_str())?;
-                                    }
-                                    tx.commit()?;
-                                }
-                                Err(e) => {
-                                    log::error!("Failed to send stream notification: {}", e);
-                                }
-                            }
-                        }
+    let follows = &follows;
+    futures::stream::iter(batches)
+        .for_each_concurrent(checker_concurrency(), |(provider, channels)| async move {
+            for (channel_name, result) in provider.check_many(&channels).await {
+                let backoff_key = format!("{}:{}", provider.platform(), channel_name);
+                let status = match result {
+                    Ok(status) => {
+                        health::record_success(&backoff_key);
+                        let result = if status.is_some() { "live" } else { "offline" };
+                        crate::metrics::record_stream_poll(provider.platform(), result);
+                        status
                     }
-                } else {
-                    // Stream is offline, remove from live set
-                    let tx = db.begin_write()?;
-                    {
-                        let mut table = tx.open_table(LIVE_STREAMS_STATE)?;
-                        table.remove(stream_key.as_str())?;
+                    Err(e) => {
+                        let delay = health::record_failure(&backoff_key, period);
+                        crate::metrics::record_stream_poll(provider.platform(), "error");
+                        log::error!(
+                            "Error checking stream {}, retrying in {:?}: {}",
+                            channel_name,
+                            delay,
+                            e
+                        );
+                        continue;
+                    }
+                };
+
+                let key = (provider.platform().to_string(), channel_name);
+                for follow in follows.get(&key).into_iter().flatten() {
+                    if let Err(e) = refresh_follow(http, provider, follow, status.as_ref()).await {
+                        log::error!("Error updating stream {}: {}", follow.key(), e);
                     }
-                    tx.commit()?;
                 }
             }
-            Ok(None) => {
-                log::warn!("Channel {} not found on Kick", follow.channel_name);
-            }
-            Err(e) => {
-                log::error!("Error checking stream {}: {}", follow.channel_name, e);
+        })
+        .await;
+
+    health::record_run(period, follows.len(), started);
+    Ok(())
+}
+
+fn legacy_follows<T: ReadableTable<&'static str, &'static str>>(
+    table: &T,
+) -> Result<Vec<(String, StreamFollow)>, Error> {
+    Ok(table
+        .range::<&str>(..)?
+        .filter_map(|item| {
+            let (key, value) = item.ok()?;
+            serde_json::from_str::<StreamFollow>(value.value())
+                .ok()
+                .filter(|follow| follow.guild_id.is_none())
+                .map(|follow| (key.value().to_string(), follow))
+        })
+        .collect())
+}
+
+/// Guild of each legacy follow's notification channel, channels that are gone or not in a guild are left out
+pub fn lookup_follow_guilds(db: &redb::Database) -> Result<Lookups, Error> {
+    let channels: BTreeSet<u64> = {
+        let tx = db.begin_read()?;
+        legacy_follows(&tx.open_table(STREAMS)?)?
+            .iter()
+            .map(|(_, follow)| follow.channel_id)
+            .collect()
+    };
+
+    let mut guilds = Lookups::new();
+    if channels.is_empty() {
+        return Ok(guilds);
+    }
+    let http = serenity::Http::new(&crate::env::DISCORD_TOKEN);
+    for channel_id in channels {
+        match crate::db::block_on(serenity::ChannelId::new(channel_id).to_channel(&http)) {
+            Ok(serenity::Channel::Guild(channel)) => {
+                guilds.insert(channel_id, vec![channel.guild_id.get()]);
             }
+            Ok(_) => {}
+            Err(e) if crate::db::is_not_found(&e) => {}
+            // Anything else may work next time, so the migration waits for it
+            Err(e) => return Err(e.into()),
         }
     }
+    Ok(guilds)
+}
+
+/// Scope follows saved before guild support to the guild of their notification channel.
+/// Follows that can't be scoped stay as they are, the checker skips them and only the owner sees them.
+pub fn migrate_legacy_follows(tx: &redb::WriteTransaction, guilds: &Lookups) -> Result<(), Error> {
+    let mut follows = tx.open_table(STREAMS)?;
+    let mut states = tx.open_table(LIVE_STREAMS_STATE)?;
+    for (old_key, mut follow) in legacy_follows(&follows)? {
+        let Some(&guild_id) = guilds.get(&follow.channel_id).and_then(|ids| ids.first()) else {
+            log::warn!("Could not resolve a guild for stream follow {}", old_key);
+            continue;
+        };
+
+        follow.guild_id = Some(guild_id);
+        let new_key = follow.key();
+        follows.insert(new_key.as_str(), serde_json::to_string(&follow)?.as_str())?;
+        follows.remove(old_key.as_str())?;
+
+        let state = states
+            .remove(old_key.as_str())?
+            .map(|state| state.value().to_string());
+        if let Some(state) = state {
+            states.insert(new_key.as_str(), state.as_str())?;
+        }
+
+        log::info!("Migrated stream follow {} to {}", old_key, new_key);
+    }
 
     Ok(())
 }
 
+fn checker_interval() -> Duration {
+    let secs = crate::REACTION_CONFIG
+        .get()
+        .and_then(|config| config.streams.as_ref())
+        .and_then(|streams| streams.interval_secs)
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+    Duration::from_secs(secs.max(MIN_INTERVAL_SECS))
+}
+
+fn checker_concurrency() -> usize {
+    crate::REACTION_CONFIG
+        .get()
+        .and_then(|config| config.streams.as_ref())
+        .and_then(|streams| streams.concurrency)
+        .unwrap_or(DEFAULT_CONCURRENCY)
+        .max(1)
+}
+
 pub async fn start_stream_checker(http: Arc<serenity::Http>) {
     log::info!("Starting stream checker background task");
 
-    let mut check_interval = interval(Duration::from_secs(60));
+    let period = checker_interval();
+    let mut check_interval = interval(period);
+    // A slow run shouldn't be followed by a burst of catch-up checks
+    check_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
     loop {
         check_interval.tick().await;
 
-        if let Err(e) = check_and_notify_streams(&http).await {
+        if let Err(e) = check_and_notify_streams(&http, period).await {
             log::error!("Error in stream checker: {}", e);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// Returns scripted statuses, one per check
+    struct FakeProvider {
+        statuses: Mutex<VecDeque<Option<LiveStatus>>>,
+    }
+
+    impl FakeProvider {
+        fn new(statuses: Vec<Option<LiveStatus>>) -> Self {
+            FakeProvider {
+                statuses: Mutex::new(statuses.into()),
+            }
+        }
+    }
+
+    #[serenity::async_trait]
+    impl StreamProvider for FakeProvider {
+        fn platform(&self) -> &'static str {
+            "fake"
+        }
+
+        fn display_name(&self) -> &'static str {
+            "Fake"
+        }
+
+        fn parse_url(&self, _url: &str) -> Option<String> {
+            None
+        }
+
+        fn channel_url(&self, channel: &str) -> String {
+            format!("https://example.com/{}", channel)
+        }
+
+        async fn check(&self, _channel: &str) -> Result<Option<LiveStatus>, Error> {
+            Ok(self.statuses.lock().unwrap().pop_front().flatten())
+        }
+    }
+
+    /// Records what would have been posted, messages can be deleted to simulate moderators
+    #[derive(Default)]
+    struct FakeNotifier {
+        existing: Mutex<Vec<u64>>,
+        sent: Mutex<Vec<u64>>,
+        edited: Mutex<Vec<u64>>,
+    }
+
+    impl FakeNotifier {
+        fn delete(&self, message_id: u64) {
+            self.existing.lock().unwrap().retain(|id| *id != message_id);
+        }
+
+        fn sent(&self) -> Vec<u64> {
+            self.sent.lock().unwrap().clone()
+        }
+
+        fn edited(&self) -> Vec<u64> {
+            self.edited.lock().unwrap().clone()
+        }
+    }
+
+    #[serenity::async_trait]
+    impl Notifier for FakeNotifier {
+        async fn send(
+            &self,
+            _channel_id: u64,
+            _message: serenity::CreateMessage,
+            _crosspost: bool,
+        ) -> Result<u64, Error> {
+            let mut sent = self.sent.lock().unwrap();
+            let message_id = 100 + sent.len() as u64;
+            sent.push(message_id);
+            self.existing.lock().unwrap().push(message_id);
+            Ok(message_id)
+        }
+
+        async fn edit(
+            &self,
+            _channel_id: u64,
+            message_id: u64,
+            _embed: serenity::CreateEmbed,
+        ) -> Result<bool, Error> {
+            if !self.existing.lock().unwrap().contains(&message_id) {
+                return Ok(false);
+            }
+            self.edited.lock().unwrap().push(message_id);
+            Ok(true)
+        }
+    }
+
+    fn live(started_at: &str, viewers: u32) -> Option<LiveStatus> {
+        Some(LiveStatus {
+            channel_name: "Streamer".to_string(),
+            title: Some("Playing something".to_string()),
+            description: None,
+            url: "https://example.com/streamer".to_string(),
+            thumbnail: None,
+            viewer_count: Some(viewers),
+            category: None,
+            vod_url: None,
+            started_at: started_at.to_string(),
+        })
+    }
+
+    fn follow() -> StreamFollow {
+        StreamFollow {
+            guild_id: Some(1),
+            user_id: 2,
+            url: "https://example.com/streamer".to_string(),
+            channel_id: 3,
+            platform: "fake".to_string(),
+            channel_name: "streamer".to_string(),
+            mention_role: None,
+            mention_here: false,
+            message: None,
+            crosspost: false,
+        }
+    }
+
+    /// One checker pass for the follow, like `refresh_follow` without the database
+    async fn poll(
+        provider: &FakeProvider,
+        notifier: &FakeNotifier,
+        state: Option<LiveStreamState>,
+    ) -> Option<LiveStreamState> {
+        let follow = follow();
+        let status = provider.check(&follow.channel_name).await.unwrap();
+        update_follow(notifier, provider, &follow, state, status.as_ref()).await
+    }
+
+    #[tokio::test]
+    async fn new_session_posts_a_notification_and_ends_the_previous_one() {
+        let provider = FakeProvider::new(vec![
+            live("2025-01-01T10:00:00Z", 10),
+            live("2025-01-01T18:00:00Z", 5),
+        ]);
+        let notifier = FakeNotifier::default();
+
+        let state = poll(&provider, &notifier, None).await.unwrap();
+        assert_eq!(notifier.sent(), vec![100]);
+        assert_eq!(state.message_id, 100);
+        assert_eq!(state.peak_viewers, 10);
+
+        let state = poll(&provider, &notifier, Some(state)).await.unwrap();
+        // The first notification is summarized, the new session gets its own
+        assert_eq!(notifier.edited(), vec![100]);
+        assert_eq!(notifier.sent(), vec![100, 101]);
+        assert_eq!(state.message_id, 101);
+        assert_eq!(state.start_time, "2025-01-01T18:00:00Z");
+        assert_eq!(state.peak_viewers, 5);
+    }
+
+    #[tokio::test]
+    async fn same_session_edits_the_notification() {
+        let provider = FakeProvider::new(vec![
+            live("2025-01-01T10:00:00Z", 10),
+            live("2025-01-01T10:00:00Z", 25),
+            live("2025-01-01T10:00:00Z", 15),
+        ]);
+        let notifier = FakeNotifier::default();
+
+        let mut state = None;
+        for _ in 0..3 {
+            state = poll(&provider, &notifier, state).await;
+        }
+        let state = state.unwrap();
+
+        assert_eq!(notifier.sent(), vec![100]);
+        assert_eq!(notifier.edited(), vec![100, 100]);
+        assert_eq!(state.message_id, 100);
+        assert_eq!(state.peak_viewers, 25);
+    }
+
+    #[tokio::test]
+    async fn deleted_message_is_sent_again_keeping_the_session() {
+        let provider = FakeProvider::new(vec![
+            live("2025-01-01T10:00:00Z", 40),
+            live("2025-01-01T10:00:00Z", 10),
+        ]);
+        let notifier = FakeNotifier::default();
+
+        let state = poll(&provider, &notifier, None).await.unwrap();
+        notifier.delete(state.message_id);
+        let state = poll(&provider, &notifier, Some(state)).await.unwrap();
+
+        assert_eq!(notifier.sent(), vec![100, 101]);
+        assert!(notifier.edited().is_empty());
+        assert_eq!(state.message_id, 101);
+        assert_eq!(state.start_time, "2025-01-01T10:00:00Z");
+        // The peak from before the message was deleted is carried over
+        assert_eq!(state.peak_viewers, 40);
+    }
+
+    #[tokio::test]
+    async fn offline_summarizes_and_forgets_the_session() {
+        let provider = FakeProvider::new(vec![live("2025-01-01T10:00:00Z", 10), None, None]);
+        let notifier = FakeNotifier::default();
+
+        let state = poll(&provider, &notifier, None).await;
+        assert!(state.is_some());
+
+        let state = poll(&provider, &notifier, state).await;
+        assert!(state.is_none());
+        assert_eq!(notifier.sent(), vec![100]);
+        assert_eq!(notifier.edited(), vec![100]);
+
+        // Staying offline doesn't touch Discord again
+        let state = poll(&provider, &notifier, state).await;
+        assert!(state.is_none());
+        assert_eq!(notifier.edited(), vec![100]);
+    }
+}
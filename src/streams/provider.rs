@@ -0,0 +1,85 @@
+use crate::Error;
+use poise::serenity_prelude as serenity;
+
+use super::kick::Kick;
+use super::twitch::Twitch;
+use super::youtube::Youtube;
+
+/// Platform independent snapshot of a live stream
+#[derive(Debug, Clone)]
+pub struct LiveStatus {
+    /// Display name of the channel
+    pub channel_name: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Link the notification points at
+    pub url: String,
+    pub thumbnail: Option<String>,
+    pub viewer_count: Option<u32>,
+    pub category: Option<String>,
+    /// Recording of the broadcast, when the platform exposes it while live
+    pub vod_url: Option<String>,
+    /// Identifies the stream session, a change means the channel went live again
+    pub started_at: String,
+}
+
+#[serenity::async_trait]
+pub trait StreamProvider: Send + Sync {
+    /// Key stored in `StreamFollow::platform`, e.g. `kick`
+    fn platform(&self) -> &'static str;
+
+    /// Human readable platform name, e.g. `Kick`
+    fn display_name(&self) -> &'static str;
+
+    /// Channel identifier for a URL on this platform, if it is one
+    fn parse_url(&self, url: &str) -> Option<String>;
+
+    /// Canonical URL for a channel identifier
+    fn channel_url(&self, channel: &str) -> String;
+
+    /// Current live status, `None` when the channel is offline
+    async fn check(&self, channel: &str) -> Result<Option<LiveStatus>, Error>;
+
+    /// How many channels `check_many` should be given at once
+    fn batch_size(&self) -> usize {
+        1
+    }
+
+    /// Check several channels, providers with a batch endpoint override this
+    async fn check_many(
+        &self,
+        channels: &[String],
+    ) -> Vec<(String, Result<Option<LiveStatus>, Error>)> {
+        let mut results = Vec::with_capacity(channels.len());
+        for channel in channels {
+            results.push((channel.clone(), self.check(channel).await));
+        }
+        results
+    }
+
+    /// Recording of the most recent broadcast, looked up once a stream has ended
+    async fn latest_vod(&self, _channel: &str) -> Result<Option<String>, Error> {
+        Ok(None)
+    }
+}
+
+static PROVIDERS: [&dyn StreamProvider; 3] = [&Kick, &Twitch, &Youtube];
+
+pub fn providers() -> &'static [&'static dyn StreamProvider] {
+    &PROVIDERS
+}
+
+pub fn provider(platform: &str) -> Option<&'static dyn StreamProvider> {
+    PROVIDERS
+        .iter()
+        .copied()
+        .find(|provider| provider.platform() == platform)
+}
+
+/// Find the provider that understands a URL along with the channel it points at
+pub fn parse_stream_url(url: &str) -> Option<(&'static dyn StreamProvider, String)> {
+    PROVIDERS
+        .iter()
+        .copied()
+        .find_map(|provider| provider.parse_url(url).map(|channel| (provider, channel)))
+}
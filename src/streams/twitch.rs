@@ -0,0 +1,254 @@
+use super::auth::TokenCache;
+use super::health;
+use super::provider::{LiveStatus, StreamProvider};
+use crate::{Error, HTTP_CLIENT};
+use poise::serenity_prelude as serenity;
+use serde::Deserialize;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Helix accepts up to 100 logins per streams request
+const HELIX_BATCH_SIZE: usize = 100;
+
+static TOKEN: LazyLock<TokenCache> = LazyLock::new(TokenCache::new);
+
+#[derive(Debug, Deserialize)]
+struct TwitchOAuthResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TwitchStreamsResponse {
+    data: Vec<TwitchStream>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TwitchUsersResponse {
+    data: Vec<TwitchUser>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TwitchUser {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TwitchVideosResponse {
+    data: Vec<TwitchVideo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TwitchVideo {
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TwitchStream {
+    user_login: String,
+    user_name: String,
+    game_name: Option<String>,
+    title: String,
+    viewer_count: u32,
+    started_at: String,
+    thumbnail_url: String,
+}
+
+pub struct Twitch;
+
+/// Client id and secret, an error rather than a panic so other platforms keep being checked
+fn credentials() -> Result<(&'static str, &'static str), Error> {
+    match (
+        crate::env::TWITCH_CLIENT_ID.as_deref(),
+        crate::env::TWITCH_CLIENT_SECRET.as_deref(),
+    ) {
+        (Some(id), Some(secret)) => Ok((id, secret)),
+        _ => Err("Twitch is not configured, set TWITCH_CLIENT_ID and TWITCH_CLIENT_SECRET".into()),
+    }
+}
+
+async fn get_twitch_oauth_token() -> Result<String, Error> {
+    TOKEN.get(request_twitch_oauth_token).await
+}
+
+async fn request_twitch_oauth_token() -> Result<(String, Duration), Error> {
+    let client = HTTP_CLIENT.get().unwrap();
+    let (client_id, client_secret) = credentials()?;
+
+    let params = [
+        ("grant_type", "client_credentials"),
+        ("client_id", client_id),
+        ("client_secret", client_secret),
+    ];
+
+    let response = client
+        .post("https://id.twitch.tv/oauth2/token")
+        .form(&params)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(format!("OAuth token request failed: {}", response.status()).into());
+    }
+
+    let oauth_response: TwitchOAuthResponse = response.json().await?;
+    let expires_in = Duration::from_secs(oauth_response.expires_in.unwrap_or(3600));
+    Ok((oauth_response.access_token, expires_in))
+}
+
+async fn helix_get<T: serde::de::DeserializeOwned>(
+    endpoint: &str,
+    query: &[(&str, &str)],
+) -> Result<T, Error> {
+    let (client_id, _) = credentials()?;
+    let access_token = get_twitch_oauth_token().await?;
+
+    let response = HTTP_CLIENT
+        .get()
+        .unwrap()
+        .get(format!("https://api.twitch.tv/helix/{}", endpoint))
+        .query(query)
+        .header("Client-Id", client_id)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await?;
+
+    log::debug!("Received response with status: {}", response.status());
+    health::record_rate_limit("twitch", &response);
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        TOKEN.invalidate().await;
+    }
+    if !response.status().is_success() {
+        return Err(format!("Twitch API request failed: {}", response.status()).into());
+    }
+
+    Ok(response.json().await?)
+}
+
+/// Live streams for a batch of Twitch logins, offline channels are left out
+async fn check_twitch_streams(channel_names: &[String]) -> Result<Vec<TwitchStream>, Error> {
+    log::debug!("Checking Twitch channels: {}", channel_names.join(", "));
+
+    // Helix pages at 20 results by default, a full batch needs a page as large as the batch
+    let first = HELIX_BATCH_SIZE.to_string();
+    let query: Vec<(&str, &str)> = std::iter::once(("first", first.as_str()))
+        .chain(
+            channel_names
+                .iter()
+                .map(|channel| ("user_login", channel.as_str())),
+        )
+        .collect();
+    let api_response: TwitchStreamsResponse = helix_get("streams", &query).await?;
+    Ok(api_response.data)
+}
+
+fn live_status(stream: TwitchStream) -> LiveStatus {
+    // Helix thumbnails are templated, the timestamp stops Discord serving a stale cached image
+    let thumbnail = format!(
+        "{}?t={}",
+        stream
+            .thumbnail_url
+            .replace("{width}", "1280")
+            .replace("{height}", "720"),
+        chrono::Utc::now().timestamp()
+    );
+
+    LiveStatus {
+        url: format!("https://www.twitch.tv/{}", stream.user_login),
+        channel_name: stream.user_name,
+        title: Some(stream.title),
+        description: None,
+        thumbnail: Some(thumbnail),
+        viewer_count: Some(stream.viewer_count),
+        category: stream.game_name.filter(|game| !game.is_empty()),
+        vod_url: None,
+        started_at: stream.started_at,
+    }
+}
+
+#[serenity::async_trait]
+impl StreamProvider for Twitch {
+    fn platform(&self) -> &'static str {
+        "twitch"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Twitch"
+    }
+
+    fn parse_url(&self, url: &str) -> Option<String> {
+        // Parse twitch.tv URLs like https://www.twitch.tv/channelname
+        if url.contains("twitch.tv/")
+            && let Some(channel) = url.split("twitch.tv/").nth(1)
+        {
+            let channel_name = channel
+                .split(['/', '?'])
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+            if !channel_name.is_empty() {
+                return Some(channel_name);
+            }
+        }
+        None
+    }
+
+    fn channel_url(&self, channel: &str) -> String {
+        format!("https://www.twitch.tv/{}", channel)
+    }
+
+    fn batch_size(&self) -> usize {
+        HELIX_BATCH_SIZE
+    }
+
+    async fn check(&self, channel: &str) -> Result<Option<LiveStatus>, Error> {
+        let streams = check_twitch_streams(&[channel.to_string()]).await?;
+        Ok(streams.into_iter().next().map(live_status))
+    }
+
+    async fn check_many(
+        &self,
+        channels: &[String],
+    ) -> Vec<(String, Result<Option<LiveStatus>, Error>)> {
+        let mut streams = match check_twitch_streams(channels).await {
+            Ok(streams) => streams,
+            Err(e) => {
+                return channels
+                    .iter()
+                    .map(|channel| (channel.clone(), Err(e.to_string().into())))
+                    .collect();
+            }
+        };
+
+        channels
+            .iter()
+            .map(|channel| {
+                let stream = streams
+                    .iter()
+                    .position(|stream| stream.user_login.eq_ignore_ascii_case(channel))
+                    .map(|index| live_status(streams.swap_remove(index)));
+                (channel.clone(), Ok(stream))
+            })
+            .collect()
+    }
+
+    async fn latest_vod(&self, channel: &str) -> Result<Option<String>, Error> {
+        let users: TwitchUsersResponse = helix_get("users", &[("login", channel)]).await?;
+        let Some(user) = users.data.into_iter().next() else {
+            return Ok(None);
+        };
+
+        // Past broadcasts are only kept when the channel has VODs enabled
+        let videos: TwitchVideosResponse = helix_get(
+            "videos",
+            &[
+                ("user_id", user.id.as_str()),
+                ("type", "archive"),
+                ("first", "1"),
+            ],
+        )
+        .await?;
+        Ok(videos.data.into_iter().next().map(|video| video.url))
+    }
+}
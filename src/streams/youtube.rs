@@ -0,0 +1,279 @@
+use super::provider::{LiveStatus, StreamProvider};
+use crate::{Error, HTTP_CLIENT};
+use poise::serenity_prelude as serenity;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+static CHANNEL_ID_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"<link rel="canonical" href="https://www\.youtube\.com/channel/(UC[\w-]{22})""#)
+        .unwrap()
+});
+static VIDEO_ID_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<yt:videoId>([\w-]{11})</yt:videoId>").unwrap());
+static TITLE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"<meta name="title" content="([^"]*)""#).unwrap());
+static OWNER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""ownerChannelName":"((?:[^"\\]|\\.)*)""#).unwrap());
+static START_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""startTimestamp":"([^"]+)""#).unwrap());
+
+// Handles rarely change channel, so resolved ids are reused for a while
+static CHANNEL_IDS: LazyLock<Mutex<HashMap<String, (String, Instant)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+const CHANNEL_ID_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Newest uploads looked up through the Data API, a single request covers them all
+const API_VIDEOS: usize = 5;
+/// Newest uploads probed without a key, each one costs a page fetch
+const PROBED_VIDEOS: usize = 3;
+
+#[derive(Debug)]
+struct YoutubeLive {
+    video_id: String,
+    title: String,
+    channel_title: String,
+    started_at: String,
+    viewer_count: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VideosResponse {
+    items: Vec<VideoItem>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VideoItem {
+    id: String,
+    snippet: VideoSnippet,
+    live_streaming_details: Option<LiveStreamingDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VideoSnippet {
+    title: String,
+    channel_title: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LiveStreamingDetails {
+    actual_start_time: Option<String>,
+    actual_end_time: Option<String>,
+    concurrent_viewers: Option<String>,
+}
+
+fn channel_path(channel: &str) -> String {
+    if channel.starts_with("UC") && !channel.contains('/') {
+        format!("channel/{}", channel)
+    } else {
+        channel.to_string()
+    }
+}
+
+fn decode_html(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+async fn fetch_page(url: &str) -> Result<String, Error> {
+    let response = HTTP_CLIENT
+        .get()
+        .unwrap()
+        .get(url)
+        // Skips the EU cookie consent interstitial
+        .header("Cookie", "CONSENT=YES+1")
+        .header("Accept-Language", "en-US,en")
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(format!("YouTube request failed: {}", response.status()).into());
+    }
+    Ok(response.text().await?)
+}
+
+async fn resolve_channel_id(channel: &str) -> Result<String, Error> {
+    if channel.starts_with("UC") && !channel.contains('/') {
+        return Ok(channel.to_string());
+    }
+    if let Some((id, resolved_at)) = CHANNEL_IDS.lock().unwrap().get(channel)
+        && resolved_at.elapsed() < CHANNEL_ID_TTL
+    {
+        return Ok(id.clone());
+    }
+
+    let page = fetch_page(&format!("https://www.youtube.com/{}", channel)).await?;
+    let id = CHANNEL_ID_RE
+        .captures(&page)
+        .map(|caps| caps[1].to_string())
+        .ok_or_else(|| format!("Could not resolve YouTube channel {}", channel))?;
+
+    CHANNEL_IDS
+        .lock()
+        .unwrap()
+        .insert(channel.to_string(), (id.clone(), Instant::now()));
+    Ok(id)
+}
+
+/// Ids of the channel's newest uploads from its Atom feed, newest first.
+/// Scheduled and live broadcasts show up among them
+async fn recent_video_ids(channel: &str, limit: usize) -> Result<Vec<String>, Error> {
+    let channel_id = resolve_channel_id(channel).await?;
+    let feed = fetch_page(&format!(
+        "https://www.youtube.com/feeds/videos.xml?channel_id={}",
+        channel_id
+    ))
+    .await?;
+
+    Ok(VIDEO_ID_RE
+        .captures_iter(&feed)
+        .map(|caps| caps[1].to_string())
+        .take(limit)
+        .collect())
+}
+
+/// Live check using the channel's Atom feed and the Data API, costs one quota unit
+async fn check_with_api(channel: &str, api_key: &str) -> Result<Option<YoutubeLive>, Error> {
+    let video_ids = recent_video_ids(channel, API_VIDEOS).await?;
+    if video_ids.is_empty() {
+        return Ok(None);
+    }
+
+    let response = HTTP_CLIENT
+        .get()
+        .unwrap()
+        .get("https://www.googleapis.com/youtube/v3/videos")
+        .query(&[
+            ("part", "snippet,liveStreamingDetails"),
+            ("id", &video_ids.join(",")),
+            ("key", api_key),
+        ])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(format!("YouTube Data API request failed: {}", response.status()).into());
+    }
+
+    let videos: VideosResponse = response.json().await?;
+    Ok(videos.items.into_iter().find_map(|video| {
+        let details = video.live_streaming_details?;
+        if details.actual_end_time.is_some() {
+            return None;
+        }
+        Some(YoutubeLive {
+            video_id: video.id,
+            title: video.snippet.title,
+            channel_title: video.snippet.channel_title,
+            started_at: details.actual_start_time?,
+            viewer_count: details
+                .concurrent_viewers
+                .and_then(|viewers| viewers.parse().ok()),
+        })
+    }))
+}
+
+/// Live check without an API key using the channel's Atom feed,
+/// probing the watch page of each recent upload until one is live
+async fn check_with_feed(channel: &str) -> Result<Option<YoutubeLive>, Error> {
+    for video_id in recent_video_ids(channel, PROBED_VIDEOS).await? {
+        if let Some(live) = probe_watch_page(channel, video_id).await? {
+            return Ok(Some(live));
+        }
+    }
+    Ok(None)
+}
+
+async fn probe_watch_page(channel: &str, video_id: String) -> Result<Option<YoutubeLive>, Error> {
+    let page = fetch_page(&format!("https://www.youtube.com/watch?v={}", video_id)).await?;
+
+    if !page.contains(r#""isLiveNow":true"#) {
+        return Ok(None);
+    }
+
+    let capture = |re: &Regex| re.captures(&page).map(|caps| caps[1].to_string());
+    Ok(Some(YoutubeLive {
+        title: capture(&TITLE_RE)
+            .map(|title| decode_html(&title))
+            .unwrap_or_else(|| "Live on YouTube".to_string()),
+        channel_title: capture(&OWNER_RE)
+            .map(|name| name.replace("\\\"", "\"").replace("\\u0026", "&"))
+            .unwrap_or_else(|| channel.to_string()),
+        // The video id identifies the session if the start time isn't in the page
+        started_at: capture(&START_RE).unwrap_or_else(|| video_id.clone()),
+        video_id,
+        viewer_count: None,
+    }))
+}
+
+pub struct Youtube;
+
+#[serenity::async_trait]
+impl StreamProvider for Youtube {
+    fn platform(&self) -> &'static str {
+        "youtube"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "YouTube"
+    }
+
+    /// Parse channel URLs into `@handle`, a `UC...` channel id, or a legacy `c/` or `user/` path
+    fn parse_url(&self, url: &str) -> Option<String> {
+        let path = url.split("youtube.com/").nth(1)?;
+        let mut segments = path.split(['/', '?']).filter(|s| !s.is_empty());
+
+        match segments.next()? {
+            handle if handle.starts_with('@') && handle.len() > 1 => Some(handle.to_lowercase()),
+            "channel" => segments
+                .next()
+                .filter(|id| id.starts_with("UC"))
+                .map(str::to_string),
+            kind @ ("c" | "user") => segments.next().map(|name| format!("{}/{}", kind, name)),
+            _ => None,
+        }
+    }
+
+    fn channel_url(&self, channel: &str) -> String {
+        format!("https://www.youtube.com/{}", channel_path(channel))
+    }
+
+    /// Finds recent uploads in the channel's Atom feed and asks the Data API about them when
+    /// `YOUTUBE_API_KEY` is set, falling back to probing their watch pages otherwise
+    async fn check(&self, channel: &str) -> Result<Option<LiveStatus>, Error> {
+        log::debug!("Checking YouTube channel: {}", channel);
+
+        let live = match crate::env::YOUTUBE_API_KEY.as_deref() {
+            Some(api_key) => check_with_api(channel, api_key).await?,
+            None => check_with_feed(channel).await?,
+        };
+
+        Ok(live.map(|live| {
+            let watch_url = format!("https://www.youtube.com/watch?v={}", live.video_id);
+            LiveStatus {
+                url: watch_url.clone(),
+                thumbnail: Some(format!(
+                    "https://i.ytimg.com/vi/{}/maxresdefault_live.jpg?t={}",
+                    live.video_id,
+                    chrono::Utc::now().timestamp()
+                )),
+                channel_name: live.channel_title,
+                title: Some(live.title),
+                description: None,
+                viewer_count: live.viewer_count,
+                category: None,
+                // The broadcast stays at the same URL once it ends
+                vod_url: Some(watch_url),
+                started_at: live.started_at,
+            }
+        }))
+    }
+}
@@ -1,13 +1,13 @@
 use crate::colors;
-use crate::{Error, KV_DATABASE};
+use crate::db::Table;
+use crate::{ACTIVE_TICKETS, Error, TICKETS};
 use poise::serenity_prelude as serenity;
-use redb::ReadableDatabase;
 use serde::{Deserialize, Serialize};
 
-// Table definitions
-const TICKETS: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("tickets");
-const ACTIVE_TICKETS: redb::TableDefinition<&str, &str> =
-    redb::TableDefinition::new("active_tickets");
+/// Keyed by menu id
+const TICKET_MENUS: Table<TicketMenu> = Table::new(TICKETS);
+/// Keyed by ticket channel
+const ACTIVE_TICKET_CHANNELS: Table<ActiveTicket> = Table::new(ACTIVE_TICKETS);
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TicketMenu {
@@ -316,14 +316,7 @@ pub async fn handle_ticket_button(
     // Parse ticket menu ID from custom_id (format: "ticket_create_{id}")
     if let Some(menu_id) = custom_id.strip_prefix("ticket_create_") {
         // Load ticket menu from database
-        let db = KV_DATABASE.get().unwrap();
-        let tx = db.begin_read()?;
-        let table = tx.open_table(TICKETS)?;
-
-        let menu_data = table.get(menu_id)?;
-        if menu_data.is_none() {
-            drop(table);
-            drop(tx);
+        let Some(ticket_menu) = TICKET_MENUS.get(menu_id)? else {
             interaction
                 .create_response(
                     ctx,
@@ -335,11 +328,7 @@ pub async fn handle_ticket_button(
                 )
                 .await?;
             return Ok(());
-        }
-
-        let ticket_menu: TicketMenu = serde_json::from_str(menu_data.unwrap().value())?;
-        drop(table);
-        drop(tx);
+        };
 
         // Helper to truncate placeholder to Discord's 100 character limit
         let truncate_placeholder = |s: Option<&String>| -> String {
@@ -409,14 +398,7 @@ pub async fn handle_ticket_modal(
     // Parse ticket menu ID from custom_id (format: "ticket_modal_{id}")
     if let Some(menu_id) = custom_id.strip_prefix("ticket_modal_") {
         // Load ticket menu from database
-        let db = KV_DATABASE.get().unwrap();
-        let tx = db.begin_read()?;
-        let table = tx.open_table(TICKETS)?;
-
-        let menu_data = table.get(menu_id)?;
-        if menu_data.is_none() {
-            drop(table);
-            drop(tx);
+        let Some(ticket_menu) = TICKET_MENUS.get(menu_id)? else {
             interaction
                 .create_response(
                     ctx,
@@ -428,11 +410,7 @@ pub async fn handle_ticket_modal(
                 )
                 .await?;
             return Ok(());
-        }
-
-        let ticket_menu: TicketMenu = serde_json::from_str(menu_data.unwrap().value())?;
-        drop(table);
-        drop(tx);
+        };
 
         // Extract modal values
         let title = interaction
@@ -594,58 +572,23 @@ fn generate_ticket_number() -> Result<u32, Error> {
 
 /// Save ticket menu to database
 pub fn save_ticket_menu(menu: &TicketMenu) -> Result<(), Error> {
-    let db = KV_DATABASE.get().unwrap();
-    let tx = db.begin_write()?;
-    {
-        let mut table = tx.open_table(TICKETS)?;
-        let key = menu.get_key();
-        let value = serde_json::to_string(menu)?;
-        table.insert(key.as_str(), value.as_str())?;
-    }
-    tx.commit()?;
+    TICKET_MENUS.insert(&menu.get_key(), menu)?;
     Ok(())
 }
 
 /// Load ticket menu from database
 pub fn load_ticket_menu(id: &str) -> Result<Option<TicketMenu>, Error> {
-    let db = KV_DATABASE.get().unwrap();
-    let tx = db.begin_read()?;
-    let table = tx.open_table(TICKETS)?;
-
-    if let Some(value) = table.get(id)? {
-        let menu: TicketMenu = serde_json::from_str(value.value())?;
-        Ok(Some(menu))
-    } else {
-        Ok(None)
-    }
+    Ok(TICKET_MENUS.get(id)?)
 }
 
 /// List all ticket menus
 pub fn list_ticket_menus() -> Result<Vec<TicketMenu>, Error> {
-    let db = KV_DATABASE.get().unwrap();
-    let tx = db.begin_read()?;
-    let table = tx.open_table(TICKETS)?;
-
-    let mut menus = Vec::new();
-    for item in table.range::<&str>(..)? {
-        let (_, value) = item?;
-        if let Ok(menu) = serde_json::from_str::<TicketMenu>(value.value()) {
-            menus.push(menu);
-        }
-    }
-
-    Ok(menus)
+    Ok(TICKET_MENUS.values()?)
 }
 
 /// Delete ticket menu from database
 pub fn delete_ticket_menu(id: &str) -> Result<(), Error> {
-    let db = KV_DATABASE.get().unwrap();
-    let tx = db.begin_write()?;
-    {
-        let mut table = tx.open_table(TICKETS)?;
-        table.remove(id)?;
-    }
-    tx.commit()?;
+    TICKET_MENUS.remove(id)?;
     Ok(())
 }
 
@@ -660,41 +603,18 @@ pub struct ActiveTicket {
 
 /// Store active ticket in database
 fn save_active_ticket(ticket: &ActiveTicket) -> Result<(), Error> {
-    let db = KV_DATABASE.get().unwrap();
-    let tx = db.begin_write()?;
-    {
-        let mut table = tx.open_table(ACTIVE_TICKETS)?;
-        let value = serde_json::to_string(ticket)?;
-        table.insert(ticket.channel_id.as_str(), value.as_str())?;
-    }
-    tx.commit()?;
+    ACTIVE_TICKET_CHANNELS.insert(&ticket.channel_id, ticket)?;
     Ok(())
 }
 
 /// Load active ticket from database by channel ID
 fn load_active_ticket(channel_id: &str) -> Result<Option<ActiveTicket>, Error> {
-    let db = KV_DATABASE.get().unwrap();
-    let tx = db.begin_read()?;
-    let table = tx.open_table(ACTIVE_TICKETS)?;
-
-    if let Some(value) = table.get(channel_id)? {
-        let ticket: ActiveTicket = serde_json::from_str(value.value())?;
-        Ok(Some(ticket))
-    } else {
-        Ok(None)
-    }
+    Ok(ACTIVE_TICKET_CHANNELS.get(channel_id)?)
 }
 
 /// Delete active ticket from database
 fn delete_active_ticket(channel_id: &str) -> Result<(), Error> {
-    let db = KV_DATABASE.get().unwrap();
-    let tx = db.begin_write()?;
-    {
-        let mut table = tx.open_table(ACTIVE_TICKETS)?;
-        let key = channel_id.to_string();
-        table.remove(key.as_str())?;
-    }
-    tx.commit()?;
+    ACTIVE_TICKET_CHANNELS.remove(channel_id)?;
     Ok(())
 }
 
@@ -1,9 +1,14 @@
+use crate::audit::{self, AuditEntry};
 use crate::colors;
+use crate::db::Table;
 use crate::{Context, Error, KV_DATABASE, PAPERS};
 use poise::serenity_prelude as serenity;
-use redb::ReadableDatabase;
+use redb::{ReadableDatabase, ReadableTable};
 use serde::{Deserialize, Serialize};
 
+/// Keyed `<channel id>_<message id>`
+const PAPERS_STATES: Table<PapersState> = Table::new(PAPERS);
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PapersState {
     pub channel_id: u64,
@@ -60,7 +65,7 @@ pub struct ButtonConfig {
     pub emoji: Option<String>, // Partial emoji: either unicode emoji or custom emoji format
 }
 
-// Old ButtonConfig structure for migration (v0)
+// Old ButtonConfig structure, see `migrate_role_buttons`
 #[derive(Debug, Deserialize)]
 struct ButtonConfigV0 {
     pub name: String, // Old field name
@@ -122,7 +127,7 @@ impl PapersState {
     }
 
     pub fn get_key(&self) -> String {
-        format!("{}_{}", self.channel_id, self.message_id)
+        crate::db::key(&[&self.channel_id, &self.message_id])
     }
 
     pub fn to_embed(&self) -> serenity::CreateEmbed {
@@ -252,45 +257,17 @@ fn parse_custom_emoji(emoji_str: &str) -> Option<serenity::ReactionType> {
 }
 
 pub fn load_papers_state_by_channel(channel_id: u64) -> Result<Option<PapersState>, Error> {
-    let db = KV_DATABASE.get().unwrap();
-    let tx = db.begin_read()?;
-    let table = tx.open_table(PAPERS)?;
-
-    // Scan for any state in this channel
-    for item in table.range::<&str>(..)? {
-        let (_, value) = item?;
-
-        // Try to deserialize with migration support
-        let state: PapersState = match serde_json::from_str(value.value()) {
-            Ok(s) => s,
-            Err(_) => {
-                // Try to migrate from old format
-                match try_migrate_papers_state(value.value()) {
-                    Ok(migrated) => {
-                        // Save migrated version
-                        drop(table);
-                        drop(tx);
-                        save_papers_state(&migrated)?;
-                        return Ok(Some(migrated));
-                    }
-                    Err(e) => {
-                        return Err(format!("Failed to load or migrate papers state: {}", e).into());
-                    }
-                }
-            }
-        };
-
-        if state.channel_id == channel_id {
-            return Ok(Some(state));
-        }
-    }
-
-    Ok(None)
+    Ok(PAPERS_STATES
+        .values()?
+        .into_iter()
+        .find(|state| state.channel_id == channel_id))
 }
 
-// Migration function from old JSON format
-fn try_migrate_papers_state(json: &str) -> Result<PapersState, Error> {
-    // Try parsing as old format with ButtonConfigV0
+/// Schema migration 1: buttons used to be role buttons only, with a `name` and `role_id`
+pub fn migrate_role_buttons(
+    tx: &redb::WriteTransaction,
+    _: &crate::db::Lookups,
+) -> Result<(), Error> {
     #[derive(Deserialize)]
     struct PapersStateV0 {
         channel_id: u64,
@@ -300,36 +277,75 @@ fn try_migrate_papers_state(json: &str) -> Result<PapersState, Error> {
         buttons: Vec<ButtonConfigV0>,
     }
 
-    let old_state: PapersStateV0 = serde_json::from_str(json)?;
+    let mut table = tx.open_table(PAPERS)?;
+    let mut migrated = Vec::new();
+    for item in table.range::<&str>(..)? {
+        let (key, value) = item?;
+        if serde_json::from_str::<PapersState>(value.value()).is_ok() {
+            continue;
+        }
+        // Anything that isn't the old format either is left for `/papers prune`
+        let Ok(old_state) = serde_json::from_str::<PapersStateV0>(value.value()) else {
+            continue;
+        };
+        let state = PapersState {
+            channel_id: old_state.channel_id,
+            message_id: old_state.message_id,
+            guild_id: old_state.guild_id,
+            embed_config: old_state.embed_config,
+            buttons: old_state
+                .buttons
+                .into_iter()
+                .map(|b| ButtonConfig {
+                    label: b.name,
+                    button_type: ButtonType::Role { role_id: b.role_id },
+                    style: b.style,
+                    emoji: b.emoji,
+                })
+                .collect(),
+        };
+        migrated.push((key.value().to_string(), serde_json::to_string(&state)?));
+    }
 
-    Ok(PapersState {
-        channel_id: old_state.channel_id,
-        message_id: old_state.message_id,
-        guild_id: old_state.guild_id,
-        embed_config: old_state.embed_config,
-        buttons: old_state
-            .buttons
-            .into_iter()
-            .map(|b| ButtonConfig {
-                label: b.name,
-                button_type: ButtonType::Role { role_id: b.role_id },
-                style: b.style,
-                emoji: b.emoji,
-            })
-            .collect(),
-    })
+    for (key, value) in &migrated {
+        table.insert(key.as_str(), value.as_str())?;
+    }
+    log::info!(
+        "Migrated {} papers messages to button types",
+        migrated.len()
+    );
+    Ok(())
 }
 
 pub fn save_papers_state(state: &PapersState) -> Result<(), Error> {
-    let key = state.get_key();
-    let value = serde_json::to_string(state)?;
-    crate::db::write_entry(PAPERS, &key, &value)
+    PAPERS_STATES
+        .insert(&state.get_key(), state)
+        .map(|_| ())
         .map_err(|e| format!("Failed to save papers state: {}", e).into())
 }
 
+/// Save a change made through a command and record it in the audit log
+fn save_papers_state_audited(
+    ctx: &Context<'_>,
+    action: &str,
+    state: &PapersState,
+) -> Result<(), Error> {
+    let key = state.get_key();
+    let before = PAPERS_STATES
+        .insert(&key, state)
+        .map_err(|e| format!("Failed to save papers state: {}", e))?;
+    audit::record(
+        AuditEntry::discord(*ctx, action, &key)
+            .before(&before)
+            .after(state),
+    );
+    Ok(())
+}
+
 pub fn delete_papers_state(channel_id: u64, message_id: u64) -> Result<(), Error> {
-    let key = format!("{}_{}", channel_id, message_id);
-    crate::db::delete_entry(PAPERS, &key)
+    PAPERS_STATES
+        .remove(&crate::db::key(&[&channel_id, &message_id]))
+        .map(|_| ())
         .map_err(|e| format!("Failed to delete papers state: {}", e).into())
 }
 
@@ -488,7 +504,7 @@ async fn start(ctx: Context<'_>) -> Result<(), Error> {
     // Update state with message ID and save
     let mut state = state;
     state.message_id = sent_message.id.get();
-    save_papers_state(&state)?;
+    save_papers_state_audited(&ctx, "papers.create", &state)?;
 
     send_embed_reply(
         &ctx,
@@ -540,7 +556,7 @@ async fn button(
     });
 
     // Save and update message
-    save_papers_state(&state)?;
+    save_papers_state_audited(&ctx, "papers.update", &state)?;
     update_papers_message(ctx.serenity_context().http.as_ref(), &state).await?;
 
     send_embed_reply(
@@ -604,7 +620,7 @@ async fn link(
     });
 
     // Save and update message
-    save_papers_state(&state)?;
+    save_papers_state_audited(&ctx, "papers.update", &state)?;
     update_papers_message(ctx.serenity_context().http.as_ref(), &state).await?;
 
     send_embed_reply(
@@ -741,7 +757,7 @@ async fn editbutton(
                     btn.emoji = new_emoji;
                 }
 
-                save_papers_state(&state)?;
+                save_papers_state_audited(&ctx, "papers.update", &state)?;
                 update_papers_message(app_ctx.serenity_context.http.as_ref(), &state).await?;
 
                 response
@@ -808,7 +824,7 @@ async fn delbutton(
     match removal_result {
         Some((index, button_name)) => {
             // Save and update message
-            save_papers_state(&state)?;
+            save_papers_state_audited(&ctx, "papers.update", &state)?;
             update_papers_message(ctx.serenity_context().http.as_ref(), &state).await?;
 
             send_embed_reply(
@@ -918,7 +934,7 @@ async fn author(ctx: Context<'_>) -> Result<(), Error> {
                 state.embed_config.author_name = new_author_name.clone();
                 state.embed_config.author_url = new_author_url.clone();
 
-                save_papers_state(&state)?;
+                save_papers_state_audited(&ctx, "papers.update", &state)?;
                 update_papers_message(app_ctx.serenity_context.http.as_ref(), &state).await?;
 
                 let description = match new_author_name {
@@ -1062,7 +1078,7 @@ async fn body(ctx: Context<'_>) -> Result<(), Error> {
                 state.embed_config.color = new_color;
                 state.embed_config.footer_text = new_footer;
 
-                save_papers_state(&state)?;
+                save_papers_state_audited(&ctx, "papers.update", &state)?;
                 update_papers_message(app_ctx.serenity_context.http.as_ref(), &state).await?;
 
                 response
@@ -1188,7 +1204,7 @@ async fn images(ctx: Context<'_>) -> Result<(), Error> {
                 state.embed_config.author_icon_url = new_author_icon;
                 state.embed_config.footer_icon_url = new_footer_icon;
 
-                save_papers_state(&state)?;
+                save_papers_state_audited(&ctx, "papers.update", &state)?;
                 update_papers_message(app_ctx.serenity_context.http.as_ref(), &state).await?;
 
                 response
@@ -1237,6 +1253,7 @@ async fn stop(ctx: Context<'_>) -> Result<(), Error> {
 
     // Remove from database
     delete_papers_state(state.channel_id, state.message_id)?;
+    audit::record(AuditEntry::discord(ctx, "papers.delete", &state.get_key()).before(&state));
 
     send_embed_reply(
         &ctx,
@@ -1253,6 +1270,7 @@ async fn stop(ctx: Context<'_>) -> Result<(), Error> {
 /// Clean up papers messages that no longer exist
 #[poise::command(prefix_command, slash_command)]
 async fn prune(ctx: Context<'_>) -> Result<(), Error> {
+    // Read raw rather than through `PAPERS_STATES`, which would skip the corrupted entries this removes
     let db = KV_DATABASE.get().unwrap();
     let tx = db.begin_read()?;
     let table = tx.open_table(PAPERS)?;
@@ -1266,20 +1284,10 @@ async fn prune(ctx: Context<'_>) -> Result<(), Error> {
         let (key, value) = item?;
 
         // Try to parse the state, if it fails mark as corrupted
-        let state = match serde_json::from_str::<PapersState>(value.value()) {
-            Ok(s) => s,
-            Err(_) => {
-                // Try migration
-                match try_migrate_papers_state(value.value()) {
-                    Ok(s) => s,
-                    Err(_) => {
-                        // Corrupted data, mark for deletion
-                        corrupted += 1;
-                        to_delete.push(key.value().to_string());
-                        continue;
-                    }
-                }
-            }
+        let Ok(state) = serde_json::from_str::<PapersState>(value.value()) else {
+            corrupted += 1;
+            to_delete.push(key.value().to_string());
+            continue;
         };
 
         checked += 1;
@@ -1311,6 +1319,11 @@ async fn prune(ctx: Context<'_>) -> Result<(), Error> {
             }
         }
         tx.commit()?;
+
+        audit::record(
+            AuditEntry::discord(ctx, "papers.prune", &format!("{} entries", deleted))
+                .after(serde_json::json!({ "deleted": to_delete, "corrupted": corrupted })),
+        );
     }
 
     let mut description = format!(
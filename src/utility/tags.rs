@@ -1,3 +1,4 @@
+use crate::audit::{self, AuditEntry};
 use crate::env::FOOTER_URL;
 use crate::{Context, Error, KV_DATABASE, TABLE, colors};
 
@@ -19,12 +20,25 @@ pub async fn tag(_ctx: Context<'_>) -> Result<(), Error> {
 pub async fn add(ctx: Context<'_>, key: String, value: String) -> Result<(), Error> {
     let db = KV_DATABASE.get().unwrap();
     let tx = db.begin_write()?;
-    {
+    let before = {
         let mut table = tx.open_table(TABLE)?;
-        let _ = table.insert(key.as_str(), value.as_str());
-    }
+        table
+            .insert(key.as_str(), value.as_str())?
+            .map(|old| old.value().to_string())
+    };
     tx.commit()?;
 
+    let action = if before.is_some() {
+        "tag.update"
+    } else {
+        "tag.create"
+    };
+    audit::record(
+        AuditEntry::discord(ctx, action, &key)
+            .before(&before)
+            .after(&value),
+    );
+
     let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
     let reply = {
         let embed = serenity::CreateEmbed::new()
@@ -45,12 +59,18 @@ pub async fn add(ctx: Context<'_>, key: String, value: String) -> Result<(), Err
 pub async fn remove(ctx: Context<'_>, key: String) -> Result<(), Error> {
     let db = KV_DATABASE.get().unwrap();
     let tx = db.begin_write()?;
-    {
+    let before = {
         let mut table = tx.open_table(TABLE)?;
-        let _ = table.remove(key.as_str());
-    }
+        table
+            .remove(key.as_str())?
+            .map(|old| old.value().to_string())
+    };
     tx.commit()?;
 
+    if before.is_some() {
+        audit::record(AuditEntry::discord(ctx, "tag.delete", &key).before(&before));
+    }
+
     let footer = serenity::CreateEmbedFooter::new(format!("Powered by {}", &*FOOTER_URL));
     let reply = {
         let embed = serenity::CreateEmbed::new()
@@ -0,0 +1,309 @@
+use crate::db::Table;
+use crate::permissions::{self, Permission};
+use crate::{Error, SESSIONS};
+use actix_web::body::MessageBody;
+use actix_web::cookie::{Cookie, SameSite, time};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::{Method, header};
+use actix_web::middleware::Next;
+use actix_web::{FromRequest, HttpMessage, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::future::{Ready, ready};
+
+pub const SESSION_COOKIE: &str = "session";
+const SESSION_DAYS: i64 = 7;
+
+/// Keyed by session id
+const SESSION_TABLE: Table<Session> = Table::new(SESSIONS);
+
+// Reachable without logging in
+const PUBLIC_PATHS: [&str; 2] = ["/api/auth/", "/api/health"];
+
+// Outside /api where Prometheus expects it, but still needs a token
+const METRICS_PATH: &str = "/metrics";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub user_id: u64,
+    pub username: String,
+    pub avatar: Option<String>,
+    /// Unix timestamp
+    pub expires_at: i64,
+}
+
+/// The logged in dashboard user, extracted in handlers that need to know who is acting
+#[derive(Debug, Clone)]
+pub struct WebUser {
+    pub user_id: u64,
+    pub username: String,
+    pub avatar: Option<String>,
+    /// Set when authenticated with an API token instead of a session
+    pub scopes: Option<Vec<String>>,
+}
+
+impl WebUser {
+    pub fn is_owner(&self) -> bool {
+        self.user_id == *crate::env::AUTHOR_ID
+    }
+
+    /// Whether the user holds `permission` in the guild.
+    /// Global resources (`None`) span every guild, so only the bot owner may touch them.
+    pub async fn has_permission(&self, guild_id: Option<u64>, permission: Permission) -> bool {
+        if self.is_owner() {
+            return true;
+        }
+        let Some(guild_id) = guild_id else {
+            return false;
+        };
+
+        let role_ids = member_role_ids(guild_id, self.user_id).await;
+        permissions::has_permission(guild_id, self.user_id, &role_ids, permission)
+            .await
+            .unwrap_or_else(|e| {
+                log::error!("Failed to check web permissions: {}", e);
+                false
+            })
+    }
+
+    /// Guilds whose data the user may list, `None` for the bot owner who sees every guild.
+    /// Rejects the request with a 403 when the user holds `permission` in no guild at all.
+    pub async fn visible_guilds(
+        &self,
+        permission: Permission,
+    ) -> Result<Option<Vec<u64>>, HttpResponse> {
+        if self.is_owner() {
+            return Ok(None);
+        }
+
+        let mut guilds = Vec::new();
+        for guild_id in permissions::granted_guilds().unwrap_or_default() {
+            if self
+                .has_permission(Some(guild_id), permission.clone())
+                .await
+            {
+                guilds.push(guild_id);
+            }
+        }
+        if guilds.is_empty() {
+            return Err(HttpResponse::Forbidden().json(serde_json::json!({
+                "error": format!("Requires the {} permission", permission.as_str())
+            })));
+        }
+        Ok(Some(guilds))
+    }
+
+    /// Reject the request with a 403 unless the user is the bot owner
+    pub fn require_owner(&self) -> Result<(), HttpResponse> {
+        if self.is_owner() {
+            return Ok(());
+        }
+        Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only the bot owner can do this"
+        })))
+    }
+
+    /// Reject the request with a 403 unless the user holds `permission`
+    pub async fn require(
+        &self,
+        guild_id: Option<u64>,
+        permission: Permission,
+    ) -> Result<(), HttpResponse> {
+        if self.has_permission(guild_id, permission.clone()).await {
+            return Ok(());
+        }
+        Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Requires the {} permission", permission.as_str())
+        })))
+    }
+}
+
+impl FromRequest for WebUser {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<WebUser>()
+                .cloned()
+                .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not logged in")),
+        )
+    }
+}
+
+async fn member_role_ids(guild_id: u64, user_id: u64) -> Vec<u64> {
+    let Some(http) = crate::DISCORD_HTTP.get() else {
+        return Vec::new();
+    };
+    http.get_member(guild_id.into(), user_id.into())
+        .await
+        .map(|member| member.roles.iter().map(|role| role.get()).collect())
+        .unwrap_or_default()
+}
+
+/// Store a new session and return its id
+pub fn create_session(
+    user_id: u64,
+    username: &str,
+    avatar: Option<String>,
+) -> Result<String, Error> {
+    prune_sessions()?;
+
+    let id = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let session = Session {
+        user_id,
+        username: username.to_string(),
+        avatar,
+        expires_at: (chrono::Utc::now() + chrono::Duration::days(SESSION_DAYS)).timestamp(),
+    };
+    SESSION_TABLE.insert(&id, &session)?;
+    Ok(id)
+}
+
+pub fn load_session(id: &str) -> Option<Session> {
+    let session = SESSION_TABLE.get(id).ok()??;
+    (session.expires_at > chrono::Utc::now().timestamp()).then_some(session)
+}
+
+pub fn delete_session(id: &str) -> Result<(), Error> {
+    SESSION_TABLE.remove(id)?;
+    Ok(())
+}
+
+fn prune_sessions() -> Result<(), Error> {
+    let now = chrono::Utc::now().timestamp();
+    // Read raw so sessions that no longer decode are pruned as well
+    let expired = crate::db::read_table(SESSIONS, |key, value| {
+        match serde_json::from_str::<Session>(value) {
+            Ok(session) if session.expires_at > now => None,
+            _ => Some(key.to_string()),
+        }
+    })?;
+    for id in expired {
+        SESSION_TABLE.remove(&id)?;
+    }
+    Ok(())
+}
+
+/// Cookies are only marked secure when the dashboard is served over https
+fn secure_cookies() -> bool {
+    crate::env::WEB_BASE_URL
+        .as_deref()
+        .is_some_and(|url| url.starts_with("https://"))
+}
+
+pub fn build_cookie(name: &str, value: String, max_age: time::Duration) -> Cookie<'static> {
+    Cookie::build(name.to_string(), value)
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(secure_cookies())
+        .max_age(max_age)
+        .finish()
+}
+
+pub fn session_cookie(id: String) -> Cookie<'static> {
+    build_cookie(SESSION_COOKIE, id, time::Duration::days(SESSION_DAYS))
+}
+
+pub fn removal_cookie(name: &str) -> Cookie<'static> {
+    build_cookie(name, String::new(), time::Duration::ZERO)
+}
+
+/// Compare secrets without leaking how much of them matched through timing
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn bearer_token(req: &ServiceRequest) -> Option<String> {
+    let value = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim().to_string())
+}
+
+/// The scope an API token needs for a route, `None` for routes tokens can't use
+fn route_scope(method: &Method, path: &str) -> Option<String> {
+    if path == METRICS_PATH {
+        return Some("metrics:read".to_string());
+    }
+    let resource = match path.strip_prefix("/api/")?.split('/').next()? {
+        resource @ ("tags" | "tickets" | "streams" | "music" | "permissions" | "history"
+        | "logs" | "audit") => resource,
+        // The live stream carries the same log records
+        "events" => "logs",
+        "stats" | "health" | "commands" | "emojis" | "channels" | "roles" | "aydy" => "info",
+        _ => return None,
+    };
+    let access = if method == Method::GET {
+        "read"
+    } else {
+        "write"
+    };
+    Some(format!("{}:{}", resource, access))
+}
+
+/// Resolve the session cookie or API token into a `WebUser` and turn away anonymous API requests
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let session = req
+        .cookie(SESSION_COOKIE)
+        .and_then(|cookie| load_session(cookie.value()));
+
+    if let Some(session) = session {
+        req.extensions_mut().insert(WebUser {
+            user_id: session.user_id,
+            username: session.username,
+            avatar: session.avatar,
+            scopes: None,
+        });
+    } else if let Some(bearer) = bearer_token(&req) {
+        let Some(token) = crate::api_tokens::verify_token(&bearer) else {
+            let response = HttpResponse::Unauthorized().json(serde_json::json!({
+                "error": "Invalid or expired API token"
+            }));
+            return Ok(req.into_response(response).map_into_right_body());
+        };
+        let scope = route_scope(req.method(), req.path());
+        if !scope.as_deref().is_some_and(|scope| token.allows(scope)) {
+            let response = HttpResponse::Forbidden().json(serde_json::json!({
+                "error": match scope {
+                    Some(scope) => format!("Token is missing the {} scope", scope),
+                    None => "Not available to API tokens".to_string(),
+                }
+            }));
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+        req.extensions_mut().insert(WebUser {
+            user_id: token.created_by,
+            username: format!("token:{}", token.name),
+            avatar: None,
+            scopes: Some(token.scopes),
+        });
+    } else {
+        let path = req.path();
+        let is_public = (!path.starts_with("/api/") && path != METRICS_PATH)
+            || PUBLIC_PATHS.iter().any(|public| path.starts_with(public));
+        if !is_public {
+            let response = HttpResponse::Unauthorized().json(serde_json::json!({
+                "error": "Not logged in"
+            }));
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
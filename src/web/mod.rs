@@ -1,6 +1,8 @@
+pub mod auth;
 mod services;
 
 use actix_files::{Files, NamedFile};
+use actix_web::middleware::from_fn;
 use actix_web::{App, HttpServer, Result as ActixResult, web};
 use miette::Result;
 use std::path::PathBuf;
@@ -14,6 +16,13 @@ pub async fn start_web_server(subsys: &mut tokio_graceful_shutdown::SubsystemHan
 
     let server = HttpServer::new(|| {
         App::new()
+            .wrap(from_fn(auth::authenticate))
+            // Auth endpoints
+            .service(services::auth::me)
+            .service(services::auth::login)
+            .service(services::auth::callback)
+            .service(services::auth::token_login)
+            .service(services::auth::logout)
             // General endpoints
             .service(services::general::stats)
             .service(services::general::health)
@@ -36,6 +45,42 @@ pub async fn start_web_server(subsys: &mut tokio_graceful_shutdown::SubsystemHan
             .service(services::tickets::delete_ticket)
             // Channel endpoints
             .service(services::channels::get_channels)
+            .service(services::channels::get_roles)
+            // Music endpoints
+            .service(services::music::events)
+            .service(services::music::get_queue)
+            .service(services::music::get_history)
+            .service(services::music::get_top)
+            .service(services::music::add_to_queue)
+            .service(services::music::reorder_queue)
+            .service(services::music::skip)
+            .service(services::music::pause)
+            .service(services::music::resume)
+            .service(services::music::set_volume)
+            .service(services::music::now_playing)
+            // Streams endpoints
+            .service(services::streams::get_streams)
+            .service(services::streams::get_live_streams)
+            .service(services::streams::create_follow)
+            .service(services::streams::update_follow)
+            .service(services::streams::delete_follow)
+            // Permissions endpoints
+            .service(services::permissions::get_policies)
+            .service(services::permissions::set_policy)
+            .service(services::permissions::delete_policy)
+            // Audit endpoints
+            .service(services::audit::get_audit)
+            // Events endpoints
+            .service(services::events::stream_events)
+            // Metrics endpoints
+            .service(services::metrics::metrics)
+            // Backup endpoints
+            .service(services::backup::export)
+            .service(services::backup::import)
+            // API token endpoints
+            .service(services::api_tokens::get_tokens)
+            .service(services::api_tokens::create_token)
+            .service(services::api_tokens::delete_token)
             // Static files
             .service(
                 Files::new("/", "./static")
@@ -0,0 +1,122 @@
+use crate::api_tokens;
+use crate::audit::{self, AuditEntry};
+use crate::permissions::Permission;
+use crate::web::auth::WebUser;
+use actix_web::{HttpResponse, Responder, delete, get, post, web};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct CreateTokenRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    /// e.g. `30d`, never expires when unset
+    pub duration: Option<String>,
+}
+
+fn bad_request(error: impl ToString) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": error.to_string()
+    }))
+}
+
+#[get("/api/apitokens")]
+pub async fn get_tokens(user: WebUser) -> impl Responder {
+    // Tokens act as their creator, so any guild admin may manage their own
+    if let Err(response) = user.visible_guilds(Permission::Admin).await {
+        return response;
+    }
+
+    match api_tokens::list_tokens() {
+        Ok(tokens) => {
+            let tokens: Vec<_> = tokens
+                .iter()
+                .filter(|token| token.managed_by(user.user_id))
+                .map(api_tokens::ApiToken::info_json)
+                .collect();
+            HttpResponse::Ok().json(serde_json::json!({
+                "tokens": tokens,
+                "count": tokens.len(),
+                "scopes": api_tokens::SCOPES,
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// Issue a token, the secret is only part of this response
+#[post("/api/apitokens")]
+pub async fn create_token(user: WebUser, request: web::Json<CreateTokenRequest>) -> impl Responder {
+    // Tokens act as their creator, so any guild admin may manage their own
+    if let Err(response) = user.visible_guilds(Permission::Admin).await {
+        return response;
+    }
+    if request.name.trim().is_empty() {
+        return bad_request("Name is required");
+    }
+    let scopes = match api_tokens::parse_scopes(&request.scopes) {
+        Ok(scopes) => scopes,
+        Err(e) => return bad_request(e),
+    };
+    let expires_at = match request.duration.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(duration) => match crate::permissions::parse_duration(duration) {
+            Some(duration) => Some(chrono::Utc::now().timestamp() + duration.as_secs() as i64),
+            None => return bad_request(format!("Invalid duration: {}", duration)),
+        },
+    };
+
+    match api_tokens::create_token(&request.name, scopes, user.user_id, expires_at) {
+        Ok((token, secret)) => {
+            audit::record(
+                AuditEntry::web(&user, "api_token.create", &token.id).after(token.info_json()),
+            );
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "token": token.info_json(),
+                "secret": secret
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+#[delete("/api/apitokens/{id}")]
+pub async fn delete_token(user: WebUser, id: web::Path<String>) -> impl Responder {
+    // Tokens act as their creator, so any guild admin may manage their own
+    if let Err(response) = user.visible_guilds(Permission::Admin).await {
+        return response;
+    }
+
+    let token = match api_tokens::load_token(&id) {
+        Ok(Some(token)) if token.managed_by(user.user_id) => token,
+        // Other users' tokens are reported as missing, so their ids can't be probed
+        Ok(_) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Token not found"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": e.to_string()
+            }));
+        }
+    };
+    match api_tokens::delete_token(&token.id) {
+        Ok(_) => {
+            audit::record(
+                AuditEntry::web(&user, "api_token.revoke", &token.id).before(token.info_json()),
+            );
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "id": token.id
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
@@ -0,0 +1,108 @@
+use crate::audit::{self, AuditEntry, AuditFilter, AuditSource};
+use crate::permissions::Permission;
+use crate::web::auth::WebUser;
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 200;
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+    pub action: Option<String>,
+    pub actor_id: Option<String>,
+    pub guild_id: Option<String>,
+    pub source: Option<String>,
+    pub target: Option<String>,
+}
+
+fn bad_request(error: impl ToString) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": error.to_string()
+    }))
+}
+
+fn parse_optional_id(id: Option<&str>) -> Result<Option<u64>, String> {
+    match id.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(id) => id
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("Invalid id: {}", id)),
+    }
+}
+
+// Ids are sent as strings since Discord snowflakes don't fit in a JS number
+fn entry_json(id: &str, entry: &AuditEntry) -> serde_json::Value {
+    serde_json::json!({
+        "id": id,
+        "timestamp": entry.timestamp,
+        "source": entry.source.as_str(),
+        "actor_id": entry.actor_id.map(|id| id.to_string()),
+        "actor": entry.actor,
+        "action": entry.action,
+        "target": entry.target,
+        "guild_id": entry.guild_id.map(|id| id.to_string()),
+        "before": entry.before,
+        "after": entry.after,
+    })
+}
+
+#[get("/api/audit")]
+pub async fn get_audit(user: WebUser, query: web::Query<AuditQuery>) -> impl Responder {
+    let query = query.into_inner();
+    let (actor_id, guild_id) = match (
+        parse_optional_id(query.actor_id.as_deref()),
+        parse_optional_id(query.guild_id.as_deref()),
+    ) {
+        (Ok(actor_id), Ok(guild_id)) => (actor_id, guild_id),
+        (Err(e), _) | (_, Err(e)) => return bad_request(e),
+    };
+    // Admins of a guild may read its changes, the whole log spans every guild and is the owner's
+    let allowed = match guild_id {
+        Some(_) => user.require(guild_id, Permission::Admin).await,
+        None => user.require_owner(),
+    };
+    if let Err(response) = allowed {
+        return response;
+    }
+    let source = match query.source.as_deref() {
+        None | Some("") => None,
+        Some(source) => match source.parse::<AuditSource>() {
+            Ok(source) => Some(source),
+            Err(e) => return bad_request(e),
+        },
+    };
+
+    let filter = AuditFilter {
+        action: query
+            .action
+            .map(|action| action.trim().to_lowercase())
+            .filter(|action| !action.is_empty()),
+        actor_id,
+        target: query.target.filter(|target| !target.is_empty()),
+        guild_id,
+        source,
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    match audit::query(&filter, query.cursor.as_deref(), limit) {
+        Ok((entries, next_cursor)) => {
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|(id, entry)| entry_json(id, entry))
+                .collect();
+            HttpResponse::Ok().json(serde_json::json!({
+                "entries": entries,
+                "count": entries.len(),
+                "next_cursor": next_cursor
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
@@ -0,0 +1,217 @@
+use crate::web::auth::{self, WebUser};
+use actix_web::cookie::time;
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
+use serde::Deserialize;
+
+const STATE_COOKIE: &str = "oauth_state";
+const DISCORD_API: &str = "https://discord.com/api/v10";
+
+#[derive(Deserialize)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TokenLoginRequest {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct DiscordUser {
+    id: String,
+    username: String,
+    global_name: Option<String>,
+    avatar: Option<String>,
+}
+
+struct OAuthConfig {
+    client_id: &'static str,
+    client_secret: &'static str,
+    redirect_uri: String,
+}
+
+fn oauth_config() -> Option<OAuthConfig> {
+    Some(OAuthConfig {
+        client_id: crate::env::DISCORD_CLIENT_ID.as_deref()?,
+        client_secret: crate::env::DISCORD_CLIENT_SECRET.as_deref()?,
+        redirect_uri: format!("{}/api/auth/callback", crate::env::WEB_BASE_URL.as_deref()?),
+    })
+}
+
+fn redirect(location: &str) -> actix_web::HttpResponseBuilder {
+    let mut response = HttpResponse::Found();
+    response.insert_header((header::LOCATION, location.to_string()));
+    response
+}
+
+async fn fetch_discord_user(code: &str, config: &OAuthConfig) -> Result<DiscordUser, crate::Error> {
+    let client = crate::HTTP_CLIENT.get().unwrap();
+    let token: TokenResponse = client
+        .post(format!("{}/oauth2/token", DISCORD_API))
+        .basic_auth(config.client_id, Some(config.client_secret))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", config.redirect_uri.as_str()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let user = client
+        .get(format!("{}/users/@me", DISCORD_API))
+        .bearer_auth(token.access_token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(user)
+}
+
+#[get("/api/auth/me")]
+pub async fn me(user: Option<WebUser>) -> impl Responder {
+    match user {
+        Some(user) => HttpResponse::Ok().json(serde_json::json!({
+            "authenticated": true,
+            "user_id": user.user_id.to_string(),
+            "username": user.username,
+            "avatar": user.avatar,
+            "scopes": user.scopes,
+        })),
+        None => HttpResponse::Ok().json(serde_json::json!({
+            "authenticated": false,
+            "oauth_enabled": oauth_config().is_some(),
+            "token_login_enabled": crate::env::WEB_ADMIN_TOKEN.is_some(),
+        })),
+    }
+}
+
+#[get("/api/auth/login")]
+pub async fn login() -> impl Responder {
+    let Some(config) = oauth_config() else {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Discord login isn't configured"
+        }));
+    };
+
+    // Checked in the callback so a login can't be started on someone else's behalf
+    let state = uuid::Uuid::new_v4().simple().to_string();
+    let location = format!(
+        "https://discord.com/oauth2/authorize?client_id={}&redirect_uri={}&response_type=code&scope=identify&state={}",
+        config.client_id,
+        urlencoding::encode(&config.redirect_uri),
+        state
+    );
+    redirect(&location)
+        .cookie(auth::build_cookie(
+            STATE_COOKIE,
+            state,
+            time::Duration::minutes(10),
+        ))
+        .finish()
+}
+
+#[get("/api/auth/callback")]
+pub async fn callback(req: HttpRequest, query: web::Query<CallbackQuery>) -> impl Responder {
+    let Some(config) = oauth_config() else {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Discord login isn't configured"
+        }));
+    };
+
+    let expected_state = req
+        .cookie(STATE_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+    let (Some(code), Some(state), Some(expected_state)) = (
+        query.code.as_deref(),
+        query.state.as_deref(),
+        expected_state,
+    ) else {
+        if let Some(ref error) = query.error {
+            log::warn!("Discord login was cancelled: {}", error);
+        }
+        return redirect("/?login=failed").finish();
+    };
+    if !auth::constant_time_eq(state, &expected_state) {
+        log::warn!("Discord login rejected, state didn't match");
+        return redirect("/?login=failed").finish();
+    }
+
+    let user = match fetch_discord_user(code, &config).await {
+        Ok(user) => user,
+        Err(e) => {
+            log::error!("Failed to complete Discord login: {}", e);
+            return redirect("/?login=failed").finish();
+        }
+    };
+    let Ok(user_id) = user.id.parse::<u64>() else {
+        return redirect("/?login=failed").finish();
+    };
+    let username = user.global_name.unwrap_or(user.username);
+
+    match auth::create_session(user_id, &username, user.avatar) {
+        Ok(session_id) => {
+            log::info!("{} logged into the dashboard", username);
+            redirect("/")
+                .cookie(auth::session_cookie(session_id))
+                .cookie(auth::removal_cookie(STATE_COOKIE))
+                .finish()
+        }
+        Err(e) => {
+            log::error!("Failed to create session: {}", e);
+            redirect("/?login=failed").finish()
+        }
+    }
+}
+
+/// Log in as the bot author with the static admin token, meant for local development
+#[post("/api/auth/token")]
+pub async fn token_login(request: web::Json<TokenLoginRequest>) -> impl Responder {
+    let Some(ref admin_token) = *crate::env::WEB_ADMIN_TOKEN else {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Token login isn't enabled"
+        }));
+    };
+    if !auth::constant_time_eq(request.token.trim(), admin_token) {
+        log::warn!("Rejected dashboard login with an invalid admin token");
+        return HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Invalid token"
+        }));
+    }
+
+    match auth::create_session(*crate::env::AUTHOR_ID, "admin", None) {
+        Ok(session_id) => HttpResponse::Ok()
+            .cookie(auth::session_cookie(session_id))
+            .json(serde_json::json!({
+                "success": true
+            })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+#[post("/api/auth/logout")]
+pub async fn logout(req: HttpRequest) -> impl Responder {
+    if let Some(cookie) = req.cookie(auth::SESSION_COOKIE)
+        && let Err(e) = auth::delete_session(cookie.value())
+    {
+        log::error!("Failed to delete session: {}", e);
+    }
+    HttpResponse::Ok()
+        .cookie(auth::removal_cookie(auth::SESSION_COOKIE))
+        .json(serde_json::json!({
+            "success": true
+        }))
+}
@@ -0,0 +1,85 @@
+use crate::audit::{self, AuditEntry};
+use crate::backup::{self, Export};
+use crate::web::auth::WebUser;
+use actix_web::{HttpResponse, Responder, get, post, web};
+use futures::StreamExt;
+
+// Well above any export we expect, the JSON extractor's default limit is far too small
+const MAX_IMPORT_BYTES: usize = 256 * 1024 * 1024;
+
+fn server_error(error: impl ToString) -> HttpResponse {
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": error.to_string()
+    }))
+}
+
+// Exports contain every guild's data and API token hashes, so only the bot owner may touch them
+#[get("/api/backup/export")]
+pub async fn export(user: WebUser) -> impl Responder {
+    if let Err(response) = user.require_owner() {
+        return response;
+    }
+
+    let export = match tokio::task::spawn_blocking(|| backup::export(crate::db::get_db()?)).await {
+        Ok(Ok(export)) => export,
+        Ok(Err(e)) => return server_error(e),
+        Err(e) => return server_error(e),
+    };
+    audit::record(AuditEntry::web(&user, "backup.export", "database"));
+
+    let name = format!("export-{}.json", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    HttpResponse::Ok()
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", name),
+        ))
+        .json(export)
+}
+
+/// Replace the database contents with an uploaded export
+#[post("/api/backup/import")]
+pub async fn import(user: WebUser, mut payload: web::Payload) -> impl Responder {
+    if let Err(response) = user.require_owner() {
+        return response;
+    }
+
+    let mut body = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => return server_error(e),
+        };
+        if body.len() + chunk.len() > MAX_IMPORT_BYTES {
+            return HttpResponse::PayloadTooLarge().json(serde_json::json!({
+                "error": "Export is too large"
+            }));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let export: Export = match serde_json::from_slice(&body) {
+        Ok(export) => export,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Invalid export: {}", e)
+            }));
+        }
+    };
+
+    let result =
+        tokio::task::spawn_blocking(move || backup::import(crate::db::get_db()?, &export)).await;
+    match result {
+        Ok(Ok(summary)) => {
+            audit::record(AuditEntry::web(&user, "backup.import", "database").after(&summary));
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "summary": summary,
+            }))
+        }
+        // Mostly an export this build can't restore
+        Ok(Err(e)) => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+        Err(e) => server_error(e),
+    }
+}
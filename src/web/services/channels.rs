@@ -1,3 +1,5 @@
+use crate::permissions::Permission;
+use crate::web::auth::WebUser;
 use actix_web::{HttpResponse, Responder, get};
 use serde::{Deserialize, Serialize};
 
@@ -17,13 +19,66 @@ pub struct CategoryInfo {
     pub guild_name: String,
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct RoleInfo {
+    pub id: String,
+    pub name: String,
+    pub guild_id: String,
+    pub guild_name: String,
+}
+
 // Helper function to get Discord HTTP client
 fn get_discord_http() -> Option<std::sync::Arc<poise::serenity_prelude::Http>> {
     crate::DISCORD_HTTP.get().cloned()
 }
 
+/// `None` is the bot owner, who sees every guild
+fn is_visible(visible: &Option<Vec<u64>>, guild_id: u64) -> bool {
+    visible
+        .as_ref()
+        .is_none_or(|guilds| guilds.contains(&guild_id))
+}
+
+/// Make sure a channel or category exists and belongs to the guild
+pub async fn check_channel(guild_id: u64, channel_id: u64) -> Result<(), String> {
+    let http = crate::DISCORD_HTTP
+        .get()
+        .ok_or("Discord client not ready")?;
+    let channel = http
+        .get_channel(channel_id.into())
+        .await
+        .map_err(|e| format!("Failed to get channel: {}", e))?;
+    match channel.guild() {
+        Some(channel) if channel.guild_id.get() == guild_id => Ok(()),
+        _ => Err("Channel does not belong to this guild".to_string()),
+    }
+}
+
+// Pickers for every form that takes a channel or role, so the lowest level that can fill one in
+/// Make sure a role exists in the guild and can be mentioned
+pub async fn check_role(guild_id: u64, role_id: u64) -> Result<(), String> {
+    let http = crate::DISCORD_HTTP
+        .get()
+        .ok_or("Discord client not ready")?;
+    let roles = http
+        .get_guild_roles(guild_id.into())
+        .await
+        .map_err(|e| format!("Failed to get roles: {}", e))?;
+    // @everyone shares the guild's id and is mentioned with `mention_here` instead
+    if role_id != guild_id && roles.iter().any(|role| role.id.get() == role_id) {
+        Ok(())
+    } else {
+        Err("Role does not belong to this guild".to_string())
+    }
+}
+
 #[get("/api/channels")]
-pub async fn get_channels() -> impl Responder {
+pub async fn get_channels(user: WebUser) -> impl Responder {
+    let visible = match user.visible_guilds(Permission::Trusted).await {
+        Ok(guilds) => guilds,
+        Err(response) => return response,
+    };
+
     let http_option = get_discord_http();
     if http_option.is_none() {
         return HttpResponse::ServiceUnavailable().json(serde_json::json!({
@@ -39,6 +94,9 @@ pub async fn get_channels() -> impl Responder {
     match http.get_guilds(None, None).await {
         Ok(guilds) => {
             for guild_info in guilds {
+                if !is_visible(&visible, guild_info.id.get()) {
+                    continue;
+                }
                 // Get channels for this guild
                 match http.get_channels(guild_info.id).await {
                     Ok(guild_channels) => {
@@ -86,3 +144,56 @@ pub async fn get_channels() -> impl Responder {
         "categories": categories
     }))
 }
+
+#[get("/api/roles")]
+pub async fn get_roles(user: WebUser) -> impl Responder {
+    let visible = match user.visible_guilds(Permission::Trusted).await {
+        Ok(guilds) => guilds,
+        Err(response) => return response,
+    };
+
+    let Some(http) = get_discord_http() else {
+        return HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "error": "Discord client not ready"
+        }));
+    };
+
+    let guilds = match http.get_guilds(None, None).await {
+        Ok(guilds) => guilds,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to get guilds: {}", e)
+            }));
+        }
+    };
+
+    let mut roles = Vec::new();
+    for guild_info in guilds {
+        if !is_visible(&visible, guild_info.id.get()) {
+            continue;
+        }
+        match http.get_guild_roles(guild_info.id).await {
+            Ok(guild_roles) => {
+                for role in guild_roles {
+                    // @everyone shares the guild's id and can't be pinged as a role
+                    if role.id.get() == guild_info.id.get() {
+                        continue;
+                    }
+                    roles.push(RoleInfo {
+                        id: role.id.to_string(),
+                        name: role.name.clone(),
+                        guild_id: guild_info.id.to_string(),
+                        guild_name: guild_info.name.clone(),
+                    });
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to get roles for guild {}: {:?}", guild_info.id, e);
+            }
+        }
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "roles": roles
+    }))
+}
@@ -0,0 +1,142 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::events::{self, LiveEvent};
+use crate::web::auth::WebUser;
+
+// Proxies tend to drop connections that stay silent for too long
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    /// Minimum log level, e.g. `warn` also sends errors
+    pub level: Option<String>,
+    /// Only logs from modules containing this, e.g. `streams`
+    pub module: Option<String>,
+    /// Comma separated, `log` and/or `command` (both by default)
+    pub kinds: Option<String>,
+}
+
+struct EventFilter {
+    level: log::LevelFilter,
+    module: Option<String>,
+    logs: bool,
+    commands: bool,
+}
+
+impl EventFilter {
+    fn from_query(query: &EventsQuery) -> Result<Self, String> {
+        let level = match query.level.as_deref().map(str::trim) {
+            None | Some("") => log::LevelFilter::Trace,
+            Some(level) => log::LevelFilter::from_str(level)
+                .map_err(|_| format!("Invalid log level: {}", level))?,
+        };
+        let kinds: Vec<String> = query
+            .kinds
+            .as_deref()
+            .unwrap_or("log,command")
+            .split(',')
+            .map(|kind| kind.trim().to_lowercase())
+            .filter(|kind| !kind.is_empty())
+            .collect();
+        if let Some(kind) = kinds
+            .iter()
+            .find(|kind| !matches!(kind.as_str(), "log" | "command"))
+        {
+            return Err(format!("Unknown event kind: {}", kind));
+        }
+
+        Ok(EventFilter {
+            level,
+            module: query
+                .module
+                .as_deref()
+                .map(|module| module.trim().to_lowercase())
+                .filter(|module| !module.is_empty()),
+            logs: kinds.iter().any(|kind| kind == "log"),
+            commands: kinds.iter().any(|kind| kind == "command"),
+        })
+    }
+
+    fn matches(&self, event: &LiveEvent) -> bool {
+        match event {
+            LiveEvent::Log(record) => {
+                self.logs
+                    && log::Level::from_str(&record.level).is_ok_and(|level| level <= self.level)
+                    && self
+                        .module
+                        .as_ref()
+                        .is_none_or(|module| record.module.to_lowercase().contains(module))
+            }
+            LiveEvent::Command(_) => self.commands,
+        }
+    }
+}
+
+/// Server-Sent Events stream of log records and command executions.
+/// Starts with the recent log backlog, then sends events as they happen.
+#[get("/api/events")]
+pub async fn stream_events(user: WebUser, query: web::Query<EventsQuery>) -> impl Responder {
+    // Process wide logs and every guild's invocations, so only for the bot owner
+    if let Err(response) = user.require_owner() {
+        return response;
+    }
+    let filter = match EventFilter::from_query(&query) {
+        Ok(filter) => filter,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": e
+            }));
+        }
+    };
+
+    // Subscribe before reading the backlog so nothing falls between the two
+    let receiver = events::subscribe();
+    let backlog: VecDeque<LiveEvent> = events::recent_logs()
+        .into_iter()
+        .map(LiveEvent::Log)
+        .filter(|event| filter.matches(event))
+        .collect();
+    let ticker = tokio::time::interval_at(
+        tokio::time::Instant::now() + KEEPALIVE_INTERVAL,
+        KEEPALIVE_INTERVAL,
+    );
+
+    let stream = futures::stream::unfold(
+        (backlog, receiver, ticker, filter),
+        |(mut backlog, mut receiver, mut ticker, filter)| async move {
+            let event = match backlog.pop_front() {
+                Some(event) => event,
+                None => loop {
+                    tokio::select! {
+                        _ = ticker.tick() => {
+                            let chunk = web::Bytes::from_static(b": ping\n\n");
+                            return Some((
+                                Ok::<_, std::convert::Infallible>(chunk),
+                                (backlog, receiver, ticker, filter),
+                            ));
+                        }
+                        received = receiver.recv() => match received {
+                            Ok(event) if filter.matches(&event) => break event,
+                            Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => return None,
+                        },
+                    }
+                },
+            };
+
+            let payload = serde_json::to_string(&event).unwrap_or_default();
+            let chunk = web::Bytes::from(format!("data: {}\n\n", payload));
+            Some((Ok(chunk), (backlog, receiver, ticker, filter)))
+        },
+    );
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
+}
@@ -1,7 +1,14 @@
-use actix_web::{HttpResponse, Responder, get};
-use redb::ReadableDatabase;
+use crate::command_history::{self, HistoryFilter};
+use crate::permissions::Permission;
+use crate::web::auth::WebUser;
+use actix_web::{HttpResponse, Responder, get, web};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
 use std::io::{BufRead, BufReader};
 
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+const MAX_HISTORY_LIMIT: usize = 500;
+
 #[get("/api/stats")]
 pub async fn stats() -> impl Responder {
     // Count tags
@@ -26,48 +33,15 @@ pub async fn stats() -> impl Responder {
         .unwrap_or(0);
 
     // Count commands in last 24 hours
-    let commands_24h = {
-        let db = match crate::KV_DATABASE.get() {
-            Some(db) => db,
-            None => {
-                return HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": "Database not initialized"
-                }));
-            }
-        };
-
-        let read_txn = db.begin_read().unwrap();
-        match read_txn.open_table(crate::HISTORY) {
-            Ok(table) => {
-                let now = chrono::Utc::now();
-                let cutoff = now - chrono::Duration::hours(24);
-
-                table
-                    .range::<&str>(..)
-                    .unwrap()
-                    .filter_map(|item| {
-                        let (_key, value) = item.ok()?;
-                        let value_str = value.value();
-                        let entry: serde_json::Value = serde_json::from_str(value_str).ok()?;
-                        let timestamp_str = entry.get("timestamp")?.as_str()?;
-                        let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp_str).ok()?;
-                        if timestamp.with_timezone(&chrono::Utc) >= cutoff {
-                            Some(())
-                        } else {
-                            None
-                        }
-                    })
-                    .count()
-            }
-            Err(_) => 0,
-        }
-    };
+    let commands_24h =
+        command_history::count_since(Utc::now() - chrono::Duration::hours(24)).unwrap_or(0);
 
     HttpResponse::Ok().json(serde_json::json!({
         "servers": server_count,
         "commands_24h": commands_24h,
         "tags": tag_count,
-        "emojis": emoji_count
+        "emojis": emoji_count,
+        "streams": crate::streams::health::snapshot()
     }))
 }
 
@@ -79,7 +53,11 @@ pub async fn health() -> impl Responder {
 }
 
 #[get("/api/logs")]
-pub async fn logs() -> impl Responder {
+pub async fn logs(user: WebUser) -> impl Responder {
+    if let Err(response) = user.require(None, Permission::Admin).await {
+        return response;
+    }
+
     match std::fs::File::open("bot.log") {
         Ok(file) => {
             let reader = BufReader::new(file);
@@ -106,56 +84,80 @@ pub async fn logs() -> impl Responder {
     }
 }
 
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+    pub user: Option<String>,
+    pub command: Option<String>,
+    pub guild: Option<String>,
+    pub success: Option<bool>,
+    /// RFC 3339 timestamps
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+fn parse_time(time: Option<&str>) -> Result<Option<DateTime<Utc>>, String> {
+    match time.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(time) => DateTime::parse_from_rfc3339(time)
+            .map(|time| Some(time.with_timezone(&Utc)))
+            .map_err(|_| format!("Invalid timestamp: {}", time)),
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
 #[get("/api/history")]
-pub async fn history() -> impl Responder {
-    let db = match crate::KV_DATABASE.get() {
-        Some(db) => db,
-        None => {
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Database not initialized"
-            }));
-        }
+pub async fn history(user: WebUser, query: web::Query<HistoryQuery>) -> impl Responder {
+    // Invocations include their arguments, so admins only see their own guilds
+    let guilds = match user.visible_guilds(Permission::Admin).await {
+        Ok(guilds) => guilds,
+        Err(response) => return response,
     };
-
-    let read_txn = match db.begin_read() {
-        Ok(txn) => txn,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Failed to read database: {}", e)
+    let query = query.into_inner();
+    let (since, until) = match (
+        parse_time(query.since.as_deref()),
+        parse_time(query.until.as_deref()),
+    ) {
+        (Ok(since), Ok(until)) => (since, until),
+        (Err(e), _) | (_, Err(e)) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": e
             }));
         }
     };
 
-    let history_entries: Vec<serde_json::Value> = {
-        let table = match read_txn.open_table(crate::HISTORY) {
-            Ok(t) => t,
-            Err(_) => {
-                return HttpResponse::Ok().json(serde_json::json!({
-                    "history": [],
-                    "count": 0
-                }));
-            }
-        };
-
-        table
-            .range::<&str>(..)
-            .unwrap()
-            .filter_map(|item| {
-                let (_key, value) = item.ok()?;
-                let value_str = value.value();
-                serde_json::from_str(value_str).ok()
-            })
-            .collect::<Vec<_>>()
-            .into_iter()
-            .rev()
-            .take(100)
-            .collect()
+    let filter = HistoryFilter {
+        user: non_empty(query.user),
+        command: non_empty(query.command)
+            .map(|command| command.trim_start_matches('/').to_lowercase()),
+        guild: non_empty(query.guild),
+        guilds,
+        success: query.success,
+        since,
+        until,
     };
-
-    HttpResponse::Ok().json(serde_json::json!({
-        "history": history_entries,
-        "count": history_entries.len()
-    }))
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .clamp(1, MAX_HISTORY_LIMIT);
+
+    match command_history::query(&filter, non_empty(query.cursor).as_deref(), limit) {
+        Ok((history, next_cursor)) => HttpResponse::Ok().json(serde_json::json!({
+            "history": history,
+            "count": history.len(),
+            "next_cursor": next_cursor
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to read history: {}", e)
+        })),
+    }
 }
 
 #[get("/api/commands")]
@@ -0,0 +1,16 @@
+use actix_web::{HttpResponse, Responder, get};
+
+use crate::permissions::Permission;
+use crate::web::auth::WebUser;
+
+/// Prometheus scrape endpoint, use an API token with the `metrics:read` scope
+#[get("/metrics")]
+pub async fn metrics(user: WebUser) -> impl Responder {
+    if let Err(response) = user.require(None, Permission::Admin).await {
+        return response;
+    }
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(crate::metrics::render().await)
+}
@@ -1,5 +1,14 @@
+pub mod api_tokens;
+pub mod audit;
+pub mod auth;
 pub mod aydy;
+pub mod backup;
 pub mod channels;
+pub mod events;
 pub mod general;
+pub mod metrics;
+pub mod music;
+pub mod permissions;
+pub mod streams;
 pub mod tags;
 pub mod tickets;
@@ -0,0 +1,317 @@
+use actix_web::{HttpResponse, Responder, get, post, put, web};
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::music::{control, history};
+use crate::permissions::Permission;
+use crate::web::auth::WebUser;
+
+#[derive(Deserialize, Serialize)]
+pub struct QueueRequest {
+    pub query: String,
+    pub requested_by: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct VolumeRequest {
+    pub volume: f32,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct TopQuery {
+    pub period: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ReorderRequest {
+    pub from: usize,
+    pub to: usize,
+}
+
+fn parse_guild_id(guild_id: &str) -> Option<serenity::GuildId> {
+    guild_id
+        .parse::<u64>()
+        .ok()
+        .filter(|id| *id != 0)
+        .map(serenity::GuildId::new)
+}
+
+fn invalid_guild() -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": "Invalid guild id"
+    }))
+}
+
+/// Viewing or controlling playback from the dashboard needs trusted in the guild
+async fn require_control(user: &WebUser, guild_id: serenity::GuildId) -> Result<(), HttpResponse> {
+    user.require(Some(guild_id.get()), Permission::Trusted)
+        .await
+}
+
+fn control_response(result: Result<(), crate::Error>) -> HttpResponse {
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true
+        })),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+#[get("/api/music/{guild_id}")]
+pub async fn now_playing(user: WebUser, guild_id: web::Path<String>) -> impl Responder {
+    let Some(guild_id) = parse_guild_id(&guild_id) else {
+        return invalid_guild();
+    };
+    if let Err(response) = require_control(&user, guild_id).await {
+        return response;
+    }
+
+    HttpResponse::Ok().json(control::playback_state(guild_id).await)
+}
+
+#[get("/api/music/{guild_id}/queue")]
+pub async fn get_queue(user: WebUser, guild_id: web::Path<String>) -> impl Responder {
+    let Some(guild_id) = parse_guild_id(&guild_id) else {
+        return invalid_guild();
+    };
+    if let Err(response) = require_control(&user, guild_id).await {
+        return response;
+    }
+
+    let state = control::playback_state(guild_id).await;
+    HttpResponse::Ok().json(serde_json::json!({
+        "queue": state.queue,
+        "count": state.queue.len()
+    }))
+}
+
+#[post("/api/music/{guild_id}/queue")]
+pub async fn add_to_queue(
+    user: WebUser,
+    guild_id: web::Path<String>,
+    request: web::Json<QueueRequest>,
+) -> impl Responder {
+    let Some(guild_id) = parse_guild_id(&guild_id) else {
+        return invalid_guild();
+    };
+    if let Err(response) = require_control(&user, guild_id).await {
+        return response;
+    }
+
+    if request.query.trim().is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Query is required"
+        }));
+    }
+
+    let requested_by = request.requested_by.as_deref().unwrap_or(&user.username);
+    match control::enqueue(guild_id, request.query.clone(), requested_by).await {
+        Ok(track) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "track": track
+        })),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+#[post("/api/music/{guild_id}/skip")]
+pub async fn skip(user: WebUser, guild_id: web::Path<String>) -> impl Responder {
+    let Some(guild_id) = parse_guild_id(&guild_id) else {
+        return invalid_guild();
+    };
+    if let Err(response) = require_control(&user, guild_id).await {
+        return response;
+    }
+
+    control_response(control::skip(guild_id).await)
+}
+
+#[post("/api/music/{guild_id}/pause")]
+pub async fn pause(user: WebUser, guild_id: web::Path<String>) -> impl Responder {
+    let Some(guild_id) = parse_guild_id(&guild_id) else {
+        return invalid_guild();
+    };
+    if let Err(response) = require_control(&user, guild_id).await {
+        return response;
+    }
+
+    control_response(control::pause(guild_id).await)
+}
+
+#[post("/api/music/{guild_id}/resume")]
+pub async fn resume(user: WebUser, guild_id: web::Path<String>) -> impl Responder {
+    let Some(guild_id) = parse_guild_id(&guild_id) else {
+        return invalid_guild();
+    };
+    if let Err(response) = require_control(&user, guild_id).await {
+        return response;
+    }
+
+    control_response(control::resume(guild_id).await)
+}
+
+#[put("/api/music/{guild_id}/volume")]
+pub async fn set_volume(
+    user: WebUser,
+    guild_id: web::Path<String>,
+    request: web::Json<VolumeRequest>,
+) -> impl Responder {
+    let Some(guild_id) = parse_guild_id(&guild_id) else {
+        return invalid_guild();
+    };
+    if let Err(response) = require_control(&user, guild_id).await {
+        return response;
+    }
+
+    match control::set_volume(guild_id, request.volume).await {
+        Ok(volume) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "volume": volume
+        })),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+#[put("/api/music/{guild_id}/queue/reorder")]
+pub async fn reorder_queue(
+    user: WebUser,
+    guild_id: web::Path<String>,
+    request: web::Json<ReorderRequest>,
+) -> impl Responder {
+    let Some(guild_id) = parse_guild_id(&guild_id) else {
+        return invalid_guild();
+    };
+    if let Err(response) = require_control(&user, guild_id).await {
+        return response;
+    }
+
+    control_response(control::reorder(guild_id, request.from, request.to).await)
+}
+
+#[get("/api/music/{guild_id}/history")]
+pub async fn get_history(
+    user: WebUser,
+    guild_id: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+) -> impl Responder {
+    let Some(guild_id) = parse_guild_id(&guild_id) else {
+        return invalid_guild();
+    };
+    if let Err(response) = require_control(&user, guild_id).await {
+        return response;
+    }
+
+    let limit = query.limit.unwrap_or(100).min(1000);
+    match history::recent_plays(guild_id.get(), limit) {
+        Ok(plays) => HttpResponse::Ok().json(serde_json::json!({
+            "history": plays,
+            "count": plays.len()
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+#[get("/api/music/{guild_id}/top")]
+pub async fn get_top(
+    user: WebUser,
+    guild_id: web::Path<String>,
+    query: web::Query<TopQuery>,
+) -> impl Responder {
+    let Some(guild_id) = parse_guild_id(&guild_id) else {
+        return invalid_guild();
+    };
+    if let Err(response) = require_control(&user, guild_id).await {
+        return response;
+    }
+
+    let period = match query
+        .period
+        .as_deref()
+        .unwrap_or("week")
+        .parse::<history::StatsPeriod>()
+    {
+        Ok(period) => period,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": e
+            }));
+        }
+    };
+    let limit = query.limit.unwrap_or(10).min(100);
+
+    match history::load_plays(guild_id.get(), period.cutoff()) {
+        Ok(plays) => HttpResponse::Ok().json(serde_json::json!({
+            "period": period.label(),
+            "total_plays": plays.len(),
+            "tracks": history::top_tracks(&plays, limit),
+            "requesters": history::top_requesters(&plays, limit)
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// Server-Sent Events stream of the guild's playback state.
+/// Sends a snapshot whenever a control action happens, and at least every few seconds
+/// so the track position stays current.
+#[get("/api/music/{guild_id}/events")]
+pub async fn events(user: WebUser, guild_id: web::Path<String>) -> impl Responder {
+    let Some(guild_id) = parse_guild_id(&guild_id) else {
+        return invalid_guild();
+    };
+    if let Err(response) = require_control(&user, guild_id).await {
+        return response;
+    }
+
+    let receiver = control::subscribe();
+    let ticker = tokio::time::interval(Duration::from_secs(2));
+
+    let stream = futures::stream::unfold(
+        (receiver, ticker, None::<control::PlaybackState>),
+        move |(mut receiver, mut ticker, last)| async move {
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    changed = receiver.recv() => match changed {
+                        Ok(id) if id != guild_id.get() => continue,
+                        Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+                    },
+                }
+
+                let state = control::playback_state(guild_id).await;
+                if last.as_ref() == Some(&state) {
+                    continue;
+                }
+
+                let payload = serde_json::to_string(&state).unwrap_or_default();
+                let chunk = web::Bytes::from(format!("data: {}\n\n", payload));
+                return Some((
+                    Ok::<_, std::convert::Infallible>(chunk),
+                    (receiver, ticker, Some(state)),
+                ));
+            }
+        },
+    );
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
+}
@@ -0,0 +1,157 @@
+use crate::audit::{self, AuditEntry};
+use crate::permissions::Permission;
+use crate::permissions::policy::{self, CommandPolicy};
+use crate::web::auth::WebUser;
+use actix_web::{HttpResponse, Responder, delete, get, put, web};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct PoliciesQuery {
+    pub guild_id: String,
+}
+
+// Ids are sent as strings since Discord snowflakes don't fit in a JS number
+#[derive(Deserialize)]
+pub struct PolicyRequest {
+    pub guild_id: String,
+    pub command: String,
+    pub min_permission: Option<String>,
+    #[serde(default)]
+    pub allowed_roles: Vec<String>,
+    #[serde(default)]
+    pub allowed_channels: Vec<String>,
+}
+
+fn parse_ids(ids: &[String]) -> Result<Vec<u64>, String> {
+    ids.iter()
+        .map(|id| id.trim().parse().map_err(|_| format!("Invalid id: {}", id)))
+        .collect()
+}
+
+fn policy_json(policy: &CommandPolicy) -> serde_json::Value {
+    serde_json::json!({
+        "guild_id": policy.guild_id.to_string(),
+        "command": policy.command,
+        "min_permission": policy.min_permission.as_ref().map(Permission::as_str),
+        "allowed_roles": policy.allowed_roles.iter().map(u64::to_string).collect::<Vec<_>>(),
+        "allowed_channels": policy.allowed_channels.iter().map(u64::to_string).collect::<Vec<_>>(),
+    })
+}
+
+fn bad_request(error: impl ToString) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": error.to_string()
+    }))
+}
+
+#[get("/api/permissions/commands")]
+pub async fn get_policies(user: WebUser, query: web::Query<PoliciesQuery>) -> impl Responder {
+    let Ok(guild_id) = query.guild_id.parse::<u64>() else {
+        return bad_request(format!("Invalid guild id: {}", query.guild_id));
+    };
+    if let Err(response) = user.require(Some(guild_id), Permission::Admin).await {
+        return response;
+    }
+
+    match policy::list_policies(guild_id) {
+        Ok(mut policies) => {
+            policies.sort_by(|a, b| a.command.cmp(&b.command));
+            let policies: Vec<_> = policies.iter().map(policy_json).collect();
+            HttpResponse::Ok().json(serde_json::json!({
+                "policies": policies,
+                "count": policies.len(),
+                "commands": policy::command_paths(),
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// Create or replace the policy for a command, an empty policy removes it
+#[put("/api/permissions/commands")]
+pub async fn set_policy(user: WebUser, request: web::Json<PolicyRequest>) -> impl Responder {
+    let Ok(guild_id) = request.guild_id.parse::<u64>() else {
+        return bad_request(format!("Invalid guild id: {}", request.guild_id));
+    };
+    if let Err(response) = user.require(Some(guild_id), Permission::Admin).await {
+        return response;
+    }
+
+    let command = policy::normalize_command(&request.command);
+    if !policy::command_paths().contains(&command) {
+        return bad_request(format!("Unknown command: {}", command));
+    }
+    if policy::is_exempt(&command) {
+        return bad_request(format!("{} can't be restricted", command));
+    }
+
+    let min_permission = match request.min_permission.as_deref() {
+        None | Some("") | Some("none") => None,
+        Some(level) => match level.parse::<Permission>() {
+            Ok(perm) => Some(perm),
+            Err(e) => return bad_request(e),
+        },
+    };
+    let (allowed_roles, allowed_channels) = match (
+        parse_ids(&request.allowed_roles),
+        parse_ids(&request.allowed_channels),
+    ) {
+        (Ok(roles), Ok(channels)) => (roles, channels),
+        (Err(e), _) | (_, Err(e)) => return bad_request(e),
+    };
+
+    let existing = policy::load_policy(guild_id, &command).ok().flatten();
+    let policy = CommandPolicy {
+        guild_id,
+        command,
+        min_permission,
+        allowed_roles,
+        allowed_channels,
+    };
+    match policy::save_policy(&policy) {
+        Ok(_) => {
+            audit::record(
+                AuditEntry::web(&user, "command_policy.update", &policy.command)
+                    .guild(Some(guild_id))
+                    .before(&existing)
+                    .after((!policy.is_empty()).then_some(&policy)),
+            );
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "policy": policy_json(&policy)
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+#[delete("/api/permissions/commands/{guild_id}/{command}")]
+pub async fn delete_policy(user: WebUser, path: web::Path<(u64, String)>) -> impl Responder {
+    let (guild_id, command) = path.into_inner();
+    if let Err(response) = user.require(Some(guild_id), Permission::Admin).await {
+        return response;
+    }
+
+    let command = policy::normalize_command(&command);
+    let existing = policy::load_policy(guild_id, &command).ok().flatten();
+    match policy::delete_policy(guild_id, &command) {
+        Ok(_) => {
+            audit::record(
+                AuditEntry::web(&user, "command_policy.delete", &command)
+                    .guild(Some(guild_id))
+                    .before(&existing),
+            );
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "command": command
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
@@ -0,0 +1,363 @@
+use crate::audit::{self, AuditEntry};
+use crate::permissions::Permission;
+use crate::streams::{self, LiveStreamState, StreamFollow, provider};
+use crate::web::auth::WebUser;
+use crate::web::services::channels::{check_channel, check_role};
+use actix_web::{HttpResponse, Responder, delete, get, post, put, web};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct StreamsQuery {
+    pub guild_id: Option<String>,
+}
+
+// Ids are sent as strings since Discord snowflakes don't fit in a JS number
+#[derive(Deserialize)]
+pub struct CreateFollowRequest {
+    pub guild_id: String,
+    pub channel_id: String,
+    pub url: String,
+    pub mention_role: Option<String>,
+    #[serde(default)]
+    pub mention_here: bool,
+    pub message: Option<String>,
+    #[serde(default)]
+    pub crosspost: bool,
+}
+
+/// Replaces the editable settings of a follow, the stream itself can't be changed
+#[derive(Deserialize)]
+pub struct UpdateFollowRequest {
+    pub channel_id: String,
+    pub mention_role: Option<String>,
+    #[serde(default)]
+    pub mention_here: bool,
+    pub message: Option<String>,
+    #[serde(default)]
+    pub crosspost: bool,
+}
+
+fn parse_id(id: &str) -> Result<u64, String> {
+    id.trim().parse().map_err(|_| format!("Invalid id: {}", id))
+}
+
+// Empty strings from the form clear the role or message
+fn parse_optional_id(id: Option<&str>) -> Result<Option<u64>, String> {
+    match id.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(id) => parse_id(id).map(Some),
+    }
+}
+
+fn non_empty(text: Option<String>) -> Option<String> {
+    text.filter(|text| !text.trim().is_empty())
+}
+
+fn live_json(state: &LiveStreamState) -> serde_json::Value {
+    serde_json::json!({
+        "message_id": state.message_id.to_string(),
+        "start_time": state.start_time,
+        "first_seen": state.first_seen,
+        "peak_viewers": state.peak_viewers,
+        "channel_name": state.channel_name,
+        "title": state.title,
+        "category": state.category,
+        "url": state.url,
+        "vod_url": state.vod_url,
+    })
+}
+
+fn follow_json(follow: &StreamFollow) -> serde_json::Value {
+    let key = follow.key();
+    let live = streams::live_state(&key).ok().flatten();
+    serde_json::json!({
+        "key": key,
+        "guild_id": follow.guild_id.map(|id| id.to_string()),
+        "user_id": follow.user_id.to_string(),
+        "url": follow.url,
+        "channel_id": follow.channel_id.to_string(),
+        "platform": follow.platform,
+        "channel_name": follow.channel_name,
+        "mention_role": follow.mention_role.map(|id| id.to_string()),
+        "mention_here": follow.mention_here,
+        "message": follow.message,
+        "crosspost": follow.crosspost,
+        "live": live.as_ref().map(live_json),
+    })
+}
+
+/// Scoped follow keys start with the guild id, see `StreamFollow::key`
+fn key_guild(key: &str) -> Option<u64> {
+    key.split('_').next()?.parse().ok()
+}
+
+fn bad_request(error: impl ToString) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": error.to_string()
+    }))
+}
+
+#[get("/api/streams")]
+pub async fn get_streams(user: WebUser, query: web::Query<StreamsQuery>) -> impl Responder {
+    let follows = match query.guild_id.as_deref() {
+        Some(guild_id) => {
+            let guild_id = match parse_id(guild_id) {
+                Ok(guild_id) => guild_id,
+                Err(e) => return bad_request(e),
+            };
+            if let Err(response) = user.require(Some(guild_id), Permission::Trusted).await {
+                return response;
+            }
+            streams::guild_follows(guild_id)
+        }
+        None => match user.visible_guilds(Permission::Trusted).await {
+            Ok(None) => streams::all_follows(),
+            Ok(Some(guilds)) => streams::all_follows().map(|follows| {
+                follows
+                    .into_iter()
+                    .filter(|follow| follow.guild_id.is_some_and(|id| guilds.contains(&id)))
+                    .collect()
+            }),
+            Err(response) => return response,
+        },
+    };
+
+    match follows {
+        Ok(follows) => {
+            let follows: Vec<_> = follows.iter().map(follow_json).collect();
+            let platforms: Vec<_> = provider::providers()
+                .iter()
+                .map(|provider| {
+                    serde_json::json!({
+                        "platform": provider.platform(),
+                        "name": provider.display_name(),
+                    })
+                })
+                .collect();
+            HttpResponse::Ok().json(serde_json::json!({
+                "count": follows.len(),
+                "follows": follows,
+                "platforms": platforms,
+                "health": streams::health::snapshot(),
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+#[get("/api/streams/live")]
+pub async fn get_live_streams(user: WebUser) -> impl Responder {
+    let guilds = match user.visible_guilds(Permission::Trusted).await {
+        Ok(guilds) => guilds,
+        Err(response) => return response,
+    };
+
+    match streams::LIVE_STATES.entries() {
+        Ok(states) => {
+            let live: Vec<serde_json::Value> = states
+                .into_iter()
+                // Follows from before guild scoping have no guild, only the owner sees those
+                .filter(|(key, _)| {
+                    guilds
+                        .as_ref()
+                        .is_none_or(|guilds| key_guild(key).is_some_and(|id| guilds.contains(&id)))
+                })
+                .map(|(key, state)| {
+                    let mut entry = live_json(&state);
+                    entry["key"] = serde_json::json!(key);
+                    entry
+                })
+                .collect();
+            HttpResponse::Ok().json(serde_json::json!({
+                "live": live,
+                "count": live.len()
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+#[post("/api/streams")]
+pub async fn create_follow(
+    user: WebUser,
+    request: web::Json<CreateFollowRequest>,
+) -> impl Responder {
+    let request = request.into_inner();
+    let Some((provider, channel_name)) = provider::parse_stream_url(&request.url) else {
+        return bad_request(format!("Unsupported stream URL: {}", request.url));
+    };
+
+    let (guild_id, channel_id, mention_role) = match (
+        parse_id(&request.guild_id),
+        parse_id(&request.channel_id),
+        parse_optional_id(request.mention_role.as_deref()),
+    ) {
+        (Ok(guild_id), Ok(channel_id), Ok(mention_role)) => (guild_id, channel_id, mention_role),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return bad_request(e),
+    };
+    if let Err(response) = user.require(Some(guild_id), Permission::Trusted).await {
+        return response;
+    }
+    if let Err(e) = check_channel(guild_id, channel_id).await {
+        return bad_request(e);
+    }
+    if let Some(role_id) = mention_role
+        && let Err(e) = check_role(guild_id, role_id).await
+    {
+        return bad_request(e);
+    }
+
+    let follow = StreamFollow {
+        guild_id: Some(guild_id),
+        // Not tied to a Discord user when added from the dashboard
+        user_id: 0,
+        url: request.url,
+        channel_id,
+        platform: provider.platform().to_string(),
+        channel_name,
+        mention_role,
+        mention_here: request.mention_here,
+        message: non_empty(request.message),
+        crosspost: request.crosspost,
+    };
+
+    let before = streams::load_follow(&follow.key()).ok().flatten();
+    match streams::save_follow(&follow) {
+        Ok(_) => {
+            let action = if before.is_some() {
+                "stream.update"
+            } else {
+                "stream.follow"
+            };
+            audit::record(
+                AuditEntry::web(&user, action, &follow.key())
+                    .guild(follow.guild_id)
+                    .before(&before)
+                    .after(&follow),
+            );
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "follow": follow_json(&follow)
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+// Keys may contain slashes (e.g. YouTube `c/name` channels), hence the tail match
+#[put("/api/streams/{key:.*}")]
+pub async fn update_follow(
+    user: WebUser,
+    key: web::Path<String>,
+    request: web::Json<UpdateFollowRequest>,
+) -> impl Responder {
+    let request = request.into_inner();
+    let before = match streams::load_follow(&key) {
+        Ok(Some(follow)) => follow,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Follow not found"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": e.to_string()
+            }));
+        }
+    };
+    if let Err(response) = user.require(before.guild_id, Permission::Trusted).await {
+        return response;
+    }
+
+    let (channel_id, mention_role) = match (
+        parse_id(&request.channel_id),
+        parse_optional_id(request.mention_role.as_deref()),
+    ) {
+        (Ok(channel_id), Ok(mention_role)) => (channel_id, mention_role),
+        (Err(e), _) | (_, Err(e)) => return bad_request(e),
+    };
+    let mut follow = before.clone();
+    if channel_id != follow.channel_id {
+        let Some(guild_id) = follow.guild_id else {
+            return bad_request("Legacy follows must be migrated before moving channels");
+        };
+        if let Err(e) = check_channel(guild_id, channel_id).await {
+            return bad_request(e);
+        }
+        follow.channel_id = channel_id;
+    }
+    if let Some(role_id) = mention_role
+        && mention_role != follow.mention_role
+    {
+        let Some(guild_id) = follow.guild_id else {
+            return bad_request("Legacy follows must be migrated before mentioning a role");
+        };
+        if let Err(e) = check_role(guild_id, role_id).await {
+            return bad_request(e);
+        }
+    }
+    follow.mention_role = mention_role;
+    follow.mention_here = request.mention_here;
+    follow.message = non_empty(request.message);
+    follow.crosspost = request.crosspost;
+
+    // Moving channels changes the key, the old notification isn't carried over
+    let new_key = follow.key();
+    let result = if new_key != *key {
+        streams::delete_follow(&key).and_then(|_| streams::save_follow(&follow))
+    } else {
+        streams::save_follow(&follow)
+    };
+
+    match result {
+        Ok(_) => {
+            audit::record(
+                AuditEntry::web(&user, "stream.update", &key)
+                    .guild(follow.guild_id)
+                    .before(&before)
+                    .after(&follow),
+            );
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "follow": follow_json(&follow)
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+#[delete("/api/streams/{key:.*}")]
+pub async fn delete_follow(user: WebUser, key: web::Path<String>) -> impl Responder {
+    let before = streams::load_follow(&key).ok().flatten();
+    let guild_id = before.as_ref().and_then(|follow| follow.guild_id);
+    if let Err(response) = user.require(guild_id, Permission::Trusted).await {
+        return response;
+    }
+
+    match streams::delete_follow(&key) {
+        Ok(_) => {
+            if let Some(ref follow) = before {
+                audit::record(
+                    AuditEntry::web(&user, "stream.unfollow", &key)
+                        .guild(follow.guild_id)
+                        .before(follow),
+                );
+            }
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "key": key.as_str()
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
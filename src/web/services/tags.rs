@@ -1,3 +1,6 @@
+use crate::audit::{self, AuditEntry};
+use crate::permissions::Permission;
+use crate::web::auth::WebUser;
 use actix_web::{HttpResponse, Responder, delete, get, post, put, web};
 use serde::{Deserialize, Serialize};
 
@@ -26,13 +29,30 @@ pub async fn get_tags() -> impl Responder {
 }
 
 #[post("/api/tags")]
-pub async fn create_tag(tag: web::Json<TagRequest>) -> impl Responder {
+pub async fn create_tag(user: WebUser, tag: web::Json<TagRequest>) -> impl Responder {
+    if let Err(response) = user.require(None, Permission::Admin).await {
+        return response;
+    }
+
+    let before = crate::db::read_entry(crate::TABLE, &tag.key).ok().flatten();
     match crate::db::write_entry(crate::TABLE, &tag.key, &tag.value) {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "key": tag.key,
-            "value": tag.value
-        })),
+        Ok(_) => {
+            let action = if before.is_some() {
+                "tag.update"
+            } else {
+                "tag.create"
+            };
+            audit::record(
+                AuditEntry::web(&user, action, &tag.key)
+                    .before(&before)
+                    .after(&tag.value),
+            );
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "key": tag.key,
+                "value": tag.value
+            }))
+        }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
             "error": e.to_string()
         })),
@@ -40,13 +60,34 @@ pub async fn create_tag(tag: web::Json<TagRequest>) -> impl Responder {
 }
 
 #[put("/api/tags/{key}")]
-pub async fn update_tag(key: web::Path<String>, tag: web::Json<TagRequest>) -> impl Responder {
+pub async fn update_tag(
+    user: WebUser,
+    key: web::Path<String>,
+    tag: web::Json<TagRequest>,
+) -> impl Responder {
+    if let Err(response) = user.require(None, Permission::Admin).await {
+        return response;
+    }
+
+    let before = crate::db::read_entry(crate::TABLE, &key).ok().flatten();
     match crate::db::update_entry(crate::TABLE, &key, &tag.key, &tag.value) {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "key": tag.key,
-            "value": tag.value
-        })),
+        Ok(_) => {
+            // A rename shows up as the old key in `before` and the new one in `after`
+            audit::record(
+                AuditEntry::web(&user, "tag.update", &tag.key)
+                    .before(
+                        before.map(
+                            |value| serde_json::json!({ "key": key.as_str(), "value": value }),
+                        ),
+                    )
+                    .after(serde_json::json!({ "key": tag.key, "value": tag.value })),
+            );
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "key": tag.key,
+                "value": tag.value
+            }))
+        }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
             "error": e.to_string()
         })),
@@ -54,12 +95,20 @@ pub async fn update_tag(key: web::Path<String>, tag: web::Json<TagRequest>) -> i
 }
 
 #[delete("/api/tags/{key}")]
-pub async fn delete_tag(key: web::Path<String>) -> impl Responder {
+pub async fn delete_tag(user: WebUser, key: web::Path<String>) -> impl Responder {
+    if let Err(response) = user.require(None, Permission::Admin).await {
+        return response;
+    }
+
+    let before = crate::db::read_entry(crate::TABLE, &key).ok().flatten();
     match crate::db::delete_entry(crate::TABLE, &key) {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "key": key.as_str()
-        })),
+        Ok(_) => {
+            audit::record(AuditEntry::web(&user, "tag.delete", &key).before(&before));
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "key": key.as_str()
+            }))
+        }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
             "error": e.to_string()
         })),
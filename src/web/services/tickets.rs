@@ -1,3 +1,7 @@
+use crate::audit::{self, AuditEntry};
+use crate::permissions::Permission;
+use crate::web::auth::WebUser;
+use crate::web::services::channels::check_channel;
 use actix_web::{HttpResponse, Responder, delete, get, post, put, web};
 use serde::{Deserialize, Serialize};
 
@@ -12,18 +16,75 @@ pub struct TicketMenuRequest {
     pub modal_config: crate::tickets::ModalConfig,
 }
 
+/// Ticket menus are managed by admins of the guild they're posted in
+async fn require_menu_admin(user: &WebUser, guild_id: &str) -> Result<(), HttpResponse> {
+    user.require(guild_id.parse().ok(), Permission::Admin).await
+}
+
+fn bad_request(error: impl ToString) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": error.to_string()
+    }))
+}
+
+/// Make sure the menu's channel and ticket category belong to its guild
+async fn check_menu_channels(ticket: &TicketMenuRequest) -> Result<(), HttpResponse> {
+    let (Ok(guild_id), Ok(channel_id), Ok(category_id)) = (
+        ticket.guild_id.parse::<u64>(),
+        ticket.channel_id.parse::<u64>(),
+        ticket.category_id.parse::<u64>(),
+    ) else {
+        return Err(bad_request("Invalid guild, channel or category id"));
+    };
+    for id in [channel_id, category_id] {
+        check_channel(guild_id, id).await.map_err(bad_request)?;
+    }
+    Ok(())
+}
+
+fn audit_menu(
+    user: &WebUser,
+    action: &str,
+    before: Option<&crate::tickets::TicketMenu>,
+    after: Option<&crate::tickets::TicketMenu>,
+) {
+    let Some(menu) = after.or(before) else {
+        return;
+    };
+    audit::record(
+        AuditEntry::web(user, action, &menu.id)
+            .guild(menu.guild_id.parse().ok())
+            .before(before)
+            .after(after),
+    );
+}
+
 // Helper function to get Discord HTTP client
 fn get_discord_http() -> Option<std::sync::Arc<poise::serenity_prelude::Http>> {
     crate::DISCORD_HTTP.get().cloned()
 }
 
 #[get("/api/tickets")]
-pub async fn get_tickets() -> impl Responder {
+pub async fn get_tickets(user: WebUser) -> impl Responder {
+    let guilds = match user.visible_guilds(Permission::Admin).await {
+        Ok(guilds) => guilds,
+        Err(response) => return response,
+    };
+
     match crate::tickets::list_ticket_menus() {
-        Ok(menus) => HttpResponse::Ok().json(serde_json::json!({
+        Ok(mut menus) => {
+            if let Some(guilds) = guilds {
+                menus.retain(|menu| {
+                    menu.guild_id
+                        .parse()
+                        .is_ok_and(|guild_id| guilds.contains(&guild_id))
+                });
+            }
+            HttpResponse::Ok().json(serde_json::json!({
             "tickets": menus,
             "count": menus.len()
-        })),
+            }))
+        }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
             "error": format!("Failed to list ticket menus: {}", e)
         })),
@@ -31,9 +92,12 @@ pub async fn get_tickets() -> impl Responder {
 }
 
 #[get("/api/tickets/{id}")]
-pub async fn get_ticket(id: web::Path<String>) -> impl Responder {
+pub async fn get_ticket(user: WebUser, id: web::Path<String>) -> impl Responder {
     match crate::tickets::load_ticket_menu(&id) {
-        Ok(Some(menu)) => HttpResponse::Ok().json(menu),
+        Ok(Some(menu)) => match require_menu_admin(&user, &menu.guild_id).await {
+            Ok(_) => HttpResponse::Ok().json(menu),
+            Err(response) => response,
+        },
         Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
             "error": "Ticket menu not found"
         })),
@@ -44,7 +108,28 @@ pub async fn get_ticket(id: web::Path<String>) -> impl Responder {
 }
 
 #[post("/api/tickets")]
-pub async fn create_ticket(ticket: web::Json<TicketMenuRequest>) -> impl Responder {
+pub async fn create_ticket(user: WebUser, ticket: web::Json<TicketMenuRequest>) -> impl Responder {
+    if let Err(response) = require_menu_admin(&user, &ticket.guild_id).await {
+        return response;
+    }
+    if let Err(response) = check_menu_channels(&ticket).await {
+        return response;
+    }
+    // Ids are global, saving over another guild's menu would take it over
+    match crate::tickets::load_ticket_menu(&ticket.id) {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": "A ticket menu with this id already exists"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to load ticket menu: {}", e)
+            }));
+        }
+    }
+
     let mut menu = crate::tickets::TicketMenu {
         id: ticket.id.clone(),
         channel_id: ticket.channel_id.clone(),
@@ -59,6 +144,8 @@ pub async fn create_ticket(ticket: web::Json<TicketMenuRequest>) -> impl Respond
     // Save to database
     match crate::tickets::save_ticket_menu(&menu) {
         Ok(_) => {
+            audit_menu(&user, "ticket_menu.create", None, Some(&menu));
+
             // Post message to Discord
             if let Some(http) = get_discord_http() {
                 if let Err(e) = menu.post_or_update_with_http(&http).await {
@@ -86,6 +173,7 @@ pub async fn create_ticket(ticket: web::Json<TicketMenuRequest>) -> impl Respond
 
 #[put("/api/tickets/{id}")]
 pub async fn update_ticket(
+    user: WebUser,
     id: web::Path<String>,
     ticket: web::Json<TicketMenuRequest>,
 ) -> impl Responder {
@@ -103,11 +191,21 @@ pub async fn update_ticket(
             }));
         }
     };
+    if let Err(response) = require_menu_admin(&user, &existing_menu.guild_id).await {
+        return response;
+    }
+    if ticket.guild_id != existing_menu.guild_id {
+        return bad_request("Ticket menus can't be moved to another guild");
+    }
+    if let Err(response) = check_menu_channels(&ticket).await {
+        return response;
+    }
 
     let mut menu = crate::tickets::TicketMenu {
-        id: ticket.id.clone(),
+        // The path decides which menu is updated, not the body
+        id: id.into_inner(),
         channel_id: ticket.channel_id.clone(),
-        message_id: existing_menu.message_id,
+        message_id: existing_menu.message_id.clone(),
         guild_id: ticket.guild_id.clone(),
         category_id: ticket.category_id.clone(),
         embed_config: ticket.embed_config.clone(),
@@ -118,6 +216,13 @@ pub async fn update_ticket(
     // Save to database
     match crate::tickets::save_ticket_menu(&menu) {
         Ok(_) => {
+            audit_menu(
+                &user,
+                "ticket_menu.update",
+                Some(&existing_menu),
+                Some(&menu),
+            );
+
             // Update message in Discord
             if let Some(http) = get_discord_http() {
                 if let Err(e) = menu.post_or_update_with_http(&http).await {
@@ -144,12 +249,17 @@ pub async fn update_ticket(
 }
 
 #[delete("/api/tickets/{id}")]
-pub async fn delete_ticket(id: web::Path<String>) -> impl Responder {
+pub async fn delete_ticket(user: WebUser, id: web::Path<String>) -> impl Responder {
     // Load the ticket menu first to get message info
     let menu = crate::tickets::load_ticket_menu(&id);
+    if let Ok(Some(ref ticket_menu)) = menu
+        && let Err(response) = require_menu_admin(&user, &ticket_menu.guild_id).await
+    {
+        return response;
+    }
 
     // Try to delete the Discord message if it exists
-    if let Ok(Some(ticket_menu)) = menu
+    if let Ok(Some(ticket_menu)) = &menu
         && let (Some(message_id), Some(http)) =
             (ticket_menu.message_id.as_ref(), get_discord_http())
         && let Ok(channel_id_u64) = ticket_menu.channel_id.parse::<u64>()
@@ -166,10 +276,18 @@ pub async fn delete_ticket(id: web::Path<String>) -> impl Responder {
 
     // Delete from database
     match crate::tickets::delete_ticket_menu(&id) {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "id": id.as_str()
-        })),
+        Ok(_) => {
+            audit_menu(
+                &user,
+                "ticket_menu.delete",
+                menu.ok().flatten().as_ref(),
+                None,
+            );
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "id": id.as_str()
+            }))
+        }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
             "error": format!("Failed to delete ticket menu: {}", e)
         })),